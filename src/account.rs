@@ -0,0 +1,157 @@
+use crate::client::AnyListClient;
+use crate::error::Result;
+use crate::lists::{transform_user, UserInfo};
+use crate::protobuf::anylist::PbAccountInfoResponse;
+use chrono::{DateTime, Utc};
+use prost::Message;
+use serde_derive::{Deserialize, Serialize};
+
+/// The signed-in user's account and subscription
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    pub(crate) first_name: Option<String>,
+    pub(crate) last_name: Option<String>,
+    pub(crate) email: Option<String>,
+    pub(crate) is_premium_user: bool,
+    pub(crate) subscription_type: Option<i32>,
+    pub(crate) expires_at: Option<DateTime<Utc>>,
+    pub(crate) master_user: Option<UserInfo>,
+    pub(crate) subusers: Vec<UserInfo>,
+}
+
+impl AccountInfo {
+    pub fn first_name(&self) -> Option<&str> {
+        self.first_name.as_deref()
+    }
+
+    pub fn last_name(&self) -> Option<&str> {
+        self.last_name.as_deref()
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    /// Whether the account has AnyList Complete, directly or through a
+    /// family plan
+    pub fn is_premium_user(&self) -> bool {
+        self.is_premium_user
+    }
+
+    /// AnyList's numeric code for the kind of subscription, if any
+    pub fn subscription_type(&self) -> Option<i32> {
+        self.subscription_type
+    }
+
+    /// When the subscription expires, if there is one
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires_at
+    }
+
+    /// The account that pays for the family plan this account belongs to
+    pub fn master_user(&self) -> Option<&UserInfo> {
+        self.master_user.as_ref()
+    }
+
+    /// Accounts sharing this account's family plan
+    pub fn subusers(&self) -> &[UserInfo] {
+        &self.subusers
+    }
+}
+
+impl AnyListClient {
+    /// Get the name, email and subscription of the signed-in account, and
+    /// who shares its family plan
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(client: anylist_rs::AnyListClient) -> anylist_rs::Result<()> {
+    /// let account = client.get_account_info().await?;
+    /// for user in account.subusers() {
+    ///     println!("{}", user.email().unwrap_or(user.user_id()));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_account_info(&self) -> Result<AccountInfo> {
+        let bytes = self.post("data/account/info", vec![]).await?;
+        let response = PbAccountInfoResponse::decode(bytes.as_ref())?;
+        Ok(transform_account_info(response))
+    }
+}
+
+fn transform_account_info(response: PbAccountInfoResponse) -> AccountInfo {
+    // Older responses only carry the expiry as a string
+    let expiration_ms = response.expiration_timestamp_ms.or_else(|| {
+        response
+            .expiration_timestamp_ms_str
+            .as_deref()
+            .and_then(|ms| ms.parse().ok())
+    });
+
+    AccountInfo {
+        first_name: response.first_name,
+        last_name: response.last_name,
+        email: response.email,
+        is_premium_user: response.is_premium_user.unwrap_or(false),
+        subscription_type: response.subscription_type,
+        expires_at: expiration_ms.and_then(DateTime::from_timestamp_millis),
+        master_user: response.master_user.map(transform_user),
+        subusers: response.subusers.into_iter().map(transform_user).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::anylist::PbEmailUserIdPair;
+
+    fn user(user_id: &str, email: &str) -> PbEmailUserIdPair {
+        PbEmailUserIdPair {
+            email: Some(email.to_string()),
+            user_id: Some(user_id.to_string()),
+            full_name: None,
+        }
+    }
+
+    #[test]
+    fn test_transform_account_info() {
+        let info = transform_account_info(PbAccountInfoResponse {
+            first_name: Some("Sam".to_string()),
+            email: Some("sam@example.com".to_string()),
+            is_premium_user: Some(true),
+            subscription_type: Some(2),
+            expiration_timestamp_ms: Some(1_700_000_000_000),
+            master_user: Some(user("user-1", "sam@example.com")),
+            subusers: vec![user("user-2", "alex@example.com")],
+            ..Default::default()
+        });
+
+        assert_eq!(info.first_name(), Some("Sam"));
+        assert_eq!(info.last_name(), None);
+        assert!(info.is_premium_user());
+        assert_eq!(info.subscription_type(), Some(2));
+        assert_eq!(
+            info.expires_at(),
+            DateTime::from_timestamp(1_700_000_000, 0)
+        );
+        assert_eq!(info.master_user().unwrap().user_id(), "user-1");
+        assert_eq!(info.subusers().len(), 1);
+        assert_eq!(info.subusers()[0].email(), Some("alex@example.com"));
+    }
+
+    #[test]
+    fn test_transform_account_info_reads_string_expiry() {
+        let info = transform_account_info(PbAccountInfoResponse {
+            expiration_timestamp_ms_str: Some("1700000000000".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            info.expires_at(),
+            DateTime::from_timestamp(1_700_000_000, 0)
+        );
+        assert!(!info.is_premium_user());
+        assert!(info.master_user().is_none());
+    }
+}
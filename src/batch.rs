@@ -0,0 +1,435 @@
+//! Several list mutations committed in one request.
+//!
+//! A `PBListOperationList` can carry any number of operations, so instead of
+//! one HTTP round trip per item an [`OperationBatch`] collects item, store,
+//! store filter and category changes and posts them together. The
+//! single-operation methods on [`AnyListClient`] (`add_item`,
+//! `cross_off_item`, `create_store`, ...) are one-operation batches.
+//!
+//! Operations are built by the pure builders in [`crate::operations`], the
+//! same ones the other client methods use. Item operations go to
+//! `data/shopping-lists/update`; store, store filter
+//! and category operations only go to `data/shopping-lists/update-v2`. A
+//! batch holding both kinds is committed as one request to each.
+
+use crate::categories::Category;
+use crate::client::AnyListClient;
+use crate::error::Result;
+use crate::lists::ListItem;
+use crate::operations;
+use crate::outcome::OperationOutcome;
+use crate::protobuf::anylist::{PbListOperation, PbListOperationList};
+use crate::stores::{Store, StoreFilter};
+use crate::utils::{current_timestamp, generate_id};
+
+/// Collects list operations and commits them in as few update requests as
+/// possible: one for item operations and one for store, store filter and
+/// category operations.
+///
+/// Methods that create something return it straight away, with the ID it
+/// will have once the batch is committed. Nothing is sent until
+/// [`commit`](OperationBatch::commit).
+///
+/// # Example
+///
+/// ```no_run
+/// # use anylist_rs::AnyListClient;
+/// # async fn example(client: AnyListClient) -> anylist_rs::Result<()> {
+/// let mut batch = client.batch();
+/// for name in ["Milk", "Eggs", "Flour"] {
+///     batch.add_item("list-id", name);
+/// }
+/// batch.cross_off_item("list-id", "bread-item-id");
+///
+/// let outcome = batch.commit().await?;
+/// println!("{} operations processed", outcome.processed_operation_ids().len());
+/// # Ok(())
+/// # }
+/// ```
+pub struct OperationBatch<'a> {
+    client: &'a AnyListClient,
+    user_id: String,
+    /// Item operations, sent to `update`
+    operations: Vec<PbListOperation>,
+    /// Store, store filter and category operations, which only `update-v2`
+    /// accepts
+    v2_operations: Vec<PbListOperation>,
+}
+
+/// Which update endpoint an operation is sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endpoint {
+    Update,
+    UpdateV2,
+}
+
+impl<'a> OperationBatch<'a> {
+    pub(crate) fn new(client: &'a AnyListClient) -> Self {
+        Self {
+            client,
+            user_id: client.user_id(),
+            operations: Vec::new(),
+            v2_operations: Vec::new(),
+        }
+    }
+
+    /// Number of operations collected so far
+    pub fn len(&self) -> usize {
+        self.operations.len() + self.v2_operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add an item to a shopping list
+    pub fn add_item(&mut self, list_id: &str, name: &str) -> ListItem {
+        self.add_item_with_details(list_id, name, None, None, None)
+    }
+
+    /// Add an item with a quantity, details and category
+    pub fn add_item_with_details(
+        &mut self,
+        list_id: &str,
+        name: &str,
+        quantity: Option<&str>,
+        details: Option<&str>,
+        category: Option<&str>,
+    ) -> ListItem {
+        let item_id = generate_id();
+
+        self.push(
+            Endpoint::Update,
+            operations::build_add_item_operation(operations::AddItemParams {
+                item_id: item_id.clone(),
+                list_id: list_id.to_string(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+                name: name.to_string(),
+                quantity: quantity.map(|q| q.to_string()),
+                details: details.map(|d| d.to_string()),
+                category: category.map(|c| c.to_string()),
+                category_match_id: None,
+                category_assignment: None,
+            }),
+        );
+
+        ListItem {
+            id: item_id,
+            list_id: list_id.to_string(),
+            name: name.to_string(),
+            details: details.unwrap_or("").to_string(),
+            is_checked: false,
+            quantity: quantity.map(|q| q.to_string()),
+            category: category.map(|c| c.to_string()),
+            user_id: Some(self.user_id.clone()),
+            product_upc: None,
+        }
+    }
+
+    /// Replace an item's name, quantity, details and category
+    pub fn update_item(
+        &mut self,
+        list_id: &str,
+        item_id: &str,
+        name: &str,
+        quantity: Option<&str>,
+        details: Option<&str>,
+        category: Option<&str>,
+    ) -> &mut Self {
+        let operation_list =
+            operations::build_update_item_operation(operations::UpdateItemParams {
+                item_id: item_id.to_string(),
+                list_id: list_id.to_string(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+                timestamp: current_timestamp(),
+                name: name.to_string(),
+                quantity: quantity.map(|q| q.to_string()),
+                details: details.map(|d| d.to_string()),
+                category: category.map(|c| c.to_string()),
+            });
+        self.push(Endpoint::Update, operation_list)
+    }
+
+    /// Cross off (check) an item
+    pub fn cross_off_item(&mut self, list_id: &str, item_id: &str) -> &mut Self {
+        self.set_item_checked(list_id, item_id, true)
+    }
+
+    /// Uncheck (uncross) an item
+    pub fn uncheck_item(&mut self, list_id: &str, item_id: &str) -> &mut Self {
+        self.set_item_checked(list_id, item_id, false)
+    }
+
+    pub(crate) fn set_item_checked(
+        &mut self,
+        list_id: &str,
+        item_id: &str,
+        checked: bool,
+    ) -> &mut Self {
+        let operation_list =
+            operations::build_set_item_checked_operation(operations::SetItemCheckedParams {
+                item_id: item_id.to_string(),
+                list_id: list_id.to_string(),
+                checked,
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            });
+        self.push(Endpoint::Update, operation_list)
+    }
+
+    /// Create a store for a list
+    pub fn create_store(&mut self, list_id: &str, name: &str) -> Store {
+        let store_id = generate_id();
+
+        self.push(
+            Endpoint::UpdateV2,
+            operations::build_create_store_operation(operations::CreateStoreParams {
+                store_id: store_id.clone(),
+                list_id: list_id.to_string(),
+                name: name.to_string(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            }),
+        );
+
+        Store {
+            id: store_id,
+            name: name.to_string(),
+            sort_index: 0,
+        }
+    }
+
+    /// Rename a store
+    pub fn update_store(&mut self, list_id: &str, store_id: &str, new_name: &str) -> &mut Self {
+        let operation_list =
+            operations::build_rename_store_operation(operations::RenameStoreParams {
+                store_id: store_id.to_string(),
+                list_id: list_id.to_string(),
+                new_name: new_name.to_string(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            });
+        self.push(Endpoint::UpdateV2, operation_list)
+    }
+
+    /// Delete a store and remove it from the list's items.
+    ///
+    /// Unlike [`AnyListClient::delete_store`], this doesn't touch starter
+    /// lists or store filters; use
+    /// [`update_store_filter`](OperationBatch::update_store_filter) and
+    /// [`delete_store_filter`](OperationBatch::delete_store_filter) for
+    /// filters that include the store.
+    pub fn delete_store(&mut self, list_id: &str, store_id: &str) -> &mut Self {
+        let remove_from_items = operations::build_remove_store_from_items_operation(
+            operations::RemoveStoreFromItemsParams {
+                list_id: list_id.to_string(),
+                store_id: store_id.to_string(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            },
+        );
+        let delete = operations::build_delete_store_operation(operations::DeleteStoreParams {
+            store_id: store_id.to_string(),
+            store_name: None,
+            list_id: list_id.to_string(),
+            operation_id: generate_id(),
+            user_id: self.user_id.clone(),
+        });
+
+        self.push(Endpoint::Update, remove_from_items)
+            .push(Endpoint::UpdateV2, delete)
+    }
+
+    /// Replace a store filter's name and stores
+    pub fn update_store_filter(
+        &mut self,
+        list_id: &str,
+        filter_id: &str,
+        name: &str,
+        store_ids: &[String],
+    ) -> &mut Self {
+        let operation_list =
+            operations::build_update_store_filter_operation(operations::UpdateStoreFilterParams {
+                filter_id: filter_id.to_string(),
+                list_id: list_id.to_string(),
+                filter_name: name.to_string(),
+                store_ids: store_ids.to_vec(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            });
+        self.push(Endpoint::UpdateV2, operation_list)
+    }
+
+    /// Delete a store filter
+    pub fn delete_store_filter(&mut self, list_id: &str, filter: &StoreFilter) -> &mut Self {
+        let operation_list =
+            operations::build_delete_store_filter_operation(operations::DeleteStoreFilterParams {
+                filter_id: filter.id.clone(),
+                list_id: list_id.to_string(),
+                filter_name: filter.name.clone(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            });
+        self.push(Endpoint::UpdateV2, operation_list)
+    }
+
+    /// Create a category in one of a list's category groups
+    pub fn create_category(
+        &mut self,
+        list_id: &str,
+        category_group_id: &str,
+        name: &str,
+    ) -> Category {
+        let category_id = generate_id();
+
+        self.push(
+            Endpoint::UpdateV2,
+            operations::build_create_category_operation(operations::CategoryParams {
+                category_id: category_id.clone(),
+                category_group_id: category_group_id.to_string(),
+                list_id: list_id.to_string(),
+                name: name.to_string(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            }),
+        );
+
+        Category {
+            id: category_id,
+            name: name.to_string(),
+            icon: None,
+            sort_index: 0,
+        }
+    }
+
+    /// Rename a category
+    pub fn rename_category(
+        &mut self,
+        list_id: &str,
+        category_group_id: &str,
+        category_id: &str,
+        new_name: &str,
+    ) -> &mut Self {
+        let operation_list =
+            operations::build_rename_category_operation(operations::CategoryParams {
+                category_id: category_id.to_string(),
+                category_group_id: category_group_id.to_string(),
+                list_id: list_id.to_string(),
+                name: new_name.to_string(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            });
+        self.push(Endpoint::UpdateV2, operation_list)
+    }
+
+    /// Delete a category
+    pub fn delete_category(&mut self, list_id: &str, category_id: &str) -> &mut Self {
+        let operation_list =
+            operations::build_delete_category_operation(operations::DeleteCategoryParams {
+                category_id: category_id.to_string(),
+                list_id: list_id.to_string(),
+                operation_id: generate_id(),
+                user_id: self.user_id.clone(),
+            });
+        self.push(Endpoint::UpdateV2, operation_list)
+    }
+
+    /// Send the collected item operations to `update`, then the store, store
+    /// filter and category operations to `update-v2`, each in the order they
+    /// were added.
+    ///
+    /// Fails with [`AnyListError::OperationNotProcessed`](crate::AnyListError::OperationNotProcessed)
+    /// if the server skipped any of them. If the first request fails, the
+    /// second isn't sent. An empty batch sends nothing.
+    pub async fn commit(self) -> Result<OperationOutcome> {
+        let mut outcome = OperationOutcome::default();
+
+        for (endpoint, operations) in [
+            ("data/shopping-lists/update", self.operations),
+            ("data/shopping-lists/update-v2", self.v2_operations),
+        ] {
+            if operations.is_empty() {
+                continue;
+            }
+            let operation_list = PbListOperationList { operations };
+            outcome = outcome.merge(self.client.post_edit(endpoint, &operation_list).await?);
+        }
+
+        Ok(outcome)
+    }
+
+    fn push(&mut self, endpoint: Endpoint, operation_list: PbListOperationList) -> &mut Self {
+        let operations = match endpoint {
+            Endpoint::Update => &mut self.operations,
+            Endpoint::UpdateV2 => &mut self.v2_operations,
+        };
+        operations.extend(operation_list.operations);
+        self
+    }
+}
+
+impl AnyListClient {
+    /// Start collecting list operations to commit in a single request
+    pub fn batch(&self) -> OperationBatch<'_> {
+        OperationBatch::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SavedTokens;
+
+    fn client() -> AnyListClient {
+        AnyListClient::from_tokens(SavedTokens::new("access", "refresh", "user-1", false)).unwrap()
+    }
+
+    fn handlers(operations: &[PbListOperation]) -> Vec<String> {
+        operations
+            .iter()
+            .map(|op| op.metadata.as_ref().unwrap().handler_id.clone().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_operations_are_split_by_endpoint() {
+        let client = client();
+        let mut batch = client.batch();
+        let item = batch.add_item("list-1", "Milk");
+        batch.cross_off_item("list-1", item.id());
+        assert_eq!(batch.len(), 2);
+        assert!(batch.v2_operations.is_empty());
+
+        batch.create_store("list-1", "Market");
+        batch.delete_store("list-1", "store-1");
+        batch.delete_store_filter(
+            "list-1",
+            &StoreFilter {
+                id: "filter-1".to_string(),
+                name: "Weekend".to_string(),
+                store_ids: vec![],
+            },
+        );
+        assert_eq!(batch.len(), 6);
+
+        assert_eq!(
+            handlers(&batch.operations),
+            vec![
+                "add-shopping-list-item",
+                "set-list-item-checked",
+                "remove-store-id-from-all-items"
+            ]
+        );
+        assert_eq!(
+            handlers(&batch.v2_operations),
+            vec!["new-store", "delete-store", "delete-store-filter"]
+        );
+        assert!(batch
+            .operations
+            .iter()
+            .chain(&batch.v2_operations)
+            .all(|op| op.metadata.as_ref().unwrap().user_id.as_deref() == Some("user-1")));
+    }
+}
@@ -0,0 +1,244 @@
+//! Opt-in local replica of the user data.
+//!
+//! Every read (`get_lists`, `get_recipes`, `get_stores_for_list`, ...) is
+//! answered from `data/user-data/get`, which returns all of the user's data
+//! at once. With
+//! [`AnyListClientBuilder::cache_user_data`](crate::AnyListClientBuilder::cache_user_data)
+//! the client keeps the last response and serves reads from it until the
+//! section they need is invalidated:
+//!
+//! - by a mutation made through this client, for the section it changed
+//! - by a [`SyncEvent`] received by a [`RealtimeSync`](crate::RealtimeSync)
+//!   for this client, for the section the event names
+//! - by hand, with [`AnyListClient::invalidate_user_data`] or
+//!   [`AnyListClient::apply_sync_event`]
+//!
+//! Reading an invalidated section syncs the cached data incrementally (see
+//! [`crate::delta`]), downloading only what changed; reads of sections that
+//! are still valid never hit the network.
+
+use crate::client::AnyListClient;
+use crate::delta::UserDataState;
+use crate::protobuf::anylist::PbUserDataResponse;
+use crate::realtime::SyncEvent;
+use std::collections::HashSet;
+
+/// A part of the user data that can be invalidated on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserDataSection {
+    ShoppingLists,
+    ListFolders,
+    ListSettings,
+    RecipeData,
+    MealPlanningCalendar,
+    CategorizedItems,
+    UserCategories,
+    StarterLists,
+    StarterListOrder,
+    StarterListSettings,
+    MobileAppSettings,
+}
+
+impl UserDataSection {
+    /// The section a realtime event reports as changed, if any
+    pub fn for_event(event: &SyncEvent) -> Option<Self> {
+        match event {
+            SyncEvent::ShoppingListsChanged => Some(Self::ShoppingLists),
+            SyncEvent::CategorizedItemsChanged => Some(Self::CategorizedItems),
+            SyncEvent::ListFoldersChanged => Some(Self::ListFolders),
+            SyncEvent::ListSettingsChanged => Some(Self::ListSettings),
+            SyncEvent::StarterListsChanged => Some(Self::StarterLists),
+            SyncEvent::StarterListOrderChanged => Some(Self::StarterListOrder),
+            SyncEvent::StarterListSettingsChanged => Some(Self::StarterListSettings),
+            SyncEvent::MobileAppSettingsChanged => Some(Self::MobileAppSettings),
+            SyncEvent::UserCategoriesChanged => Some(Self::UserCategories),
+            SyncEvent::RecipeDataChanged => Some(Self::RecipeData),
+            SyncEvent::MealPlanCalendarChanged => Some(Self::MealPlanningCalendar),
+            SyncEvent::AccountInfoChanged
+            | SyncEvent::SubscriptionInfoChanged
+            | SyncEvent::AccountDeleted
+            | SyncEvent::Heartbeat
+            | SyncEvent::Unknown(_)
+            | SyncEvent::Binary(_)
+            | SyncEvent::ConnectionStateChanged(_) => None,
+        }
+    }
+
+    /// The sections a mutation endpoint writes to
+    pub(crate) fn for_endpoint(endpoint: &str) -> &'static [Self] {
+        match endpoint.trim_start_matches('/') {
+            // Creating or deleting a list also adds or removes its settings
+            // and its entry in the root folder
+            "data/shopping-lists/update" => {
+                &[Self::ShoppingLists, Self::ListSettings, Self::ListFolders]
+            }
+            "data/shopping-lists/update-v2" => &[Self::ShoppingLists],
+            // Removing a list from its folder deletes the list
+            "data/list-folders/update" => &[Self::ListFolders, Self::ShoppingLists],
+            "data/list-settings/update" => &[Self::ListSettings],
+            "data/starter-lists/update" => &[Self::StarterLists],
+            "data/user-recipe-data/update" => &[Self::RecipeData],
+            "data/meal-planning-calendar/update" => &[Self::MealPlanningCalendar],
+            _ => &[],
+        }
+    }
+}
+
+/// The last user data response and the sections invalidated since
+#[derive(Default)]
+pub(crate) struct UserDataCache {
+    state: Option<UserDataState>,
+    stale: HashSet<UserDataSection>,
+    /// Bumped on every invalidation, so a fetch that was already in flight
+    /// doesn't mark sections fresh that changed after it was sent
+    generation: u64,
+}
+
+impl UserDataCache {
+    /// The cached data, if none of `sections` is stale
+    pub(crate) fn get(&self, sections: &[UserDataSection]) -> Option<PbUserDataResponse> {
+        if sections.iter().any(|s| self.stale.contains(s)) {
+            return None;
+        }
+        self.state.as_ref().map(|state| state.data().clone())
+    }
+
+    /// The cached data to sync from, fresh or not
+    pub(crate) fn state(&self) -> Option<UserDataState> {
+        self.state.clone()
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Store a response fetched when the cache was at `generation`
+    pub(crate) fn store(&mut self, state: UserDataState, generation: u64) {
+        self.state = Some(state);
+        if generation == self.generation {
+            self.stale.clear();
+        }
+    }
+
+    pub(crate) fn invalidate(&mut self, section: UserDataSection) {
+        self.stale.insert(section);
+        self.generation += 1;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.state = None;
+        self.stale.clear();
+        self.generation += 1;
+    }
+}
+
+impl AnyListClient {
+    /// Mark one section of the cached user data as stale, so the next read
+    /// that needs it downloads the user data again.
+    ///
+    /// Does nothing unless the client was built with
+    /// [`cache_user_data`](crate::AnyListClientBuilder::cache_user_data).
+    pub fn invalidate_user_data(&self, section: UserDataSection) {
+        if let Some(cache) = &self.user_data_cache {
+            cache.lock().unwrap().invalidate(section);
+        }
+    }
+
+    /// Drop the cached user data entirely
+    pub fn clear_user_data_cache(&self) {
+        if let Some(cache) = &self.user_data_cache {
+            cache.lock().unwrap().clear();
+        }
+    }
+
+    /// Invalidate whatever part of the cached user data `event` reports as
+    /// changed.
+    ///
+    /// [`RealtimeSync`](crate::RealtimeSync) calls this for every event it
+    /// receives; call it yourself if events reach you some other way.
+    pub fn apply_sync_event(&self, event: &SyncEvent) {
+        if *event == SyncEvent::AccountDeleted {
+            self.clear_user_data_cache();
+        } else if let Some(section) = UserDataSection::for_event(event) {
+            self.invalidate_user_data(section);
+        }
+    }
+
+    /// User data for a read that needs `sections`, from the cache if it is
+    /// enabled and they are all fresh
+    pub(crate) async fn cached_user_data(
+        &self,
+        sections: &[UserDataSection],
+    ) -> crate::error::Result<PbUserDataResponse> {
+        let Some(cache) = &self.user_data_cache else {
+            return self.get_user_data().await;
+        };
+
+        let (state, generation) = {
+            let cache = cache.lock().unwrap();
+            if let Some(data) = cache.get(sections) {
+                return Ok(data);
+            }
+            (cache.state(), cache.generation())
+        };
+
+        match state {
+            Some(mut state) => {
+                let delta = self.get_user_data_delta(&state.client_timestamps()).await?;
+                state.merge(delta);
+                cache.lock().unwrap().store(state.clone(), generation);
+                Ok(state.into_data())
+            }
+            None => self.get_user_data().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_stale_sections_miss() {
+        let mut cache = UserDataCache::default();
+        assert!(cache.get(&[UserDataSection::ShoppingLists]).is_none());
+
+        cache.store(UserDataState::new(), cache.generation());
+        assert!(cache.get(&[UserDataSection::ShoppingLists]).is_some());
+
+        cache.invalidate(UserDataSection::RecipeData);
+        assert!(cache.get(&[UserDataSection::ShoppingLists]).is_some());
+        assert!(cache
+            .get(&[UserDataSection::ShoppingLists, UserDataSection::RecipeData])
+            .is_none());
+    }
+
+    #[test]
+    fn test_fetch_started_before_invalidation_stays_stale() {
+        let mut cache = UserDataCache::default();
+        let generation = cache.generation();
+        cache.invalidate(UserDataSection::ShoppingLists);
+
+        cache.store(UserDataState::new(), generation);
+        assert!(cache.get(&[UserDataSection::ShoppingLists]).is_none());
+
+        cache.store(UserDataState::new(), cache.generation());
+        assert!(cache.get(&[UserDataSection::ShoppingLists]).is_some());
+    }
+
+    #[test]
+    fn test_events_and_endpoints_map_to_sections() {
+        assert_eq!(
+            UserDataSection::for_event(&SyncEvent::RecipeDataChanged),
+            Some(UserDataSection::RecipeData)
+        );
+        assert_eq!(UserDataSection::for_event(&SyncEvent::Heartbeat), None);
+        assert_eq!(
+            UserDataSection::for_endpoint("/data/shopping-lists/update-v2"),
+            &[UserDataSection::ShoppingLists]
+        );
+        assert!(UserDataSection::for_endpoint("data/shopping-lists/update")
+            .contains(&UserDataSection::ListSettings));
+        assert!(UserDataSection::for_endpoint("data/user-data/get").is_empty());
+    }
+}
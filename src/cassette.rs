@@ -0,0 +1,499 @@
+//! Record and replay HTTP sessions.
+//!
+//! A cassette is a file with one JSON object per line, each describing one
+//! request the client made: the endpoint path, the multipart fields that were
+//! posted (usually a single `operations` field holding protobuf bytes) and the
+//! response status and body. Binary data is hex encoded, so cassettes diff
+//! cleanly and can be decoded with `tools/decode_snapshot.py`.
+//!
+//! Record a real session once with
+//! [`AnyListClientBuilder::record`](crate::AnyListClientBuilder::record), then
+//! replay it offline in regression tests with
+//! [`AnyListClientBuilder::replay`](crate::AnyListClientBuilder::replay):
+//!
+//! ```no_run
+//! use anylist_rs::{AnyListClient, SavedTokens};
+//!
+//! # async fn example() -> anylist_rs::Result<()> {
+//! // Capture a session against the real server
+//! let client = AnyListClient::builder()
+//!     .record("tests/cassettes/groceries.jsonl")
+//!     .login("user@example.com", "password")
+//!     .await?;
+//! let lists = client.get_lists().await?;
+//!
+//! // Later, with no network access. Any tokens will do.
+//! let client = AnyListClient::builder()
+//!     .replay("tests/cassettes/groceries.jsonl")
+//!     .from_tokens(SavedTokens::new("", "", "user-id", false))
+//!     .await?;
+//! assert_eq!(client.get_lists().await?, lists);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Authentication requests (`/auth/...`) and `401` responses are never
+//! written, so cassettes contain no passwords or tokens and replay never has
+//! to refresh.
+//!
+//! Replay hands out interactions in recorded order and only checks that the
+//! method, endpoint and field names match. The request bytes are not
+//! compared, because they contain freshly generated IDs and timestamps. For
+//! the same reason, the operation IDs a recorded `.../update` response
+//! acknowledges are swapped for those of the replayed request, so mutations
+//! still see their own operations confirmed.
+
+use crate::error::{AnyListError, Result};
+use crate::protobuf::anylist::PbEditOperationResponse;
+use crate::transport::{MultipartForm, Transport, TransportRequest, TransportResponse};
+use crate::utils::url_path;
+use futures_util::future::BoxFuture;
+use prost::Message;
+use reqwest::header::HeaderMap;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A multipart field of a recorded request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedField {
+    pub(crate) name: String,
+    #[serde(with = "hex_bytes")]
+    pub(crate) data: Vec<u8>,
+}
+
+impl RecordedField {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// One request/response pair in a cassette
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub(crate) method: String,
+    /// URL path, without the host (e.g. `/data/user-data/get`)
+    pub(crate) endpoint: String,
+    pub(crate) fields: Vec<RecordedField>,
+    pub(crate) status: u16,
+    #[serde(with = "hex_bytes")]
+    pub(crate) response: Vec<u8>,
+}
+
+impl Interaction {
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn fields(&self) -> &[RecordedField] {
+        &self.fields
+    }
+
+    /// Bytes of the request field with the given name, e.g. `"operations"`
+    pub fn field(&self, name: &str) -> Option<&[u8]> {
+        self.fields
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| f.data.as_slice())
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn response(&self) -> &[u8] {
+        &self.response
+    }
+
+    fn from_exchange(request: &TransportRequest, response: &TransportResponse) -> Self {
+        Interaction {
+            method: request.method.to_string(),
+            endpoint: url_path(&request.url).to_string(),
+            fields: request
+                .body
+                .iter()
+                .flat_map(|form| form.parts())
+                .map(|part| RecordedField {
+                    name: part.name().to_string(),
+                    data: part.data().to_vec(),
+                })
+                .collect(),
+            status: response.status,
+            response: response.body.clone(),
+        }
+    }
+
+    fn field_names(&self) -> Vec<&str> {
+        self.fields.iter().map(|f| f.name.as_str()).collect()
+    }
+}
+
+/// Read every interaction from a cassette file
+pub fn read_cassette(path: impl AsRef<Path>) -> Result<Vec<Interaction>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| {
+        AnyListError::Other(format!("Failed to open cassette {}: {}", path.display(), e))
+    })?;
+
+    let mut interactions = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| {
+            AnyListError::Other(format!("Failed to read cassette {}: {}", path.display(), e))
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let interaction = serde_json::from_str(&line).map_err(|e| {
+            AnyListError::InvalidResponse(format!(
+                "Invalid cassette entry at {}:{}: {}",
+                path.display(),
+                index + 1,
+                e
+            ))
+        })?;
+        interactions.push(interaction);
+    }
+    Ok(interactions)
+}
+
+/// Forwards requests to another [`Transport`] and appends each exchange to a
+/// cassette file
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    file: Mutex<File>,
+}
+
+impl RecordingTransport {
+    /// Start a new cassette at `path`, replacing any existing file
+    pub fn new(path: impl AsRef<Path>, inner: impl Transport + 'static) -> Result<Self> {
+        Self::wrap(path.as_ref(), Arc::new(inner))
+    }
+
+    pub(crate) fn wrap(path: &Path, inner: Arc<dyn Transport>) -> Result<Self> {
+        let file = File::create(path).map_err(|e| {
+            AnyListError::Other(format!(
+                "Failed to create cassette {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Ok(Self {
+            inner,
+            file: Mutex::new(file),
+        })
+    }
+
+    fn write(&self, interaction: &Interaction) -> Result<()> {
+        let line = serde_json::to_string(interaction)
+            .map_err(|e| AnyListError::Other(format!("Failed to encode interaction: {}", e)))?;
+
+        let mut file = self.file.lock().unwrap();
+        writeln!(file, "{}", line)
+            .and_then(|_| file.flush())
+            .map_err(|e| AnyListError::Other(format!("Failed to write cassette: {}", e)))
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send(&self, request: TransportRequest) -> BoxFuture<'_, Result<TransportResponse>> {
+        Box::pin(async move {
+            let is_auth = url_path(&request.url).starts_with("/auth/");
+            let recorded = if is_auth { None } else { Some(request.clone()) };
+
+            let response = self.inner.send(request).await?;
+
+            if let Some(request) = recorded {
+                if response.status != 401 {
+                    self.write(&Interaction::from_exchange(&request, &response))?;
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Answers requests from a cassette instead of the network
+pub struct ReplayTransport {
+    interactions: Mutex<VecDeque<Interaction>>,
+}
+
+impl ReplayTransport {
+    /// Load the cassette at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(read_cassette(path)?))
+    }
+
+    pub fn new(interactions: Vec<Interaction>) -> Self {
+        Self {
+            interactions: Mutex::new(interactions.into()),
+        }
+    }
+
+    /// Number of interactions not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.interactions.lock().unwrap().len()
+    }
+
+    fn next(&self, request: &TransportRequest) -> Result<TransportResponse> {
+        let endpoint = url_path(&request.url);
+        let field_names: Vec<&str> = request
+            .body
+            .iter()
+            .flat_map(MultipartForm::parts)
+            .map(|p| p.name())
+            .collect();
+
+        let mut interactions = self.interactions.lock().unwrap();
+        let interaction = interactions.pop_front().ok_or_else(|| {
+            AnyListError::Other(format!(
+                "Cassette exhausted: no recorded response for {} {}",
+                request.method, endpoint
+            ))
+        })?;
+
+        if interaction.method != request.method.as_str()
+            || interaction.endpoint != endpoint
+            || interaction.field_names() != field_names
+        {
+            return Err(AnyListError::Other(format!(
+                "Cassette mismatch: expected {} {} {:?}, got {} {} {:?}",
+                interaction.method,
+                interaction.endpoint,
+                interaction.field_names(),
+                request.method,
+                endpoint,
+                field_names
+            )));
+        }
+
+        let body = match (interaction.field("operations"), request.body.as_ref()) {
+            (Some(recorded), Some(form)) => match form.part("operations") {
+                Some(sent) => rewrite_operation_ids(&interaction.response, recorded, sent.data())
+                    .unwrap_or(interaction.response),
+                None => interaction.response,
+            },
+            _ => interaction.response,
+        };
+
+        Ok(TransportResponse {
+            status: interaction.status,
+            headers: HeaderMap::new(),
+            body,
+        })
+    }
+}
+
+/// The operation IDs of any `PB...OperationList`. Every list type keeps its
+/// operations in field 1 and each operation's metadata in field 1.
+#[derive(Clone, PartialEq, Message)]
+struct OperationIds {
+    #[prost(message, repeated, tag = "1")]
+    operations: Vec<OperationIdsOperation>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OperationIdsOperation {
+    #[prost(message, optional, tag = "1")]
+    metadata: Option<OperationIdsMetadata>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OperationIdsMetadata {
+    #[prost(string, optional, tag = "1")]
+    operation_id: Option<String>,
+}
+
+fn decode_operation_ids(operations: &[u8]) -> Option<Vec<String>> {
+    OperationIds::decode(operations)
+        .ok()?
+        .operations
+        .into_iter()
+        .map(|op| op.metadata?.operation_id)
+        .collect()
+}
+
+/// Re-encode a recorded `PBEditOperationResponse` so that it acknowledges the
+/// operations sent now instead of those sent while recording. Operations are
+/// matched by position. Returns `None` if the response is not an edit
+/// response or the requests don't line up.
+fn rewrite_operation_ids(response: &[u8], recorded: &[u8], sent: &[u8]) -> Option<Vec<u8>> {
+    let recorded = decode_operation_ids(recorded)?;
+    let sent = decode_operation_ids(sent)?;
+    if recorded.len() != sent.len() {
+        return None;
+    }
+
+    let mut response = PbEditOperationResponse::decode(response).ok()?;
+    for id in &mut response.processed_operations {
+        if let Some(index) = recorded.iter().position(|r| r == id) {
+            *id = sent[index].clone();
+        }
+    }
+    Some(response.encode_to_vec())
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: TransportRequest) -> BoxFuture<'_, Result<TransportResponse>> {
+        let response = self.next(&request);
+        Box::pin(async move { response })
+    }
+}
+
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(serde::de::Error::custom("odd number of hex digits"));
+        }
+        hex.as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                // Not from_str_radix, which would accept a leading '+'
+                let digit = |b: u8| char::from(b).to_digit(16);
+                match (digit(pair[0]), digit(pair[1])) {
+                    (Some(high), Some(low)) => Ok((high * 16 + low) as u8),
+                    _ => Err(serde::de::Error::custom("invalid hex digit")),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Method;
+
+    fn interaction(endpoint: &str, response: &[u8]) -> Interaction {
+        Interaction {
+            method: "POST".to_string(),
+            endpoint: endpoint.to_string(),
+            fields: vec![RecordedField {
+                name: "operations".to_string(),
+                data: vec![0x0a, 0x00],
+            }],
+            status: 200,
+            response: response.to_vec(),
+        }
+    }
+
+    fn post(url: &str) -> TransportRequest {
+        TransportRequest {
+            method: Method::POST,
+            url: url.to_string(),
+            headers: HeaderMap::new(),
+            body: Some(MultipartForm::new().bytes("operations", vec![1, 2, 3])),
+        }
+    }
+
+    #[test]
+    fn test_interaction_json_uses_hex() {
+        let json =
+            serde_json::to_string(&interaction("/data/user-data/get", &[0xde, 0xad])).unwrap();
+        assert!(json.contains(r#""response":"dead""#));
+        assert!(json.contains(r#""data":"0a00""#));
+
+        let parsed: Interaction = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, interaction("/data/user-data/get", &[0xde, 0xad]));
+    }
+
+    #[test]
+    fn test_invalid_hex_is_an_error() {
+        let json =
+            serde_json::to_string(&interaction("/data/user-data/get", &[0xde, 0xad])).unwrap();
+        for invalid in [r#""éé""#, r#""+dad""#, r#""dea""#] {
+            let json = json.replace(r#""dead""#, invalid);
+            assert!(serde_json::from_str::<Interaction>(&json).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_returns_responses_in_order() {
+        let replay = ReplayTransport::new(vec![
+            interaction("/data/user-data/get", b"first"),
+            interaction("/data/shopping-lists/update", b"second"),
+        ]);
+
+        let first = replay
+            .send(post("https://www.anylist.com/data/user-data/get"))
+            .await
+            .unwrap();
+        assert_eq!(first.body, b"first");
+        assert_eq!(replay.remaining(), 1);
+
+        let second = replay
+            .send(post("http://localhost/data/shopping-lists/update"))
+            .await
+            .unwrap();
+        assert_eq!(second.body, b"second");
+
+        assert!(replay
+            .send(post("http://localhost/data/user-data/get"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replay_acknowledges_the_replayed_operation_ids() {
+        let operations = |id: &str| {
+            OperationIds {
+                operations: vec![OperationIdsOperation {
+                    metadata: Some(OperationIdsMetadata {
+                        operation_id: Some(id.to_string()),
+                    }),
+                }],
+            }
+            .encode_to_vec()
+        };
+        let recorded = Interaction {
+            fields: vec![RecordedField {
+                name: "operations".to_string(),
+                data: operations("recorded-op"),
+            }],
+            response: PbEditOperationResponse {
+                processed_operations: vec!["recorded-op".to_string()],
+                ..Default::default()
+            }
+            .encode_to_vec(),
+            ..interaction("/data/shopping-lists/update", b"")
+        };
+        let replay = ReplayTransport::new(vec![recorded]);
+
+        let mut request = post("http://localhost/data/shopping-lists/update");
+        request.body = Some(MultipartForm::new().bytes("operations", operations("new-op")));
+        let response = replay.send(request).await.unwrap();
+
+        let decoded = PbEditOperationResponse::decode(response.body.as_slice()).unwrap();
+        assert_eq!(decoded.processed_operations, vec!["new-op".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_rejects_unexpected_endpoint() {
+        let replay = ReplayTransport::new(vec![interaction("/data/user-data/get", b"")]);
+
+        let result = replay
+            .send(post("https://www.anylist.com/data/list-folders/update"))
+            .await;
+        assert!(matches!(result, Err(AnyListError::Other(msg)) if msg.contains("mismatch")));
+    }
+}
@@ -1,9 +1,22 @@
+use crate::cache::{UserDataCache, UserDataSection};
+use crate::cassette::{RecordingTransport, ReplayTransport};
 use crate::error::{AnyListError, Result};
 use crate::login::login;
-use crate::utils::generate_id;
+use crate::offline::OfflineQueue;
+use crate::outcome::{OperationList, OperationOutcome};
+use crate::retry::RetryPolicy;
+use crate::token_store::TokenStore;
+use crate::transport::{
+    Method, MultipartForm, ReqwestTransport, Transport, TransportRequest, TransportResponse,
+};
+use crate::utils::{encode_operation_list, generate_id, jwt_expiry};
+use chrono::{DateTime, Utc};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 use serde_derive::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::task::JoinHandle;
 
 // ============================================================================
 // Public types for persistence and events
@@ -52,11 +65,341 @@ impl SavedTokens {
 
 /// Authentication events that can be monitored
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum AuthEvent {
     /// Tokens were successfully refreshed
     TokensRefreshed,
     /// Token refresh failed
     RefreshFailed(String),
+    /// Realtime sync reported that the account was deleted. The tokens no
+    /// longer work.
+    AccountDeleted,
+    /// Tokens changed but could not be written to the token store
+    TokenStoreFailed(String),
+}
+
+// ============================================================================
+// Endpoint configuration
+// ============================================================================
+
+const DEFAULT_API_BASE_URL: &str = "https://www.anylist.com";
+const DEFAULT_AUTH_BASE_URL: &str = "https://www.anylist.com";
+const DEFAULT_PHOTOS_BASE_URL: &str = "https://photos.anylist.com";
+const DEFAULT_WEBSOCKET_URL: &str = "wss://www.anylist.com/data/add-user-listener";
+
+/// Base URLs used by a client (see [`AnyListClientBuilder`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Endpoints {
+    /// Base for `data/...` REST endpoints
+    pub(crate) api_base_url: String,
+    /// Base for `/auth/token` and `/auth/token/refresh`
+    pub(crate) auth_base_url: String,
+    /// Base for the public recipe photo CDN
+    pub(crate) photos_base_url: String,
+    /// Full URL of the realtime listener WebSocket (without query string)
+    pub(crate) websocket_url: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            api_base_url: DEFAULT_API_BASE_URL.to_string(),
+            auth_base_url: DEFAULT_AUTH_BASE_URL.to_string(),
+            photos_base_url: DEFAULT_PHOTOS_BASE_URL.to_string(),
+            websocket_url: DEFAULT_WEBSOCKET_URL.to_string(),
+        }
+    }
+}
+
+/// Builder for an [`AnyListClient`] that talks to non-default servers.
+///
+/// Every URL defaults to the production AnyList service, so only the ones
+/// you want to redirect (e.g. to a local stand-in server in integration
+/// tests, or a staging proxy) need to be set. Trailing slashes are ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// use anylist_rs::{AnyListClient, SavedTokens};
+///
+/// # async fn example(tokens: SavedTokens) -> anylist_rs::Result<()> {
+/// let client = AnyListClient::builder()
+///     .api_base_url("http://127.0.0.1:8080")
+///     .auth_base_url("http://127.0.0.1:8080")
+///     .photos_base_url("http://127.0.0.1:8080/photos")
+///     .websocket_url("ws://127.0.0.1:8080/data/add-user-listener")
+///     .from_tokens(tokens)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AnyListClientBuilder {
+    endpoints: Endpoints,
+    transport: Arc<dyn Transport>,
+    cassette: Option<CassetteMode>,
+    retry_policy: RetryPolicy,
+    cache_user_data: bool,
+    offline_queue: Option<PathBuf>,
+    token_store: Option<Arc<dyn TokenStore>>,
+    refresh_ahead: Duration,
+}
+
+#[derive(Clone)]
+enum CassetteMode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl Default for AnyListClientBuilder {
+    fn default() -> Self {
+        Self {
+            endpoints: Endpoints::default(),
+            transport: Arc::new(ReqwestTransport::new()),
+            cassette: None,
+            retry_policy: RetryPolicy::default(),
+            cache_user_data: false,
+            offline_queue: None,
+            token_store: None,
+            refresh_ahead: DEFAULT_REFRESH_AHEAD,
+        }
+    }
+}
+
+impl AnyListClientBuilder {
+    /// Create a builder with the production AnyList URLs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send all HTTP requests through a custom [`Transport`]
+    /// (default [`ReqwestTransport`])
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Set the base URL for REST endpoints (default `https://www.anylist.com`)
+    pub fn api_base_url(mut self, url: impl Into<String>) -> Self {
+        self.endpoints.api_base_url = trim_trailing_slash(url.into());
+        self
+    }
+
+    /// Set the base URL for authentication endpoints (default `https://www.anylist.com`)
+    pub fn auth_base_url(mut self, url: impl Into<String>) -> Self {
+        self.endpoints.auth_base_url = trim_trailing_slash(url.into());
+        self
+    }
+
+    /// Set the base URL of the recipe photo CDN (default `https://photos.anylist.com`)
+    pub fn photos_base_url(mut self, url: impl Into<String>) -> Self {
+        self.endpoints.photos_base_url = trim_trailing_slash(url.into());
+        self
+    }
+
+    /// Set the realtime listener WebSocket URL
+    /// (default `wss://www.anylist.com/data/add-user-listener`)
+    ///
+    /// The client ID and access token are appended as query parameters.
+    pub fn websocket_url(mut self, url: impl Into<String>) -> Self {
+        self.endpoints.websocket_url = trim_trailing_slash(url.into());
+        self
+    }
+
+    /// Retry REST calls that fail for transient reasons according to `policy`
+    /// (default [`RetryPolicy::default`]; use [`RetryPolicy::none`] to disable)
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Keep the last user data response and answer reads from it until the
+    /// section they need changes (default off).
+    ///
+    /// See [`crate::cache`] for how sections are invalidated.
+    pub fn cache_user_data(mut self) -> Self {
+        self.cache_user_data = true;
+        self
+    }
+
+    /// Save mutations that fail for lack of a network to a queue file at
+    /// `path`, and send them once it is back (default off).
+    ///
+    /// Operations already in the file are loaded when the client is built.
+    /// See [`crate::offline`].
+    pub fn offline_queue(mut self, path: impl Into<PathBuf>) -> Self {
+        self.offline_queue = Some(path.into());
+        self
+    }
+
+    /// Save the client's tokens to `store` after login, when restoring
+    /// tokens and after every refresh (default none).
+    ///
+    /// See [`crate::token_store`].
+    pub fn token_store(mut self, store: impl TokenStore + 'static) -> Self {
+        self.token_store = Some(Arc::new(store));
+        self
+    }
+
+    /// How long before the access token expires to refresh it (default 1
+    /// minute).
+    ///
+    /// Only applies to access tokens that carry an expiry. See
+    /// [`AnyListClient::start_token_refresh`].
+    pub fn refresh_ahead(mut self, ahead: Duration) -> Self {
+        self.refresh_ahead = ahead;
+        self
+    }
+
+    /// Record every request and response to a cassette file at `path`,
+    /// replacing any existing file.
+    ///
+    /// Requests still go through the configured transport. See
+    /// [`crate::cassette`] for the file format.
+    pub fn record(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cassette = Some(CassetteMode::Record(path.into()));
+        self
+    }
+
+    /// Answer every request from a cassette file previously written by
+    /// [`record`](Self::record), without touching the network.
+    ///
+    /// Cassettes contain no authentication requests, so build the client with
+    /// [`from_tokens`](Self::from_tokens) rather than [`login`](Self::login).
+    pub fn replay(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cassette = Some(CassetteMode::Replay(path.into()));
+        self
+    }
+
+    /// Log in with email and password and build the client.
+    ///
+    /// See [`AnyListClient::login`].
+    pub async fn login(self, email: &str, password: &str) -> Result<AnyListClient> {
+        let client_identifier = generate_id();
+        let transport = self.build_transport()?;
+
+        let login_result = login(
+            transport.as_ref(),
+            &self.endpoints.auth_base_url,
+            email,
+            password,
+            &client_identifier,
+        )
+        .await?;
+
+        let auth = Arc::new(Mutex::new(AuthState {
+            access_token: login_result.access_token,
+            refresh_token: login_result.refresh_token,
+            user_id: login_result.user_id,
+            is_premium_user: login_result.is_premium_user,
+            auto_refresh_enabled: true,
+            token_generation: 0,
+            obtained_at: Some(Utc::now()),
+        }));
+
+        let client = AnyListClient {
+            auth,
+            auth_event_callback: None,
+            client_identifier,
+            transport,
+            user_data_cache: self.user_data_cache(),
+            offline_queue: self.open_offline_queue()?,
+            offline_replay: tokio::sync::Mutex::new(()),
+            token_store: self.token_store,
+            refreshing: tokio::sync::Mutex::new(()),
+            refresh_ahead: self.refresh_ahead,
+            endpoints: self.endpoints,
+            retry_policy: self.retry_policy,
+        };
+        client.save_tokens().await?;
+        Ok(client)
+    }
+
+    /// Build the client from previously saved tokens, and save them to the
+    /// [`token_store`](Self::token_store), if one is set.
+    ///
+    /// See [`AnyListClient::from_tokens`].
+    pub async fn from_tokens(self, tokens: SavedTokens) -> Result<AnyListClient> {
+        let client = self.build_from_tokens(tokens)?;
+        client.save_tokens().await?;
+        Ok(client)
+    }
+
+    /// Build the client from previously saved tokens without touching the
+    /// token store
+    pub(crate) fn build_from_tokens(self, tokens: SavedTokens) -> Result<AnyListClient> {
+        let auth = Arc::new(Mutex::new(AuthState {
+            access_token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+            user_id: tokens.user_id,
+            is_premium_user: tokens.is_premium_user,
+            auto_refresh_enabled: true,
+            token_generation: 0,
+            obtained_at: None,
+        }));
+
+        let client = AnyListClient {
+            auth,
+            auth_event_callback: None,
+            client_identifier: generate_id(),
+            transport: self.build_transport()?,
+            user_data_cache: self.user_data_cache(),
+            offline_queue: self.open_offline_queue()?,
+            offline_replay: tokio::sync::Mutex::new(()),
+            token_store: self.token_store,
+            refreshing: tokio::sync::Mutex::new(()),
+            refresh_ahead: self.refresh_ahead,
+            endpoints: self.endpoints,
+            retry_policy: self.retry_policy,
+        };
+        Ok(client)
+    }
+
+    /// Build the client from the tokens in the [`token_store`](Self::token_store),
+    /// or return `None` if it has none (or no store is set).
+    ///
+    /// Like every token store access, the read runs on a blocking thread.
+    pub async fn from_stored_tokens(self) -> Result<Option<AnyListClient>> {
+        let Some(store) = self.token_store.clone() else {
+            return Ok(None);
+        };
+        let tokens = tokio::task::spawn_blocking(move || store.load())
+            .await
+            .map_err(|e| AnyListError::Other(format!("Token store task failed: {}", e)))??;
+        tokens
+            .map(|tokens| self.build_from_tokens(tokens))
+            .transpose()
+    }
+
+    fn user_data_cache(&self) -> Option<Mutex<UserDataCache>> {
+        self.cache_user_data
+            .then(|| Mutex::new(UserDataCache::default()))
+    }
+
+    fn open_offline_queue(&self) -> Result<Option<Mutex<OfflineQueue>>> {
+        self.offline_queue
+            .as_ref()
+            .map(|path| OfflineQueue::open(path).map(Mutex::new))
+            .transpose()
+    }
+
+    /// The configured transport, wrapped for recording or replaced for replay
+    fn build_transport(&self) -> Result<Arc<dyn Transport>> {
+        Ok(match &self.cassette {
+            None => Arc::clone(&self.transport),
+            Some(CassetteMode::Record(path)) => {
+                Arc::new(RecordingTransport::wrap(path, Arc::clone(&self.transport))?)
+            }
+            Some(CassetteMode::Replay(path)) => Arc::new(ReplayTransport::from_file(path)?),
+        })
+    }
+}
+
+fn trim_trailing_slash(mut url: String) -> String {
+    while url.ends_with('/') {
+        url.pop();
+    }
+    url
 }
 
 // ============================================================================
@@ -70,8 +413,23 @@ struct AuthState {
     user_id: String,
     is_premium_user: bool,
     auto_refresh_enabled: bool,
+    /// Bumped every time the tokens are refreshed
+    token_generation: u64,
+    /// When the server issued the access token to this client; `None` for
+    /// restored tokens, whose age is unknown
+    obtained_at: Option<DateTime<Utc>>,
 }
 
+/// Default for [`AnyListClientBuilder::refresh_ahead`]
+const DEFAULT_REFRESH_AHEAD: Duration = Duration::from_secs(60);
+
+/// Wait before retrying a background refresh that failed transiently
+const REFRESH_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Least time a freshly issued access token is kept before it is refreshed,
+/// even if it lives no longer than the refresh margin
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
 /// Main client for interacting with the AnyList API.
 ///
 /// Automatically manages authentication tokens and handles token refresh.
@@ -107,8 +465,25 @@ pub struct AnyListClient {
     auth_event_callback: Option<Arc<dyn Fn(AuthEvent) + Send + Sync>>,
     /// Unique client identifier (UUID)
     client_identifier: String,
-    /// HTTP client for making requests
-    client: reqwest::Client,
+    /// Transport for making HTTP requests
+    transport: Arc<dyn Transport>,
+    /// Base URLs for REST, auth, photo and realtime endpoints
+    endpoints: Endpoints,
+    /// How failed REST calls are retried
+    retry_policy: RetryPolicy,
+    /// Last user data response, if caching is enabled
+    pub(crate) user_data_cache: Option<Mutex<UserDataCache>>,
+    /// Mutations waiting to be sent, if the offline queue is enabled
+    pub(crate) offline_queue: Option<Mutex<OfflineQueue>>,
+    /// Held while the offline queue is replayed, so two replays never send
+    /// the same operations
+    pub(crate) offline_replay: tokio::sync::Mutex<()>,
+    /// Where tokens are saved whenever they change
+    token_store: Option<Arc<dyn TokenStore>>,
+    /// Held while a refresh is in flight, so concurrent refreshes share one
+    refreshing: tokio::sync::Mutex<()>,
+    /// How long before the access token expires to refresh it
+    refresh_ahead: Duration,
 }
 
 impl AnyListClient {
@@ -121,6 +496,14 @@ impl AnyListClient {
     /// * `email` - User's email address
     /// * `password` - User's password
     ///
+    /// # Errors
+    ///
+    /// [`AnyListError::InvalidCredentials`] for a wrong email or password,
+    /// [`AnyListError::AccountLocked`] for a locked or disabled account,
+    /// [`AnyListError::RateLimited`] after too many attempts,
+    /// [`AnyListError::ServerError`] if the service is having trouble and
+    /// [`AnyListError::ClientError`] for any other refusal.
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -136,26 +519,7 @@ impl AnyListClient {
     /// }
     /// ```
     pub async fn login(email: &str, password: &str) -> Result<Self> {
-        let client_identifier = generate_id();
-
-        let login_result = login(email, password, &client_identifier)
-            .await
-            .map_err(|e| AnyListError::AuthenticationFailed(e.to_string()))?;
-
-        let auth = Arc::new(Mutex::new(AuthState {
-            access_token: login_result.access_token,
-            refresh_token: login_result.refresh_token,
-            user_id: login_result.user_id,
-            is_premium_user: login_result.is_premium_user,
-            auto_refresh_enabled: true,
-        }));
-
-        Ok(Self {
-            auth,
-            auth_event_callback: None,
-            client_identifier,
-            client: reqwest::Client::new(),
-        })
+        Self::builder().login(email, password).await
     }
 
     /// Create an AnyList client from previously saved tokens.
@@ -182,20 +546,14 @@ impl AnyListClient {
     /// # }
     /// ```
     pub fn from_tokens(tokens: SavedTokens) -> Result<Self> {
-        let auth = Arc::new(Mutex::new(AuthState {
-            access_token: tokens.access_token,
-            refresh_token: tokens.refresh_token,
-            user_id: tokens.user_id,
-            is_premium_user: tokens.is_premium_user,
-            auto_refresh_enabled: true,
-        }));
+        Self::builder().build_from_tokens(tokens)
+    }
 
-        Ok(Self {
-            auth,
-            auth_event_callback: None,
-            client_identifier: generate_id(),
-            client: reqwest::Client::new(),
-        })
+    /// Create a builder for a client that talks to non-default servers.
+    ///
+    /// See [`AnyListClientBuilder`].
+    pub fn builder() -> AnyListClientBuilder {
+        AnyListClientBuilder::new()
     }
 
     /// Export tokens for persistent storage.
@@ -278,6 +636,37 @@ impl AnyListClient {
         self
     }
 
+    /// End the session locally: forget the tokens and clear the token store,
+    /// if one is attached.
+    ///
+    /// Every later request fails with [`AnyListError::AuthenticationFailed`].
+    /// The tokens are forgotten even if clearing the store fails.
+    ///
+    /// Server-side revocation isn't supported: AnyList has no known endpoint
+    /// for it, so this sends nothing and the refresh token stays valid on the
+    /// server and any saved copy of it keeps working.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(client: anylist_rs::AnyListClient) -> anylist_rs::Result<()> {
+    /// client.logout().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn logout(&self) -> Result<()> {
+        // Keep a refresh from bringing the tokens back
+        let _refreshing = self.refreshing.lock().await;
+        {
+            let mut auth = self.auth.lock().unwrap();
+            auth.access_token.clear();
+            auth.refresh_token.clear();
+            auth.auto_refresh_enabled = false;
+            auth.token_generation += 1;
+        }
+        self.with_token_store(|store| store.clear()).await
+    }
+
     /// Get the user ID for this client.
     pub fn user_id(&self) -> String {
         let auth = self.auth.lock().unwrap();
@@ -347,53 +736,152 @@ impl AnyListClient {
     where
         F: Fn(crate::realtime::SyncEvent) + Send + Sync + 'static,
     {
-        let mut sync = crate::realtime::RealtimeSync::new(Arc::clone(self), callback);
+        let mut sync = crate::realtime::RealtimeSync::new(
+            Arc::clone(self),
+            crate::realtime::RealtimeConfig::default(),
+            callback,
+        );
         sync.connect().await?;
         Ok(sync)
     }
 
+    /// When the current access token expires, if it says.
+    ///
+    /// AnyList access tokens are JWTs carrying an `exp` claim; a token
+    /// without one is refreshed only after the server rejects it.
+    pub fn access_token_expires_at(&self) -> Option<DateTime<Utc>> {
+        jwt_expiry(&self.auth.lock().unwrap().access_token)
+    }
+
+    /// Refresh the access token in the background shortly before it
+    /// expires, so requests and realtime connections never see it lapse.
+    ///
+    /// The task holds only a weak reference: it stops when the client is
+    /// dropped, when auto-refresh is disabled, when the refresh token is
+    /// rejected or when the access token has no expiry. Abort the returned
+    /// handle to stop it sooner.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use anylist_rs::AnyListClient;
+    /// use std::sync::Arc;
+    ///
+    /// # async fn example() -> anylist_rs::Result<()> {
+    /// let client = Arc::new(AnyListClient::login("user@example.com", "password").await?);
+    /// let refresher = client.start_token_refresh();
+    ///
+    /// // Long-running work...
+    ///
+    /// refresher.abort();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start_token_refresh(self: &Arc<Self>) -> JoinHandle<()> {
+        let client = Arc::downgrade(self);
+        tokio::spawn(async move {
+            while let Some((delay, generation)) = Self::next_refresh(&client) {
+                tokio::time::sleep(delay).await;
+
+                let Some(client) = client.upgrade() else {
+                    return;
+                };
+                if client.auth.lock().unwrap().token_generation != generation {
+                    // Refreshed in the meantime: schedule from the new token
+                    continue;
+                }
+                match client.refresh_tokens().await {
+                    Ok(()) => {}
+                    Err(AnyListError::AuthenticationFailed(_)) => return,
+                    Err(_) => {
+                        drop(client);
+                        tokio::time::sleep(REFRESH_RETRY_DELAY).await;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Time until the current access token is due for a refresh, and the
+    /// token generation that applies to
+    fn next_refresh(client: &Weak<Self>) -> Option<(Duration, u64)> {
+        let client = client.upgrade()?;
+        let (due, generation) = client.refresh_due()?;
+        let delay = (due - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+        Some((delay, generation))
+    }
+
+    /// When the current access token should be refreshed, if auto-refresh
+    /// is on and the token has an expiry.
+    ///
+    /// That is `refresh_ahead` before it expires, but no sooner than half
+    /// its lifetime (and at least [`MIN_REFRESH_INTERVAL`]) after it was
+    /// issued: a server handing out tokens that live no longer than the
+    /// margin would otherwise have them refreshed again straight away.
+    fn refresh_due(&self) -> Option<(DateTime<Utc>, u64)> {
+        let auth = self.auth.lock().unwrap();
+        if !auth.auto_refresh_enabled {
+            return None;
+        }
+        let expires_at = jwt_expiry(&auth.access_token)?;
+        let ahead = chrono::Duration::from_std(self.refresh_ahead).ok()?;
+        let mut due = expires_at - ahead;
+        if let Some(obtained_at) = auth.obtained_at {
+            let min_interval = chrono::Duration::from_std(MIN_REFRESH_INTERVAL).ok()?;
+            due = due.max(obtained_at + ((expires_at - obtained_at) / 2).max(min_interval));
+        }
+        Some((due, auth.token_generation))
+    }
+
     // ========================================================================
     // Internal authentication methods
     // ========================================================================
 
     /// Refresh the access token using the refresh token.
     ///
-    /// This calls /auth/token/refresh endpoint with multipart form data
+    /// This calls /auth/token/refresh endpoint with multipart form data.
+    /// Concurrent calls share one request: a call made while another
+    /// refresh is in flight waits for it and returns without refreshing
+    /// again.
     pub async fn refresh_tokens(&self) -> Result<()> {
+        let generation = self.auth.lock().unwrap().token_generation;
+        let _refreshing = self.refreshing.lock().await;
+
         let refresh_token = {
             let auth = self.auth.lock().unwrap();
+            if auth.token_generation != generation {
+                // The refresh we waited for replaced the tokens
+                return Ok(());
+            }
             auth.refresh_token.clone()
         };
 
-        let mut headers = HeaderMap::new();
-        headers.insert("X-AnyLeaf-API-Version", HeaderValue::from_static("3"));
-        headers.insert(
-            "X-AnyLeaf-Client-Identifier",
-            HeaderValue::from_str(&self.client_identifier).unwrap(),
-        );
-
-        let form = reqwest::multipart::Form::new().text("refresh_token", refresh_token);
-
         let response = self
-            .client
-            .post("https://www.anylist.com/auth/token/refresh")
-            .headers(headers)
-            .multipart(form)
-            .send()
+            .transport
+            .send(TransportRequest {
+                method: Method::POST,
+                url: format!("{}/auth/token/refresh", self.endpoints.auth_base_url),
+                headers: self.auth_request_headers(),
+                body: Some(MultipartForm::new().text("refresh_token", refresh_token)),
+            })
             .await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await?;
+        if response.status == 429 || response.status >= 500 {
+            // Transient failure: the refresh token may still be good
+            let error = AnyListError::from_response(&response);
+            self.notify_auth_event(AuthEvent::RefreshFailed(error.to_string()));
+            return Err(error);
+        }
+
+        if !response.is_success() {
             let error_msg = format!(
                 "Token refresh failed with status: {}, body: {}",
-                status, body
+                response.status,
+                String::from_utf8_lossy(&response.body)
             );
 
             // Notify callback of failure
-            if let Some(callback) = &self.auth_event_callback {
-                callback(AuthEvent::RefreshFailed(error_msg.clone()));
-            }
+            self.notify_auth_event(AuthEvent::RefreshFailed(error_msg.clone()));
 
             return Err(AnyListError::AuthenticationFailed(error_msg));
         }
@@ -404,21 +892,90 @@ impl AnyListClient {
             refresh_token: String,
         }
 
-        let token_response: RefreshResponse = response.json().await?;
+        let token_response: RefreshResponse =
+            serde_json::from_slice(&response.body).map_err(|e| {
+                AnyListError::InvalidResponse(format!("Invalid token refresh response: {}", e))
+            })?;
 
         // Update auth state
         {
             let mut auth = self.auth.lock().unwrap();
             auth.access_token = token_response.access_token;
             auth.refresh_token = token_response.refresh_token;
+            auth.token_generation += 1;
+            auth.obtained_at = Some(Utc::now());
+        }
+
+        // The old refresh token no longer works: keep the store current
+        if let Err(e) = self.save_tokens().await {
+            self.notify_auth_event(AuthEvent::TokenStoreFailed(e.to_string()));
         }
 
         // Notify callback
+        self.notify_auth_event(AuthEvent::TokensRefreshed);
+
+        Ok(())
+    }
+
+    /// Refresh first if the access token is due to expire, so the request
+    /// doesn't have to fail with a 401.
+    ///
+    /// Only a rejected refresh token is an error. Any other failure leaves
+    /// the current access token in place, since it may well still be valid.
+    pub(crate) async fn refresh_if_expiring(&self) -> Result<()> {
+        match self.refresh_due() {
+            Some((due, _)) if due <= Utc::now() => match self.refresh_tokens().await {
+                Err(e @ AnyListError::AuthenticationFailed(_)) => Err(e),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Generation of the current tokens, to tell whether they were
+    /// refreshed since
+    pub(crate) fn token_generation(&self) -> u64 {
+        self.auth.lock().unwrap().token_generation
+    }
+
+    /// Write the current tokens to the token store, if one is set
+    async fn save_tokens(&self) -> Result<()> {
+        let tokens = self.export_tokens()?;
+        self.with_token_store(move |store| store.save(&tokens))
+            .await
+    }
+
+    /// Run `f` on the token store, if one is set. Stores do file I/O and
+    /// may derive keys, so this runs on a blocking thread rather than the
+    /// runtime's.
+    async fn with_token_store(
+        &self,
+        f: impl FnOnce(&dyn TokenStore) -> Result<()> + Send + 'static,
+    ) -> Result<()> {
+        let Some(store) = self.token_store.clone() else {
+            return Ok(());
+        };
+        tokio::task::spawn_blocking(move || f(store.as_ref()))
+            .await
+            .map_err(|e| AnyListError::Other(format!("Token store task failed: {}", e)))?
+    }
+
+    /// Pass an event to the auth event callback, if one is set
+    pub(crate) fn notify_auth_event(&self, event: AuthEvent) {
         if let Some(callback) = &self.auth_event_callback {
-            callback(AuthEvent::TokensRefreshed);
+            callback(event);
         }
+    }
 
-        Ok(())
+    /// Headers for `/auth/token/...` requests, which carry no access token
+    fn auth_request_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-AnyLeaf-API-Version", HeaderValue::from_static("3"));
+        headers.insert(
+            "X-AnyLeaf-Client-Identifier",
+            HeaderValue::from_str(&self.client_identifier).unwrap(),
+        );
+        headers
     }
 
     /// Get default headers for API requests
@@ -446,7 +1003,44 @@ impl AnyListClient {
     pub(crate) async fn post(&self, endpoint: &str, body: Vec<u8>) -> Result<Vec<u8>> {
         // Delegate to post_multipart with standard "operations" field name
         // Note: endpoint here doesn't have leading slash, so we add one
-        self.post_multipart(&format!("/{}", endpoint), "operations", body).await
+        let result = self
+            .post_multipart(&format!("/{}", endpoint), "operations", body)
+            .await;
+
+        // Even a failed mutation may have been applied
+        for &section in UserDataSection::for_endpoint(endpoint) {
+            self.invalidate_user_data(section);
+        }
+
+        result
+    }
+
+    /// Post an operation list to a mutation endpoint.
+    ///
+    /// Decodes the `PBEditOperationResponse` and fails with
+    /// [`AnyListError::OperationNotProcessed`] if the server did not process
+    /// every operation in the list. With an offline queue, a list that can't
+    /// be sent is queued instead (see [`crate::offline`]).
+    pub(crate) async fn post_edit<M: OperationList>(
+        &self,
+        endpoint: &str,
+        operation_list: &M,
+    ) -> Result<OperationOutcome> {
+        if self.offline_queue.is_some() {
+            return self.post_edit_or_queue(endpoint, operation_list).await;
+        }
+        self.send_edit(endpoint, operation_list).await
+    }
+
+    /// Post an operation list, without queueing it on failure
+    pub(crate) async fn send_edit<M: OperationList>(
+        &self,
+        endpoint: &str,
+        operation_list: &M,
+    ) -> Result<OperationOutcome> {
+        let buf = encode_operation_list(operation_list)?;
+        let response = self.post(endpoint, buf).await?;
+        OperationOutcome::from_response(&response, &operation_list.operation_ids())
     }
 
     /// Make a POST request with custom multipart field name.
@@ -459,54 +1053,38 @@ impl AnyListClient {
         field_name: &str,
         body: Vec<u8>,
     ) -> Result<Vec<u8>> {
-        let url = format!("https://www.anylist.com{}", endpoint);
-        // Convert to owned String since we need it for both initial request and potential retry
-        let field_name_owned = field_name.to_string();
-
         // Create multipart form with the specified field name containing the protobuf data
-        let form = reqwest::multipart::Form::new()
-            .part(field_name_owned.clone(), reqwest::multipart::Part::bytes(body.clone()));
+        let form = MultipartForm::new().bytes(field_name, body);
+        self.post_multipart_form(endpoint, form).await
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(self.get_headers())
-            .multipart(form)
-            .send()
-            .await?;
+    /// Send one multipart POST, refreshing tokens and retrying once on 401
+    async fn post_form_refreshing(&self, url: &str, form: MultipartForm) -> Result<Vec<u8>> {
+        self.refresh_if_expiring().await?;
+        let generation = self.token_generation();
+        let response = self.send_post(url, form.clone()).await?;
 
         // Handle 401 with automatic token refresh
-        if response.status() == 401 {
+        if response.status == 401 {
             let auto_refresh = {
                 let auth = self.auth.lock().unwrap();
                 auth.auto_refresh_enabled
             };
 
             if auto_refresh {
-                // Try to refresh tokens
-                self.refresh_tokens().await?;
+                // Try to refresh tokens, unless another request already has
+                if self.token_generation() == generation {
+                    self.refresh_tokens().await?;
+                }
 
                 // Retry the request with new token
-                let retry_form = reqwest::multipart::Form::new()
-                    .part(field_name_owned, reqwest::multipart::Part::bytes(body));
-
-                let retry_response = self
-                    .client
-                    .post(&url)
-                    .headers(self.get_headers())
-                    .multipart(retry_form)
-                    .send()
-                    .await?;
+                let retry_response = self.send_post(url, form).await?;
 
-                if !retry_response.status().is_success() {
-                    return Err(AnyListError::NetworkError(format!(
-                        "Request failed after token refresh with status: {}",
-                        retry_response.status()
-                    )));
+                if !retry_response.is_success() {
+                    return Err(AnyListError::from_response(&retry_response));
                 }
 
-                let bytes = retry_response.bytes().await?;
-                return Ok(bytes.to_vec());
+                return Ok(retry_response.body);
             } else {
                 return Err(AnyListError::AuthenticationFailed(
                     "Unauthorized (auto-refresh disabled)".to_string(),
@@ -514,74 +1092,385 @@ impl AnyListClient {
             }
         }
 
-        if !response.status().is_success() {
-            return Err(AnyListError::NetworkError(format!(
-                "Request failed with status: {}",
-                response.status()
-            )));
+        if !response.is_success() {
+            return Err(AnyListError::from_response(&response));
         }
 
-        let bytes = response.bytes().await?;
-        Ok(bytes.to_vec())
+        Ok(response.body)
     }
 
     /// Make a POST request with a pre-built multipart form.
     ///
     /// Used for complex multipart requests like photo uploads where
-    /// we need more control over the form parts.
+    /// we need more control over the form parts. Like [`post_multipart`],
+    /// it refreshes tokens and retries on 401: the form is rebuilt from its
+    /// parts for every attempt.
     ///
-    /// Note: This method cannot automatically retry on 401 because
-    /// `reqwest::multipart::Form` is not `Clone`. If authentication
-    /// fails, the caller should retry the request.
+    /// [`post_multipart`]: Self::post_multipart
     pub(crate) async fn post_multipart_form(
         &self,
         endpoint: &str,
-        form: reqwest::multipart::Form,
+        form: MultipartForm,
     ) -> Result<Vec<u8>> {
-        let url = format!("https://www.anylist.com{}", endpoint);
+        let url = format!("{}{}", self.endpoints.api_base_url, endpoint);
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(self.get_headers())
-            .multipart(form)
-            .send()
-            .await?;
+        self.retry_policy
+            .run(|| self.post_form_refreshing(&url, form.clone()))
+            .await
+    }
 
-        if response.status() == 401 {
-            return Err(AnyListError::AuthenticationFailed(
-                "Unauthorized - please refresh tokens and retry".to_string(),
-            ));
-        }
+    /// Send an authenticated multipart POST through the transport
+    async fn send_post(&self, url: &str, form: MultipartForm) -> Result<TransportResponse> {
+        self.transport
+            .send(TransportRequest {
+                method: Method::POST,
+                url: url.to_string(),
+                headers: self.get_headers(),
+                body: Some(form),
+            })
+            .await
+    }
 
-        if !response.status().is_success() {
-            return Err(AnyListError::NetworkError(format!(
-                "Request failed with status: {}",
-                response.status()
-            )));
-        }
+    /// Build the realtime listener URL, authenticated with the current access token
+    pub(crate) fn websocket_url(&self) -> String {
+        let access_token = {
+            let auth = self.auth.lock().unwrap();
+            auth.access_token.clone()
+        };
+
+        format!(
+            "{}?client_id={}&access_token={}",
+            self.endpoints.websocket_url,
+            urlencoding::encode(&self.client_identifier),
+            urlencoding::encode(&access_token)
+        )
+    }
 
-        let bytes = response.bytes().await?;
-        Ok(bytes.to_vec())
+    /// Build the public CDN URL of a recipe photo
+    pub(crate) fn photo_url(&self, photo_id: &str) -> String {
+        format!("{}/{}.jpg", self.endpoints.photos_base_url, photo_id)
     }
 
     /// Make an unauthenticated GET request to an absolute URL and return the body bytes.
     ///
     /// Used for public CDN fetches (e.g. recipe photos on photos.anylist.com),
-    /// which don't require auth headers but should share this client's
-    /// connection pool.
+    /// which don't require auth headers but should go through this client's
+    /// transport.
     pub(crate) async fn get_bytes(&self, url: &str) -> Result<Vec<u8>> {
-        let response = self.client.get(url).send().await?;
-
-        if !response.status().is_success() {
-            return Err(AnyListError::NetworkError(format!(
-                "GET {} failed with status: {}",
-                url,
-                response.status()
-            )));
+        self.retry_policy
+            .run(|| async {
+                let response = self
+                    .transport
+                    .send(TransportRequest {
+                        method: Method::GET,
+                        url: url.to_string(),
+                        headers: HeaderMap::new(),
+                        body: None,
+                    })
+                    .await?;
+
+                if !response.is_success() {
+                    return Err(AnyListError::from_response(&response));
+                }
+
+                Ok(response.body)
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::BoxFuture;
+    use std::collections::VecDeque;
+
+    /// Returns scripted responses in order and records every request
+    #[derive(Default)]
+    struct StubTransport {
+        responses: Mutex<VecDeque<TransportResponse>>,
+        requests: Arc<Mutex<Vec<TransportRequest>>>,
+    }
+
+    impl StubTransport {
+        fn respond(self, status: u16, body: &[u8]) -> Self {
+            self.responses.lock().unwrap().push_back(TransportResponse {
+                status,
+                headers: HeaderMap::new(),
+                body: body.to_vec(),
+            });
+            self
+        }
+    }
+
+    impl Transport for StubTransport {
+        fn send(&self, request: TransportRequest) -> BoxFuture<'_, Result<TransportResponse>> {
+            self.requests.lock().unwrap().push(request);
+            let response = self.responses.lock().unwrap().pop_front();
+            Box::pin(async move {
+                // Let concurrent requests interleave, as a network would
+                tokio::task::yield_now().await;
+                response.ok_or_else(|| AnyListError::NetworkError("no response".to_string()))
+            })
+        }
+    }
+
+    fn test_tokens() -> SavedTokens {
+        SavedTokens::new("access token", "refresh", "user-1", false)
+    }
+
+    #[test]
+    fn test_builder_defaults_to_production_urls() {
+        let client = AnyListClient::from_tokens(test_tokens()).unwrap();
+
+        assert_eq!(client.endpoints, Endpoints::default());
+        assert_eq!(
+            client.photo_url("abc"),
+            "https://photos.anylist.com/abc.jpg"
+        );
+        assert!(client
+            .websocket_url()
+            .starts_with("wss://www.anylist.com/data/add-user-listener?client_id="));
+    }
+
+    #[test]
+    fn test_builder_overrides_urls() {
+        let mut client = AnyListClient::builder()
+            .api_base_url("http://127.0.0.1:8080/")
+            .auth_base_url("http://127.0.0.1:8081")
+            .photos_base_url("http://127.0.0.1:8082/photos/")
+            .websocket_url("ws://127.0.0.1:8083/listen")
+            .build_from_tokens(test_tokens())
+            .unwrap();
+        client.set_client_identifier("client-1".to_string());
+
+        assert_eq!(client.endpoints.api_base_url, "http://127.0.0.1:8080");
+        assert_eq!(client.endpoints.auth_base_url, "http://127.0.0.1:8081");
+        assert_eq!(
+            client.photo_url("abc"),
+            "http://127.0.0.1:8082/photos/abc.jpg"
+        );
+        assert_eq!(
+            client.websocket_url(),
+            "ws://127.0.0.1:8083/listen?client_id=client-1&access_token=access%20token"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_refreshes_tokens_and_retries_on_401() {
+        let transport = StubTransport::default()
+            .respond(401, b"")
+            .respond(
+                200,
+                br#"{"access_token":"new-access","refresh_token":"new-refresh"}"#,
+            )
+            .respond(200, b"ok");
+        let requests = Arc::clone(&transport.requests);
+
+        let client = AnyListClient::builder()
+            .api_base_url("http://api.test")
+            .auth_base_url("http://auth.test")
+            .transport(transport)
+            .from_tokens(test_tokens())
+            .await
+            .unwrap();
+
+        let body = client.post("data/user-data/get", vec![1, 2]).await.unwrap();
+        assert_eq!(body, b"ok");
+        assert_eq!(
+            client.export_tokens().unwrap().refresh_token(),
+            "new-refresh"
+        );
+
+        let requests = requests.lock().unwrap();
+        let urls: Vec<&str> = requests.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "http://api.test/data/user-data/get",
+                "http://auth.test/auth/token/refresh",
+                "http://api.test/data/user-data/get",
+            ]
+        );
+        assert_eq!(
+            requests[0]
+                .body
+                .as_ref()
+                .unwrap()
+                .part("operations")
+                .unwrap()
+                .data(),
+            &[1, 2]
+        );
+        assert_eq!(
+            requests[2].headers[AUTHORIZATION].to_str().unwrap(),
+            "Bearer new-access"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_keeps_status_and_body_of_failed_response() {
+        let transport = StubTransport::default()
+            .respond(503, b"maintenance")
+            .respond(400, b"bad operation");
+
+        let client = AnyListClient::builder()
+            .transport(transport)
+            .retry_policy(RetryPolicy::none())
+            .from_tokens(test_tokens())
+            .await
+            .unwrap();
+
+        let err = client.post("data/user-data/get", vec![]).await.unwrap_err();
+        assert!(err.is_retryable());
+        match err {
+            AnyListError::ServerError { status, body } => {
+                assert_eq!(status, 503);
+                assert_eq!(body, "maintenance");
+            }
+            other => panic!("expected ServerError, got {:?}", other),
+        }
+
+        let err = client.post("data/user-data/get", vec![]).await.unwrap_err();
+        assert_eq!(err.status(), Some(400));
+        assert!(!err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_post_retries_transient_failures() {
+        let transport = StubTransport::default()
+            .respond(502, b"")
+            .respond(503, b"")
+            .respond(200, b"ok");
+        let requests = Arc::clone(&transport.requests);
+
+        let client = AnyListClient::builder()
+            .transport(transport)
+            .retry_policy(
+                RetryPolicy::new()
+                    .max_attempts(3)
+                    .initial_delay(std::time::Duration::from_millis(1)),
+            )
+            .from_tokens(test_tokens())
+            .await
+            .unwrap();
+
+        let body = client.post("data/user-data/get", vec![]).await.unwrap();
+        assert_eq!(body, b"ok");
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_401s_share_one_refresh() {
+        let transport = StubTransport::default()
+            .respond(401, b"")
+            .respond(401, b"")
+            .respond(401, b"")
+            .respond(
+                200,
+                br#"{"access_token":"new-access","refresh_token":"new-refresh"}"#,
+            )
+            .respond(200, b"ok")
+            .respond(200, b"ok")
+            .respond(200, b"ok");
+        let requests = Arc::clone(&transport.requests);
+
+        let client = AnyListClient::builder()
+            .auth_base_url("http://auth.test")
+            .transport(transport)
+            .from_tokens(test_tokens())
+            .await
+            .unwrap();
+
+        let (a, b, c) = tokio::join!(
+            client.post("data/user-data/get", vec![]),
+            client.post("data/user-data/get", vec![]),
+            client.post("data/user-data/get", vec![]),
+        );
+        assert_eq!(a.unwrap(), b"ok");
+        assert_eq!(b.unwrap(), b"ok");
+        assert_eq!(c.unwrap(), b"ok");
+
+        let refreshes = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.url == "http://auth.test/auth/token/refresh")
+            .count();
+        assert_eq!(refreshes, 1);
+    }
+
+    #[test]
+    fn test_access_token_expiry_is_read_from_the_jwt() {
+        // {"exp":1700000000}
+        let jwt = "eyJhbGciOiJIUzI1NiJ9.eyJleHAiOjE3MDAwMDAwMDB9.signature";
+        let client =
+            AnyListClient::from_tokens(SavedTokens::new(jwt, "refresh", "user-1", false)).unwrap();
+        assert_eq!(
+            client.access_token_expires_at(),
+            DateTime::from_timestamp(1_700_000_000, 0)
+        );
+
+        let client = AnyListClient::from_tokens(test_tokens()).unwrap();
+        assert_eq!(client.access_token_expires_at(), None);
+    }
+
+    #[tokio::test]
+    async fn test_login_reports_locked_accounts() {
+        for (status, body) in [(423, "too many attempts"), (403, "Account disabled")] {
+            let transport = StubTransport::default().respond(status, body.as_bytes());
+
+            let result = AnyListClient::builder()
+                .transport(transport)
+                .login("user@example.com", "password")
+                .await;
+            match result {
+                Err(AnyListError::AccountLocked {
+                    status: locked_status,
+                    body: locked_body,
+                }) => {
+                    assert_eq!(locked_status, status);
+                    assert_eq!(locked_body, body);
+                }
+                Err(other) => panic!("expected AccountLocked, got {:?}", other),
+                Ok(_) => panic!("expected AccountLocked, got a client"),
+            }
         }
+    }
+
+    #[tokio::test]
+    async fn test_login_keeps_status_of_other_refusals() {
+        let transport = StubTransport::default().respond(403, b"client not allowed");
+
+        let result = AnyListClient::builder()
+            .transport(transport)
+            .login("user@example.com", "password")
+            .await;
+        match result {
+            Err(AnyListError::ClientError { status, body }) => {
+                assert_eq!(status, 403);
+                assert_eq!(body, "client not allowed");
+            }
+            Err(other) => panic!("expected ClientError, got {:?}", other),
+            Ok(_) => panic!("expected ClientError, got a client"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_logout_forgets_tokens_without_contacting_the_server() {
+        let transport = StubTransport::default();
+        let requests = transport.requests.clone();
+
+        let client = AnyListClient::builder()
+            .transport(transport)
+            .from_tokens(test_tokens())
+            .await
+            .unwrap();
 
-        let bytes = response.bytes().await?;
-        Ok(bytes.to_vec())
+        client.logout().await.unwrap();
+        assert!(requests.lock().unwrap().is_empty());
+        let tokens = client.export_tokens().unwrap();
+        assert_eq!(tokens.access_token(), "");
+        assert_eq!(tokens.refresh_token(), "");
     }
 }
@@ -1,11 +1,12 @@
+use crate::cache::UserDataSection;
 use crate::client::AnyListClient;
 use crate::error::{AnyListError, Result};
+use crate::outcome::OperationOutcome;
 use crate::protobuf::anylist::{
     pb_operation_metadata::OperationClass, PbOperationMetadata, PbRecipeCollection,
     PbRecipeCollectionSettings, PbRecipeOperation, PbRecipeOperationList,
 };
 use crate::utils::{current_timestamp, generate_id};
-use prost::Message;
 use serde_derive::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -31,7 +32,9 @@ impl RecipeCollection {
 
 impl AnyListClient {
     pub async fn get_recipe_collections(&self) -> Result<Vec<RecipeCollection>> {
-        let data = self.get_user_data().await?;
+        let data = self
+            .cached_user_data(&[UserDataSection::RecipeData])
+            .await?;
         let collections = match data.recipe_data_response {
             Some(ref res) => res
                 .recipe_collections
@@ -90,12 +93,8 @@ impl AnyListClient {
             operations: vec![operation],
         };
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/user-recipe-data/update", buf).await?;
+        self.post_edit("data/user-recipe-data/update", &operation_list)
+            .await?;
 
         Ok(RecipeCollection {
             id: collection_id,
@@ -109,7 +108,7 @@ impl AnyListClient {
     /// # Arguments
     ///
     /// * `collection_id` - The ID of the collection to delete
-    pub async fn delete_recipe_collection(&self, collection_id: &str) -> Result<()> {
+    pub async fn delete_recipe_collection(&self, collection_id: &str) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         let operation = PbRecipeOperation {
@@ -133,13 +132,8 @@ impl AnyListClient {
             operations: vec![operation],
         };
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/user-recipe-data/update", buf).await?;
-        Ok(())
+        self.post_edit("data/user-recipe-data/update", &operation_list)
+            .await
     }
 
     /// Add a recipe to a collection
@@ -152,7 +146,7 @@ impl AnyListClient {
         &self,
         collection_id: &str,
         recipe_id: &str,
-    ) -> Result<()> {
+    ) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         // Get the current collection
@@ -201,13 +195,8 @@ impl AnyListClient {
             operations: vec![operation],
         };
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/user-recipe-data/update", buf).await?;
-        Ok(())
+        self.post_edit("data/user-recipe-data/update", &operation_list)
+            .await
     }
 
     /// Remove a recipe from a collection
@@ -220,7 +209,7 @@ impl AnyListClient {
         &self,
         collection_id: &str,
         recipe_id: &str,
-    ) -> Result<()> {
+    ) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         // Get the current collection
@@ -271,12 +260,7 @@ impl AnyListClient {
             operations: vec![operation],
         };
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/user-recipe-data/update", buf).await?;
-        Ok(())
+        self.post_edit("data/user-recipe-data/update", &operation_list)
+            .await
     }
 }
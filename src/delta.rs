@@ -0,0 +1,661 @@
+//! Incremental user data sync.
+//!
+//! `data/user-data/get` answers with everything unless the client says what
+//! it already has. [`UserDataState`] holds a copy of the user data, describes
+//! it to the server as a `PBUserDataClientTimestamps`, and merges the deltas
+//! that come back:
+//!
+//! - shopping lists arrive as `newLists`, `modifiedLists` and
+//!   `unmodifiedIds`; any list in none of them has been deleted
+//! - per-list stores, filters, category groups and categorization rules,
+//!   list folders, starter lists and meal plan events arrive as changed
+//!   objects plus the IDs of deleted ones, unless the server marks the
+//!   section as a full sync
+//! - every other section is either absent (unchanged) or sent in full
+//!
+//! After a merge the state always looks like a full response, with every
+//! list in `newLists`, so it can be read like [`AnyListClient::get_user_data`]
+//! output.
+
+use crate::client::AnyListClient;
+use crate::error::Result;
+use crate::protobuf::anylist::{
+    PbCalendarResponse, PbListCategoryGroupResponse, PbListFolderTimestamps, PbListFoldersResponse,
+    PbListResponse, PbLogicalTimestamp, PbLogicalTimestampList, PbShoppingListsResponse,
+    PbStarterListBatchResponse, PbStarterListResponse, PbTimestamp, PbTimestampList,
+    PbUserDataClientTimestamps, PbUserDataResponse,
+};
+use prost::Message;
+
+/// A local copy of the user data, kept current with incremental syncs.
+///
+/// # Example
+///
+/// ```no_run
+/// use anylist_rs::{AnyListClient, UserDataState};
+///
+/// # async fn example(client: AnyListClient) -> anylist_rs::Result<()> {
+/// let mut state = UserDataState::new();
+///
+/// // The first sync downloads everything
+/// client.sync_user_data(&mut state).await?;
+///
+/// // Later syncs only download what changed since
+/// client.sync_user_data(&mut state).await?;
+/// let lists = state.data().shopping_lists_response.as_ref();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserDataState {
+    data: PbUserDataResponse,
+}
+
+impl UserDataState {
+    /// An empty state; syncing it downloads everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from a full `data/user-data/get` response
+    pub fn from_data(data: PbUserDataResponse) -> Self {
+        let mut state = Self::new();
+        state.merge(data);
+        state
+    }
+
+    pub fn data(&self) -> &PbUserDataResponse {
+        &self.data
+    }
+
+    pub fn into_data(self) -> PbUserDataResponse {
+        self.data
+    }
+
+    /// What this state already has, to send with a sync request
+    pub fn client_timestamps(&self) -> PbUserDataClientTimestamps {
+        let data = &self.data;
+        let lists = data.shopping_lists_response.as_ref();
+        let starter_lists = data.starter_lists_response.as_ref();
+
+        PbUserDataClientTimestamps {
+            shopping_list_timestamps: lists.map(|r| PbTimestampList {
+                timestamps: r
+                    .new_lists
+                    .iter()
+                    .map(|l| timestamp(&l.identifier, l.timestamp))
+                    .collect(),
+            }),
+            shopping_list_logical_timestamps: lists.map(|r| PbLogicalTimestampList {
+                timestamps: r
+                    .list_responses
+                    .iter()
+                    .filter_map(|l| {
+                        Some(PbLogicalTimestamp {
+                            identifier: l.list_id.clone(),
+                            logical_timestamp: Some(l.logical_timestamp?),
+                            description: None,
+                        })
+                    })
+                    .collect(),
+            }),
+            list_folder_timestamps: data.list_folders_response.as_ref().map(|r| {
+                PbListFolderTimestamps {
+                    root_folder_id: r.root_folder_id.clone(),
+                    folder_timestamps: r
+                        .list_folders
+                        .iter()
+                        .map(|f| timestamp(&f.identifier, f.timestamp))
+                        .collect(),
+                }
+            }),
+            user_recipe_data_timestamp: data.recipe_data_response.as_ref().map(|r| PbTimestamp {
+                identifier: r.recipe_data_id.clone(),
+                timestamp: r.timestamp,
+            }),
+            meal_planning_calendar_timestamp: data.meal_planning_calendar_response.as_ref().map(
+                |r| PbLogicalTimestamp {
+                    identifier: Some(r.calendar_id.clone()),
+                    logical_timestamp: r.logical_timestamp,
+                    description: None,
+                },
+            ),
+            categorized_items_timestamp: data
+                .categorized_items_response
+                .as_ref()
+                .and_then(|r| r.timestamp.clone()),
+            user_categories_timestamp: data
+                .user_categories_response
+                .as_ref()
+                .map(|r| timestamp(&r.identifier, r.timestamp)),
+            starter_list_timestamps: starter_lists
+                .and_then(|r| r.user_lists_response.as_ref())
+                .map(starter_list_timestamps),
+            recent_item_timestamps: starter_lists
+                .and_then(|r| r.recent_item_lists_response.as_ref())
+                .map(starter_list_timestamps),
+            favorite_item_timestamps: starter_lists
+                .and_then(|r| r.favorite_item_lists_response.as_ref())
+                .map(starter_list_timestamps),
+            ordered_starter_list_ids_timestamp: data
+                .ordered_starter_list_ids_response
+                .as_ref()
+                .map(|r| PbTimestamp {
+                    identifier: None,
+                    timestamp: r.timestamp,
+                }),
+            list_settings_timestamp: data
+                .list_settings_response
+                .as_ref()
+                .and_then(|r| r.timestamp.clone()),
+            starter_list_settings_timestamp: data
+                .starter_list_settings_response
+                .as_ref()
+                .and_then(|r| r.timestamp.clone()),
+            mobile_app_settings_timestamp: data
+                .mobile_app_settings_response
+                .as_ref()
+                .map(|r| timestamp(&r.identifier, r.timestamp)),
+        }
+    }
+
+    /// Merge a response to a sync request into this state
+    pub fn merge(&mut self, delta: PbUserDataResponse) {
+        let data = &mut self.data;
+
+        if let Some(lists) = delta.shopping_lists_response {
+            merge_shopping_lists(
+                data.shopping_lists_response
+                    .get_or_insert_with(Default::default),
+                lists,
+            );
+        }
+        if let Some(folders) = delta.list_folders_response {
+            merge_list_folders(&mut data.list_folders_response, folders);
+        }
+        if let Some(calendar) = delta.meal_planning_calendar_response {
+            merge_calendar(&mut data.meal_planning_calendar_response, calendar);
+        }
+        if let Some(starter_lists) = delta.starter_lists_response {
+            let held = data
+                .starter_lists_response
+                .get_or_insert_with(Default::default);
+            merge_starter_lists(
+                &mut held.user_lists_response,
+                starter_lists.user_lists_response,
+            );
+            merge_starter_lists(
+                &mut held.recent_item_lists_response,
+                starter_lists.recent_item_lists_response,
+            );
+            merge_starter_lists(
+                &mut held.favorite_item_lists_response,
+                starter_lists.favorite_item_lists_response,
+            );
+            if starter_lists.has_migrated_user_favorites.is_some() {
+                held.has_migrated_user_favorites = starter_lists.has_migrated_user_favorites;
+            }
+        }
+
+        // The remaining sections are always sent whole
+        replace(&mut data.recipe_data_response, delta.recipe_data_response);
+        replace(
+            &mut data.categorized_items_response,
+            delta.categorized_items_response,
+        );
+        replace(
+            &mut data.user_categories_response,
+            delta.user_categories_response,
+        );
+        replace(
+            &mut data.ordered_starter_list_ids_response,
+            delta.ordered_starter_list_ids_response,
+        );
+        replace(
+            &mut data.list_settings_response,
+            delta.list_settings_response,
+        );
+        replace(
+            &mut data.starter_list_settings_response,
+            delta.starter_list_settings_response,
+        );
+        replace(
+            &mut data.mobile_app_settings_response,
+            delta.mobile_app_settings_response,
+        );
+    }
+}
+
+impl AnyListClient {
+    /// Bring `state` up to date, downloading only what changed since its
+    /// last sync.
+    ///
+    /// The client's known timestamps are posted as the `timestamps` field of
+    /// `data/user-data/get`.
+    pub async fn sync_user_data(&self, state: &mut UserDataState) -> Result<()> {
+        let delta = self.get_user_data_delta(&state.client_timestamps()).await?;
+        state.merge(delta);
+        Ok(())
+    }
+
+    /// Download the changes since `timestamps`
+    pub(crate) async fn get_user_data_delta(
+        &self,
+        timestamps: &PbUserDataClientTimestamps,
+    ) -> Result<PbUserDataResponse> {
+        let bytes = self
+            .post_multipart(
+                "/data/user-data/get",
+                "timestamps",
+                timestamps.encode_to_vec(),
+            )
+            .await?;
+        Ok(PbUserDataResponse::decode(bytes.as_ref())?)
+    }
+}
+
+fn timestamp(identifier: &str, timestamp: Option<f64>) -> PbTimestamp {
+    PbTimestamp {
+        identifier: Some(identifier.to_string()),
+        timestamp,
+    }
+}
+
+fn starter_list_timestamps(batch: &PbStarterListBatchResponse) -> PbTimestampList {
+    PbTimestampList {
+        timestamps: batch
+            .list_responses
+            .iter()
+            .filter_map(|r| r.starter_list.as_ref())
+            .map(|l| timestamp(&l.identifier, l.timestamp))
+            .collect(),
+    }
+}
+
+fn replace<T>(held: &mut Option<T>, delta: Option<T>) {
+    if delta.is_some() {
+        *held = delta;
+    }
+}
+
+/// Replace items with the same ID as an update, append the rest
+fn upsert<T>(items: &mut Vec<T>, updates: Vec<T>, id: impl Fn(&T) -> Option<&str>) {
+    for update in updates {
+        match items.iter().position(|item| id(item) == id(&update)) {
+            Some(index) => items[index] = update,
+            None => items.push(update),
+        }
+    }
+}
+
+fn remove_ids<T>(items: &mut Vec<T>, deleted: &[String], id: impl Fn(&T) -> Option<&str>) {
+    items.retain(|item| !id(item).is_some_and(|id| deleted.iter().any(|d| d == id)));
+}
+
+fn merge_shopping_lists(held: &mut PbShoppingListsResponse, delta: PbShoppingListsResponse) {
+    let mut lists = std::mem::take(&mut held.new_lists);
+    lists.retain(|l| delta.unmodified_ids.contains(&l.identifier));
+    upsert(
+        &mut lists,
+        delta
+            .new_lists
+            .into_iter()
+            .chain(delta.modified_lists)
+            .collect(),
+        |l| Some(l.identifier.as_str()),
+    );
+    if !delta.ordered_ids.is_empty() {
+        lists.sort_by_key(|l| {
+            delta
+                .ordered_ids
+                .iter()
+                .position(|id| *id == l.identifier)
+                .unwrap_or(usize::MAX)
+        });
+    }
+
+    held.list_responses.retain(|r| {
+        lists
+            .iter()
+            .any(|l| Some(&l.identifier) == r.list_id.as_ref())
+    });
+    for response in delta.list_responses {
+        match held
+            .list_responses
+            .iter_mut()
+            .find(|r| r.list_id == response.list_id)
+        {
+            Some(existing) => merge_list_response(existing, response),
+            None => held.list_responses.push(response),
+        }
+    }
+
+    held.new_lists = lists;
+    held.modified_lists.clear();
+    held.unmodified_ids.clear();
+    held.unknown_ids.clear();
+    // The order is applied to `new_lists` and full syncs are merged; neither
+    // marker means anything in the held state
+    held.ordered_ids.clear();
+    for response in &mut held.list_responses {
+        response.is_full_sync = None;
+    }
+}
+
+fn merge_list_response(held: &mut PbListResponse, delta: PbListResponse) {
+    if delta.is_full_sync.unwrap_or(false) {
+        *held = delta;
+        return;
+    }
+
+    if delta.logical_timestamp.is_some() {
+        held.logical_timestamp = delta.logical_timestamp;
+    }
+
+    upsert(&mut held.stores, delta.stores, |s| {
+        Some(s.identifier.as_str())
+    });
+    remove_ids(&mut held.stores, &delta.deleted_store_ids, |s| {
+        Some(s.identifier.as_str())
+    });
+
+    upsert(&mut held.store_filters, delta.store_filters, |f| {
+        Some(f.identifier.as_str())
+    });
+    remove_ids(
+        &mut held.store_filters,
+        &delta.deleted_store_filter_ids,
+        |f| Some(f.identifier.as_str()),
+    );
+
+    upsert(
+        &mut held.categorization_rules,
+        delta.categorization_rules,
+        |r| r.identifier.as_deref(),
+    );
+    remove_ids(
+        &mut held.categorization_rules,
+        &delta.deleted_categorization_rule_ids,
+        |r| r.identifier.as_deref(),
+    );
+
+    for group_response in delta.category_group_responses {
+        merge_category_group(&mut held.category_group_responses, group_response);
+    }
+    remove_ids(
+        &mut held.category_group_responses,
+        &delta.deleted_category_group_ids,
+        |r| r.category_group.as_ref()?.identifier.as_deref(),
+    );
+}
+
+fn merge_category_group(
+    held: &mut Vec<PbListCategoryGroupResponse>,
+    delta: PbListCategoryGroupResponse,
+) {
+    let group_id = |r: &PbListCategoryGroupResponse| {
+        r.category_group.as_ref().and_then(|g| g.identifier.clone())
+    };
+    let Some(existing) = held.iter_mut().find(|r| group_id(r) == group_id(&delta)) else {
+        held.push(delta);
+        return;
+    };
+
+    if let Some(mut group) = delta.category_group {
+        let mut categories = existing
+            .category_group
+            .take()
+            .map(|g| g.categories)
+            .unwrap_or_default();
+        upsert(
+            &mut categories,
+            std::mem::take(&mut group.categories),
+            |c| c.identifier.as_deref(),
+        );
+        group.categories = categories;
+        existing.category_group = Some(group);
+    }
+    if let Some(group) = existing.category_group.as_mut() {
+        remove_ids(&mut group.categories, &delta.deleted_category_ids, |c| {
+            c.identifier.as_deref()
+        });
+    }
+}
+
+fn merge_list_folders(held: &mut Option<PbListFoldersResponse>, delta: PbListFoldersResponse) {
+    let held = match held {
+        Some(held) if !delta.includes_all_folders.unwrap_or(false) => held,
+        _ => {
+            *held = Some(delta);
+            return;
+        }
+    };
+
+    if delta.list_data_id.is_some() {
+        held.list_data_id = delta.list_data_id;
+    }
+    if delta.root_folder_id.is_some() {
+        held.root_folder_id = delta.root_folder_id;
+    }
+    if delta.has_migrated_list_ordering.is_some() {
+        held.has_migrated_list_ordering = delta.has_migrated_list_ordering;
+    }
+    upsert(&mut held.list_folders, delta.list_folders, |f| {
+        Some(f.identifier.as_str())
+    });
+    remove_ids(&mut held.list_folders, &delta.deleted_folder_ids, |f| {
+        Some(f.identifier.as_str())
+    });
+}
+
+fn merge_calendar(held: &mut Option<PbCalendarResponse>, delta: PbCalendarResponse) {
+    let held = match held {
+        Some(held)
+            if held.calendar_id == delta.calendar_id && !delta.is_full_sync.unwrap_or(false) =>
+        {
+            held
+        }
+        _ => {
+            *held = Some(PbCalendarResponse {
+                is_full_sync: None,
+                ..delta
+            });
+            return;
+        }
+    };
+
+    if delta.logical_timestamp.is_some() {
+        held.logical_timestamp = delta.logical_timestamp;
+    }
+    upsert(&mut held.events, delta.events, |e| {
+        Some(e.identifier.as_str())
+    });
+    remove_ids(&mut held.events, &delta.deleted_event_ids, |e| {
+        Some(e.identifier.as_str())
+    });
+    upsert(&mut held.labels, delta.labels, |l| {
+        Some(l.identifier.as_str())
+    });
+    remove_ids(&mut held.labels, &delta.deleted_label_ids, |l| {
+        Some(l.identifier.as_str())
+    });
+}
+
+fn merge_starter_lists(
+    held: &mut Option<PbStarterListBatchResponse>,
+    delta: Option<PbStarterListBatchResponse>,
+) {
+    let Some(delta) = delta else {
+        return;
+    };
+    let held = match held {
+        Some(held) if !delta.includes_all_lists.unwrap_or(false) => held,
+        _ => {
+            *held = Some(delta);
+            return;
+        }
+    };
+
+    fn id(response: &PbStarterListResponse) -> Option<&str> {
+        response
+            .starter_list
+            .as_ref()
+            .map(|l| l.identifier.as_str())
+    }
+    upsert(&mut held.list_responses, delta.list_responses, id);
+    remove_ids(&mut held.list_responses, &delta.unknown_list_ids, id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::anylist::{PbCalendarEvent, PbShoppingList, PbStore};
+
+    fn list(id: &str, name: &str, timestamp: f64) -> PbShoppingList {
+        PbShoppingList {
+            identifier: id.to_string(),
+            name: Some(name.to_string()),
+            timestamp: Some(timestamp),
+            ..Default::default()
+        }
+    }
+
+    fn store(id: &str, name: &str) -> PbStore {
+        PbStore {
+            identifier: id.to_string(),
+            name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn full_state() -> UserDataState {
+        UserDataState::from_data(PbUserDataResponse {
+            shopping_lists_response: Some(PbShoppingListsResponse {
+                new_lists: vec![list("a", "Groceries", 1.0), list("b", "Hardware", 1.0)],
+                list_responses: vec![PbListResponse {
+                    list_id: Some("a".to_string()),
+                    logical_timestamp: Some(3),
+                    stores: vec![store("s1", "Costco"), store("s2", "Safeway")],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            meal_planning_calendar_response: Some(PbCalendarResponse {
+                calendar_id: "cal".to_string(),
+                logical_timestamp: Some(7),
+                events: vec![PbCalendarEvent {
+                    identifier: "e1".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_client_timestamps_describe_held_data() {
+        let timestamps = full_state().client_timestamps();
+
+        let lists = timestamps.shopping_list_timestamps.unwrap().timestamps;
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0].identifier.as_deref(), Some("a"));
+        assert_eq!(lists[0].timestamp, Some(1.0));
+
+        let logical = timestamps
+            .shopping_list_logical_timestamps
+            .unwrap()
+            .timestamps;
+        assert_eq!(logical[0].logical_timestamp, Some(3));
+
+        let calendar = timestamps.meal_planning_calendar_timestamp.unwrap();
+        assert_eq!(calendar.logical_timestamp, Some(7));
+        assert!(timestamps.user_recipe_data_timestamp.is_none());
+    }
+
+    #[test]
+    fn test_merge_applies_modified_new_and_deleted_lists() {
+        let mut state = full_state();
+        state.merge(PbUserDataResponse {
+            shopping_lists_response: Some(PbShoppingListsResponse {
+                modified_lists: vec![list("a", "Weekly Groceries", 2.0)],
+                new_lists: vec![list("c", "Party", 2.0)],
+                unknown_ids: vec!["b".to_string()],
+                ordered_ids: vec!["c".to_string(), "a".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let lists = state.data().shopping_lists_response.as_ref().unwrap();
+        let names: Vec<_> = lists.new_lists.iter().map(|l| l.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("Party"), Some("Weekly Groceries")]);
+        assert!(lists.modified_lists.is_empty());
+        // The calendar was not in the delta, so it is unchanged
+        assert_eq!(
+            state.data().meal_planning_calendar_response,
+            full_state().data().meal_planning_calendar_response
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_unmodified_lists() {
+        let mut state = full_state();
+        state.merge(PbUserDataResponse {
+            shopping_lists_response: Some(PbShoppingListsResponse {
+                unmodified_ids: vec!["a".to_string(), "b".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        assert_eq!(state, full_state());
+    }
+
+    #[test]
+    fn test_merge_partial_list_response_and_calendar() {
+        let mut state = full_state();
+        state.merge(PbUserDataResponse {
+            shopping_lists_response: Some(PbShoppingListsResponse {
+                unmodified_ids: vec!["a".to_string(), "b".to_string()],
+                list_responses: vec![PbListResponse {
+                    list_id: Some("a".to_string()),
+                    logical_timestamp: Some(4),
+                    stores: vec![store("s2", "Trader Joe's"), store("s3", "Aldi")],
+                    deleted_store_ids: vec!["s1".to_string()],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            meal_planning_calendar_response: Some(PbCalendarResponse {
+                calendar_id: "cal".to_string(),
+                logical_timestamp: Some(8),
+                events: vec![PbCalendarEvent {
+                    identifier: "e2".to_string(),
+                    ..Default::default()
+                }],
+                deleted_event_ids: vec!["e1".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let response = &state
+            .data()
+            .shopping_lists_response
+            .as_ref()
+            .unwrap()
+            .list_responses[0];
+        assert_eq!(response.logical_timestamp, Some(4));
+        let stores: Vec<_> = response.stores.iter().map(|s| s.name.as_deref()).collect();
+        assert_eq!(stores, vec![Some("Trader Joe's"), Some("Aldi")]);
+
+        let calendar = state
+            .data()
+            .meal_planning_calendar_response
+            .as_ref()
+            .unwrap();
+        assert_eq!(calendar.logical_timestamp, Some(8));
+        assert_eq!(calendar.events.len(), 1);
+        assert_eq!(calendar.events[0].identifier, "e2");
+    }
+}
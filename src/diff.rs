@@ -0,0 +1,540 @@
+//! Differences between two snapshots of lists, recipes or meal plans.
+//!
+//! Collections are matched up by identifier, so a renamed list is reported
+//! as [`ListChange::ListRenamed`] rather than as one list removed and
+//! another added. Changes come out in a stable order: removals first, then
+//! additions and edits in the order of the new snapshot, then reordering.
+//!
+//! ```
+//! use anylist_rs::diff::{diff_lists, ListChange};
+//! # fn example(before: Vec<anylist_rs::List>, after: Vec<anylist_rs::List>) {
+//! for change in diff_lists(&before, &after) {
+//!     if let ListChange::ItemCheckedChanged { item_id, is_checked: true, .. } = change {
+//!         println!("{} was crossed off", item_id);
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::lists::{List, ListItem};
+use crate::meal_planning::MealPlanEvent;
+use crate::recipes::Recipe;
+use std::collections::{HashMap, HashSet};
+
+/// A difference between two snapshots of shopping lists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListChange {
+    /// A list was created, with the items it already has
+    ListAdded {
+        list: List,
+    },
+    /// A list was deleted, with the items it had
+    ListRemoved {
+        list: List,
+    },
+    ListRenamed {
+        list_id: String,
+        old_name: String,
+        new_name: String,
+    },
+    /// The lists that exist in both snapshots are in a different order.
+    /// `list_ids` is the new order of every list.
+    ListsReordered {
+        list_ids: Vec<String>,
+    },
+    ItemAdded {
+        list_id: String,
+        item: ListItem,
+    },
+    ItemRemoved {
+        list_id: String,
+        item: ListItem,
+    },
+    ItemRenamed {
+        list_id: String,
+        item_id: String,
+        old_name: String,
+        new_name: String,
+    },
+    ItemQuantityChanged {
+        list_id: String,
+        item_id: String,
+        old_quantity: Option<String>,
+        new_quantity: Option<String>,
+    },
+    ItemDetailsChanged {
+        list_id: String,
+        item_id: String,
+        old_details: String,
+        new_details: String,
+    },
+    ItemCategoryChanged {
+        list_id: String,
+        item_id: String,
+        old_category: Option<String>,
+        new_category: Option<String>,
+    },
+    /// An item was crossed off (`is_checked` is true) or put back
+    ItemCheckedChanged {
+        list_id: String,
+        item_id: String,
+        is_checked: bool,
+    },
+    /// The items that exist in both snapshots are in a different order.
+    /// `item_ids` is the new order of every item in the list.
+    ItemsReordered {
+        list_id: String,
+        item_ids: Vec<String>,
+    },
+}
+
+impl ListChange {
+    /// The list the change is in, or `None` for [`ListChange::ListsReordered`]
+    pub fn list_id(&self) -> Option<&str> {
+        match self {
+            ListChange::ListAdded { list } | ListChange::ListRemoved { list } => Some(&list.id),
+            ListChange::ListsReordered { .. } => None,
+            ListChange::ListRenamed { list_id, .. }
+            | ListChange::ItemAdded { list_id, .. }
+            | ListChange::ItemRemoved { list_id, .. }
+            | ListChange::ItemRenamed { list_id, .. }
+            | ListChange::ItemQuantityChanged { list_id, .. }
+            | ListChange::ItemDetailsChanged { list_id, .. }
+            | ListChange::ItemCategoryChanged { list_id, .. }
+            | ListChange::ItemCheckedChanged { list_id, .. }
+            | ListChange::ItemsReordered { list_id, .. } => Some(list_id),
+        }
+    }
+
+    /// The item the change is about, if it is about a single item
+    pub fn item_id(&self) -> Option<&str> {
+        match self {
+            ListChange::ItemAdded { item, .. } | ListChange::ItemRemoved { item, .. } => {
+                Some(&item.id)
+            }
+            ListChange::ItemRenamed { item_id, .. }
+            | ListChange::ItemQuantityChanged { item_id, .. }
+            | ListChange::ItemDetailsChanged { item_id, .. }
+            | ListChange::ItemCategoryChanged { item_id, .. }
+            | ListChange::ItemCheckedChanged { item_id, .. } => Some(item_id),
+            _ => None,
+        }
+    }
+}
+
+/// A difference between two snapshots of recipes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecipeChange {
+    RecipeAdded {
+        recipe: Box<Recipe>,
+    },
+    RecipeRemoved {
+        recipe: Box<Recipe>,
+    },
+    RecipeRenamed {
+        recipe_id: String,
+        old_name: String,
+        new_name: String,
+    },
+    /// Anything other than the name changed, such as the ingredients,
+    /// steps or photo
+    RecipeUpdated {
+        old: Box<Recipe>,
+        new: Box<Recipe>,
+    },
+    /// The recipes that exist in both snapshots are in a different order.
+    /// `recipe_ids` is the new order of every recipe.
+    RecipesReordered {
+        recipe_ids: Vec<String>,
+    },
+}
+
+/// A difference between two snapshots of meal planning calendar events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MealPlanEventChange {
+    EventAdded {
+        event: MealPlanEvent,
+    },
+    EventRemoved {
+        event: MealPlanEvent,
+    },
+    /// The event was moved to another date
+    EventMoved {
+        event_id: String,
+        from_date: String,
+        to_date: String,
+    },
+    /// Anything other than the date changed, such as the title or recipe
+    EventUpdated {
+        old: MealPlanEvent,
+        new: MealPlanEvent,
+    },
+}
+
+/// Everything that differs between two snapshots of shopping lists
+pub fn diff_lists(old: &[List], new: &[List]) -> Vec<ListChange> {
+    let mut changes: Vec<ListChange> = removed(old, new, |l| &l.id)
+        .map(|list| ListChange::ListRemoved { list: list.clone() })
+        .collect();
+
+    let old_by_id = by_id(old, |l| &l.id);
+    for list in new {
+        let Some(&previous) = old_by_id.get(list.id.as_str()) else {
+            changes.push(ListChange::ListAdded { list: list.clone() });
+            continue;
+        };
+
+        if previous.name != list.name {
+            changes.push(ListChange::ListRenamed {
+                list_id: list.id.clone(),
+                old_name: previous.name.clone(),
+                new_name: list.name.clone(),
+            });
+        }
+        diff_items(&list.id, &previous.items, &list.items, &mut changes);
+    }
+
+    if let Some(list_ids) = reordered(old, new, |l| &l.id) {
+        changes.push(ListChange::ListsReordered { list_ids });
+    }
+    changes
+}
+
+fn diff_items(list_id: &str, old: &[ListItem], new: &[ListItem], changes: &mut Vec<ListChange>) {
+    changes.extend(
+        removed(old, new, |i| &i.id).map(|item| ListChange::ItemRemoved {
+            list_id: list_id.to_string(),
+            item: item.clone(),
+        }),
+    );
+
+    let old_by_id = by_id(old, |i| &i.id);
+    for item in new {
+        let Some(&previous) = old_by_id.get(item.id.as_str()) else {
+            changes.push(ListChange::ItemAdded {
+                list_id: list_id.to_string(),
+                item: item.clone(),
+            });
+            continue;
+        };
+
+        let list_id = list_id.to_string();
+        let item_id = item.id.clone();
+        if previous.name != item.name {
+            changes.push(ListChange::ItemRenamed {
+                list_id: list_id.clone(),
+                item_id: item_id.clone(),
+                old_name: previous.name.clone(),
+                new_name: item.name.clone(),
+            });
+        }
+        if previous.quantity != item.quantity {
+            changes.push(ListChange::ItemQuantityChanged {
+                list_id: list_id.clone(),
+                item_id: item_id.clone(),
+                old_quantity: previous.quantity.clone(),
+                new_quantity: item.quantity.clone(),
+            });
+        }
+        if previous.details != item.details {
+            changes.push(ListChange::ItemDetailsChanged {
+                list_id: list_id.clone(),
+                item_id: item_id.clone(),
+                old_details: previous.details.clone(),
+                new_details: item.details.clone(),
+            });
+        }
+        if previous.category != item.category {
+            changes.push(ListChange::ItemCategoryChanged {
+                list_id: list_id.clone(),
+                item_id: item_id.clone(),
+                old_category: previous.category.clone(),
+                new_category: item.category.clone(),
+            });
+        }
+        if previous.is_checked != item.is_checked {
+            changes.push(ListChange::ItemCheckedChanged {
+                list_id,
+                item_id,
+                is_checked: item.is_checked,
+            });
+        }
+    }
+
+    if let Some(item_ids) = reordered(old, new, |i| &i.id) {
+        changes.push(ListChange::ItemsReordered {
+            list_id: list_id.to_string(),
+            item_ids,
+        });
+    }
+}
+
+/// Everything that differs between two snapshots of recipes
+pub fn diff_recipes(old: &[Recipe], new: &[Recipe]) -> Vec<RecipeChange> {
+    let mut changes: Vec<RecipeChange> = removed(old, new, |r| r.id())
+        .map(|recipe| RecipeChange::RecipeRemoved {
+            recipe: Box::new(recipe.clone()),
+        })
+        .collect();
+
+    let old_by_id = by_id(old, |r| r.id());
+    for recipe in new {
+        let Some(&previous) = old_by_id.get(recipe.id()) else {
+            changes.push(RecipeChange::RecipeAdded {
+                recipe: Box::new(recipe.clone()),
+            });
+            continue;
+        };
+
+        if previous.name != recipe.name {
+            changes.push(RecipeChange::RecipeRenamed {
+                recipe_id: recipe.id().to_string(),
+                old_name: previous.name.clone(),
+                new_name: recipe.name.clone(),
+            });
+        }
+        let mut renamed_previous = previous.clone();
+        renamed_previous.name = recipe.name.clone();
+        if renamed_previous != *recipe {
+            changes.push(RecipeChange::RecipeUpdated {
+                old: Box::new(previous.clone()),
+                new: Box::new(recipe.clone()),
+            });
+        }
+    }
+
+    if let Some(recipe_ids) = reordered(old, new, |r| r.id()) {
+        changes.push(RecipeChange::RecipesReordered { recipe_ids });
+    }
+    changes
+}
+
+/// Everything that differs between two snapshots of meal planning calendar
+/// events. The calendar is ordered by date, so events are never reported as
+/// reordered.
+pub fn diff_meal_plan_events(
+    old: &[MealPlanEvent],
+    new: &[MealPlanEvent],
+) -> Vec<MealPlanEventChange> {
+    let mut changes: Vec<MealPlanEventChange> = removed(old, new, |e| e.id())
+        .map(|event| MealPlanEventChange::EventRemoved {
+            event: event.clone(),
+        })
+        .collect();
+
+    let old_by_id = by_id(old, |e| e.id());
+    for event in new {
+        let Some(&previous) = old_by_id.get(event.id()) else {
+            changes.push(MealPlanEventChange::EventAdded {
+                event: event.clone(),
+            });
+            continue;
+        };
+
+        if previous.date != event.date {
+            changes.push(MealPlanEventChange::EventMoved {
+                event_id: event.id().to_string(),
+                from_date: previous.date.clone(),
+                to_date: event.date.clone(),
+            });
+        }
+        let mut moved_previous = previous.clone();
+        moved_previous.date = event.date.clone();
+        if moved_previous != *event {
+            changes.push(MealPlanEventChange::EventUpdated {
+                old: previous.clone(),
+                new: event.clone(),
+            });
+        }
+    }
+    changes
+}
+
+/// Elements keyed by identifier, so matching two snapshots doesn't rescan
+/// one of them for every element of the other. The first element with an
+/// identifier wins.
+fn by_id<T>(elements: &[T], id: impl Fn(&T) -> &str) -> HashMap<&str, &T> {
+    let mut index = HashMap::with_capacity(elements.len());
+    for element in elements {
+        index.entry(id(element)).or_insert(element);
+    }
+    index
+}
+
+/// Elements of `old` whose identifier is not in `new`
+fn removed<'a, T>(
+    old: &'a [T],
+    new: &'a [T],
+    id: impl Fn(&T) -> &str + 'a,
+) -> impl Iterator<Item = &'a T> + 'a {
+    let new_ids: HashSet<&str> = new.iter().map(&id).collect();
+    old.iter().filter(move |o| !new_ids.contains(id(o)))
+}
+
+/// The new order of identifiers, if the elements in both `old` and `new`
+/// appear in a different order. Additions and removals alone are not a
+/// reorder.
+fn reordered<T>(old: &[T], new: &[T], id: impl Fn(&T) -> &str) -> Option<Vec<String>> {
+    let old_ids: HashSet<&str> = old.iter().map(&id).collect();
+    let new_ids: HashSet<&str> = new.iter().map(&id).collect();
+    let kept_in = |from: &[T], other: &HashSet<&str>| -> Vec<String> {
+        from.iter()
+            .map(&id)
+            .filter(|i| other.contains(i))
+            .map(str::to_string)
+            .collect()
+    };
+
+    if kept_in(old, &new_ids) == kept_in(new, &old_ids) {
+        return None;
+    }
+    Some(new.iter().map(|n| id(n).to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meal_planning::meal_plan_event_from_pb;
+    use crate::protobuf::anylist::PbCalendarEvent;
+
+    fn item(id: &str, name: &str, quantity: Option<&str>, checked: bool) -> ListItem {
+        ListItem {
+            id: id.to_string(),
+            list_id: "list-1".to_string(),
+            name: name.to_string(),
+            details: String::new(),
+            is_checked: checked,
+            quantity: quantity.map(str::to_string),
+            category: None,
+            user_id: None,
+            product_upc: None,
+        }
+    }
+
+    fn list(id: &str, name: &str, items: Vec<ListItem>) -> List {
+        List {
+            id: id.to_string(),
+            name: name.to_string(),
+            items,
+            shared_users: Vec::new(),
+        }
+    }
+
+    fn event(id: &str, date: &str, title: &str) -> MealPlanEvent {
+        meal_plan_event_from_pb(&PbCalendarEvent {
+            identifier: id.to_string(),
+            date: Some(date.to_string()),
+            title: Some(title.to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_list_changes_are_keyed_by_id() {
+        let old = vec![
+            list(
+                "list-1",
+                "Groceries",
+                vec![
+                    item("milk", "Milk", None, false),
+                    item("eggs", "Eggs", Some("6"), false),
+                ],
+            ),
+            list("list-2", "Hardware", Vec::new()),
+        ];
+        let new = vec![list(
+            "list-1",
+            "Weekly Groceries",
+            vec![
+                item("eggs", "Eggs", Some("12"), true),
+                item("milk", "Oat Milk", None, false),
+                item("flour", "Flour", None, false),
+            ],
+        )];
+
+        let list_id = "list-1".to_string();
+        assert_eq!(
+            diff_lists(&old, &new),
+            vec![
+                ListChange::ListRemoved {
+                    list: old[1].clone(),
+                },
+                ListChange::ListRenamed {
+                    list_id: list_id.clone(),
+                    old_name: "Groceries".to_string(),
+                    new_name: "Weekly Groceries".to_string(),
+                },
+                ListChange::ItemQuantityChanged {
+                    list_id: list_id.clone(),
+                    item_id: "eggs".to_string(),
+                    old_quantity: Some("6".to_string()),
+                    new_quantity: Some("12".to_string()),
+                },
+                ListChange::ItemCheckedChanged {
+                    list_id: list_id.clone(),
+                    item_id: "eggs".to_string(),
+                    is_checked: true,
+                },
+                ListChange::ItemRenamed {
+                    list_id: list_id.clone(),
+                    item_id: "milk".to_string(),
+                    old_name: "Milk".to_string(),
+                    new_name: "Oat Milk".to_string(),
+                },
+                ListChange::ItemAdded {
+                    list_id: list_id.clone(),
+                    item: item("flour", "Flour", None, false),
+                },
+                ListChange::ItemsReordered {
+                    list_id,
+                    item_ids: vec!["eggs".into(), "milk".into(), "flour".into()],
+                },
+            ]
+        );
+        assert!(diff_lists(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_additions_and_removals_are_not_a_reorder() {
+        let old = vec![list("a", "A", Vec::new()), list("b", "B", Vec::new())];
+        let new = vec![list("a", "A", Vec::new()), list("c", "C", Vec::new())];
+        assert!(!diff_lists(&old, &new)
+            .iter()
+            .any(|c| matches!(c, ListChange::ListsReordered { .. })));
+
+        let swapped = vec![old[1].clone(), old[0].clone()];
+        assert_eq!(
+            diff_lists(&old, &swapped),
+            vec![ListChange::ListsReordered {
+                list_ids: vec!["b".into(), "a".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_meal_plan_event_moved_and_updated() {
+        let old = vec![
+            event("e1", "2024-01-01", "Pizza"),
+            event("e2", "2024-01-02", "Tacos"),
+        ];
+        let new = vec![
+            event("e1", "2024-01-03", "Pizza"),
+            event("e2", "2024-01-02", "Fish Tacos"),
+        ];
+
+        assert_eq!(
+            diff_meal_plan_events(&old, &new),
+            vec![
+                MealPlanEventChange::EventMoved {
+                    event_id: "e1".to_string(),
+                    from_date: "2024-01-01".to_string(),
+                    to_date: "2024-01-03".to_string(),
+                },
+                MealPlanEventChange::EventUpdated {
+                    old: old[1].clone(),
+                    new: new[1].clone(),
+                },
+            ]
+        );
+    }
+}
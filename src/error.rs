@@ -1,28 +1,146 @@
+use crate::transport::TransportResponse;
 use std::fmt;
+use std::time::Duration;
 
 /// Custom error type for AnyList API operations
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum AnyListError {
     AuthenticationFailed(String),
+    /// Login was rejected: wrong email or password
+    InvalidCredentials,
+    /// Login was refused because the account is locked or disabled: a 423
+    /// response, or a 403 whose body says so
+    AccountLocked {
+        status: u16,
+        body: String,
+    },
     NetworkError(String),
     InvalidResponse(String),
     NotFound(String),
     PermissionDenied(String),
     /// Protocol buffer decoding error
     ProtobufError(String),
+    /// The server answered 429 Too Many Requests
+    RateLimited {
+        /// Parsed `Retry-After` header, if the server sent one
+        retry_after: Option<Duration>,
+        body: String,
+    },
+    /// The server answered with a 5xx status
+    ServerError {
+        status: u16,
+        body: String,
+    },
+    /// The server rejected the request with a 4xx status
+    ClientError {
+        status: u16,
+        body: String,
+    },
+    /// The server accepted a mutation request but did not process these
+    /// operations
+    OperationNotProcessed {
+        operation_ids: Vec<String>,
+    },
     /// Generic error
     Other(String),
 }
 
+impl AnyListError {
+    /// Build the error for a non-2xx response, keeping its status and body
+    pub(crate) fn from_response(response: &TransportResponse) -> Self {
+        let body = String::from_utf8_lossy(&response.body).trim().to_string();
+
+        match response.status {
+            429 => AnyListError::RateLimited {
+                retry_after: response
+                    .headers
+                    .get("Retry-After")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after),
+                body,
+            },
+            status @ 500..=599 => AnyListError::ServerError { status, body },
+            status @ 400..=499 => AnyListError::ClientError { status, body },
+            status => {
+                AnyListError::InvalidResponse(format!("Unexpected status {}: {}", status, body))
+            }
+        }
+    }
+
+    /// HTTP status code of the response that caused this error, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            AnyListError::RateLimited { .. } => Some(429),
+            AnyListError::AccountLocked { status, .. }
+            | AnyListError::ServerError { status, .. }
+            | AnyListError::ClientError { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request might succeed if sent again later.
+    ///
+    /// True for rate limiting, 5xx responses and requests that never got a
+    /// response; false for everything caused by the request itself.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            AnyListError::RateLimited { .. }
+                | AnyListError::ServerError { .. }
+                | AnyListError::NetworkError(_)
+        )
+    }
+}
+
+/// Parse a `Retry-After` value, given either in seconds or as an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
 impl fmt::Display for AnyListError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AnyListError::AuthenticationFailed(msg) => write!(f, "Authentication failed: {}", msg),
+            AnyListError::InvalidCredentials => write!(f, "Invalid email or password"),
+            AnyListError::AccountLocked { status, body } => {
+                write!(f, "Account locked ({}): {}", status, body)
+            }
             AnyListError::NetworkError(msg) => write!(f, "Network error: {}", msg),
             AnyListError::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
             AnyListError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AnyListError::PermissionDenied(msg) => write!(f, "Permission denied: {}", msg),
             AnyListError::ProtobufError(msg) => write!(f, "Protobuf error: {}", msg),
+            AnyListError::RateLimited {
+                retry_after: Some(delay),
+                body,
+            } => write!(
+                f,
+                "Rate limited (retry after {}s): {}",
+                delay.as_secs(),
+                body
+            ),
+            AnyListError::RateLimited {
+                retry_after: None,
+                body,
+            } => write!(f, "Rate limited: {}", body),
+            AnyListError::ServerError { status, body } => {
+                write!(f, "Server error ({}): {}", status, body)
+            }
+            AnyListError::ClientError { status, body } => {
+                write!(f, "Request failed with status {}: {}", status, body)
+            }
+            AnyListError::OperationNotProcessed { operation_ids } => write!(
+                f,
+                "Server did not process operations: {}",
+                operation_ids.join(", ")
+            ),
             AnyListError::Other(msg) => write!(f, "Error: {}", msg),
         }
     }
@@ -43,3 +161,72 @@ impl From<prost::DecodeError> for AnyListError {
 }
 
 pub type Result<T> = std::result::Result<T, AnyListError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    fn response(status: u16, headers: HeaderMap, body: &str) -> TransportResponse {
+        TransportResponse {
+            status,
+            headers,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_rate_limited_response_parses_retry_after() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Retry-After", HeaderValue::from_static("30"));
+
+        let err = AnyListError::from_response(&response(429, headers, "slow down\n"));
+        assert_eq!(err.status(), Some(429));
+        assert!(err.is_retryable());
+        match err {
+            AnyListError::RateLimited { retry_after, body } => {
+                assert_eq!(retry_after, Some(Duration::from_secs(30)));
+                assert_eq!(body, "slow down");
+            }
+            other => panic!("expected RateLimited, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_retry_after_in_the_past_is_zero() {
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_server_errors_are_retryable_client_errors_are_not() {
+        let server = AnyListError::from_response(&response(503, HeaderMap::new(), "down"));
+        assert!(matches!(
+            server,
+            AnyListError::ServerError { status: 503, .. }
+        ));
+        assert!(server.is_retryable());
+
+        let client = AnyListError::from_response(&response(400, HeaderMap::new(), "bad"));
+        assert!(matches!(
+            client,
+            AnyListError::ClientError { status: 400, .. }
+        ));
+        assert!(!client.is_retryable());
+        assert_eq!(client.status(), Some(400));
+
+        assert_eq!(AnyListError::NotFound("list".into()).status(), None);
+    }
+
+    #[test]
+    fn test_other_statuses_are_invalid_responses() {
+        for status in [101, 302, 304, 600] {
+            let error = AnyListError::from_response(&response(status, HeaderMap::new(), ""));
+            assert!(matches!(error, AnyListError::InvalidResponse(_)));
+            assert!(!error.is_retryable());
+        }
+    }
+}
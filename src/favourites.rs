@@ -1,9 +1,10 @@
+use crate::cache::UserDataSection;
 use crate::client::AnyListClient;
 use crate::error::{AnyListError, Result};
 use crate::lists::ListItem;
+use crate::outcome::OperationOutcome;
 use crate::protobuf::anylist::PbListItem;
 use crate::utils::generate_id;
-use prost::Message;
 use serde_derive::{Deserialize, Serialize};
 
 /// Represents a favourite item that can be quickly added to shopping lists
@@ -101,7 +102,9 @@ impl AnyListClient {
     ///
     /// Returns all starter lists of type `FavoriteItems`.
     pub async fn get_favourites_lists(&self) -> Result<Vec<FavouritesList>> {
-        let data = self.get_user_data().await?;
+        let data = self
+            .cached_user_data(&[UserDataSection::StarterLists])
+            .await?;
 
         let lists = match data.starter_lists_response {
             Some(ref res) => {
@@ -194,12 +197,8 @@ impl AnyListClient {
 
         let operation_list = crate::operations::build_add_favourite_operation(params);
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/starter-lists/update", buf).await?;
+        self.post_edit("data/starter-lists/update", &operation_list)
+            .await?;
 
         Ok(FavouriteItem {
             id: item_id,
@@ -217,7 +216,7 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the favourites list containing the item
     /// * `item_id` - The ID of the favourite item to remove
-    pub async fn remove_favourite(&self, list_id: &str, item_id: &str) -> Result<()> {
+    pub async fn remove_favourite(&self, list_id: &str, item_id: &str) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         let params = crate::operations::RemoveFavouriteParams {
@@ -229,13 +228,8 @@ impl AnyListClient {
 
         let operation_list = crate::operations::build_remove_favourite_operation(params);
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/starter-lists/update", buf).await?;
-        Ok(())
+        self.post_edit("data/starter-lists/update", &operation_list)
+            .await
     }
 
     /// Add a favourite item to a shopping list
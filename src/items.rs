@@ -1,12 +1,8 @@
 use crate::client::AnyListClient;
 use crate::error::{AnyListError, Result};
 use crate::lists::ListItem;
-use crate::protobuf::anylist::{
-    pb_operation_metadata::OperationClass, PbListItem, PbListOperation, PbListOperationList,
-    PbOperationMetadata,
-};
-use crate::utils::{current_timestamp, generate_id};
-use prost::Message;
+use crate::outcome::OperationOutcome;
+use crate::utils::generate_id;
 
 impl AnyListClient {
     /// Add an item to a shopping list
@@ -53,68 +49,10 @@ impl AnyListClient {
         details: Option<&str>,
         category: Option<&str>,
     ) -> Result<ListItem> {
-        let item_id = generate_id();
-        let operation_id = generate_id();
-
-        let new_item = PbListItem {
-            identifier: item_id.clone(),
-            server_mod_time: Some(current_timestamp()),
-            list_id: Some(list_id.to_string()),
-            name: Some(name.to_string()),
-            quantity: quantity.map(|q| q.to_string()),
-            details: details.map(|d| d.to_string()),
-            checked: Some(false),
-            recipe_id: None,
-            raw_ingredient: None,
-            price_matchup_tag: None,
-            price_id: None,
-            category: category.map(|c| c.to_string()),
-            user_id: Some(self.user_id()),
-            category_match_id: None,
-            photo_ids: vec![],
-            event_id: None,
-            store_ids: vec![],
-            prices: vec![],
-            category_assignments: vec![],
-            manual_sort_index: Some(0),
-            product_upc: None,
-        };
-
-        let operation = PbListOperation {
-            metadata: Some(PbOperationMetadata {
-                operation_id: Some(operation_id),
-                handler_id: Some("add-shopping-list-item".to_string()),
-                user_id: Some(self.user_id()),
-                operation_class: Some(OperationClass::Undefined as i32),
-            }),
-            list_id: Some(list_id.to_string()),
-            list_item_id: Some(item_id.clone()),
-            list_item: Some(new_item),
-            ..Default::default()
-        };
-
-        let operation_list = PbListOperationList {
-            operations: vec![operation],
-        };
-
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update", buf).await?;
-
-        Ok(ListItem {
-            id: item_id,
-            list_id: list_id.to_string(),
-            name: name.to_string(),
-            details: details.unwrap_or("").to_string(),
-            is_checked: false,
-            quantity: quantity.map(|q| q.to_string()),
-            category: category.map(|c| c.to_string()),
-            user_id: Some(self.user_id()),
-            product_upc: None,
-        })
+        let mut batch = self.batch();
+        let item = batch.add_item_with_details(list_id, name, quantity, details, category);
+        batch.commit().await?;
+        Ok(item)
     }
 
     /// Update an existing item
@@ -135,59 +73,10 @@ impl AnyListClient {
         quantity: Option<&str>,
         details: Option<&str>,
         category: Option<&str>,
-    ) -> Result<()> {
-        let operation_id = generate_id();
-
-        let updated_item = PbListItem {
-            identifier: item_id.to_string(),
-            server_mod_time: Some(current_timestamp()),
-            list_id: Some(list_id.to_string()),
-            name: Some(name.to_string()),
-            quantity: quantity.map(|q| q.to_string()),
-            details: details.map(|d| d.to_string()),
-            checked: Some(false),
-            recipe_id: None,
-            raw_ingredient: None,
-            price_matchup_tag: None,
-            price_id: None,
-            category: category.map(|c| c.to_string()),
-            user_id: Some(self.user_id()),
-            category_match_id: None,
-            photo_ids: vec![],
-            event_id: None,
-            store_ids: vec![],
-            prices: vec![],
-            category_assignments: vec![],
-            manual_sort_index: Some(0),
-            product_upc: None,
-        };
-
-        let operation = PbListOperation {
-            metadata: Some(PbOperationMetadata {
-                operation_id: Some(operation_id),
-                handler_id: Some("update-list-item".to_string()),
-                user_id: Some(self.user_id()),
-                operation_class: Some(OperationClass::Undefined as i32),
-            }),
-            list_id: Some(list_id.to_string()),
-            list_item_id: Some(item_id.to_string()),
-            list_item: Some(updated_item),
-            ..Default::default()
-        };
-
-        let operation_list = PbListOperationList {
-            operations: vec![operation],
-        };
-
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        // Note: "update-list-item" handler not found in API docs - may need to use specific handlers
-        // like set-list-item-name, set-list-item-quantity-v2, set-list-item-details
-        self.post("data/shopping-lists/update", buf).await?;
-        Ok(())
+    ) -> Result<OperationOutcome> {
+        let mut batch = self.batch();
+        batch.update_item(list_id, item_id, name, quantity, details, category);
+        batch.commit().await
     }
 
     /// Delete an item from a list
@@ -196,7 +85,7 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the list containing the item
     /// * `item_id` - The ID of the item to delete
-    pub async fn delete_item(&self, list_id: &str, item_id: &str) -> Result<()> {
+    pub async fn delete_item(&self, list_id: &str, item_id: &str) -> Result<OperationOutcome> {
       self.bulk_delete_items(list_id, &[item_id]).await
     }
 
@@ -212,9 +101,13 @@ impl AnyListClient {
     /// This method requires fetching the list to get full item details,
     /// but is more efficient than multiple `delete_item()` calls when
     /// deleting multiple items.
-    pub async fn bulk_delete_items(&self, list_id: &str, item_ids: &[&str]) -> Result<()> {
+    pub async fn bulk_delete_items(
+        &self,
+        list_id: &str,
+        item_ids: &[&str],
+    ) -> Result<OperationOutcome> {
         if item_ids.is_empty() {
-            return Ok(());
+            return Ok(OperationOutcome::default());
         }
 
         // Fetch the list to get full item details
@@ -253,13 +146,8 @@ impl AnyListClient {
 
         let operation_list = crate::operations::build_bulk_remove_items_operation(params);
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update", buf).await?;
-        Ok(())
+        self.post_edit("data/shopping-lists/update", &operation_list)
+            .await
     }
 
     /// Cross off (check) an item on a list
@@ -268,7 +156,7 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the list containing the item
     /// * `item_id` - The ID of the item to cross off
-    pub async fn cross_off_item(&self, list_id: &str, item_id: &str) -> Result<()> {
+    pub async fn cross_off_item(&self, list_id: &str, item_id: &str) -> Result<OperationOutcome> {
         self.set_item_checked(list_id, item_id, true).await
     }
 
@@ -278,38 +166,20 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the list containing the item
     /// * `item_id` - The ID of the item to uncheck
-    pub async fn uncheck_item(&self, list_id: &str, item_id: &str) -> Result<()> {
+    pub async fn uncheck_item(&self, list_id: &str, item_id: &str) -> Result<OperationOutcome> {
         self.set_item_checked(list_id, item_id, false).await
     }
 
     /// Set the checked status of an item
-    async fn set_item_checked(&self, list_id: &str, item_id: &str, checked: bool) -> Result<()> {
-        let operation_id = generate_id();
-
-        let operation = PbListOperation {
-            metadata: Some(PbOperationMetadata {
-                operation_id: Some(operation_id),
-                handler_id: Some("set-list-item-checked".to_string()),
-                user_id: Some(self.user_id()),
-                operation_class: Some(OperationClass::Undefined as i32),
-            }),
-            list_id: Some(list_id.to_string()),
-            list_item_id: Some(item_id.to_string()),
-            updated_value: Some(if checked { "y" } else { "n" }.to_string()),
-            ..Default::default()
-        };
-
-        let operation_list = PbListOperationList {
-            operations: vec![operation],
-        };
-
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update", buf).await?;
-        Ok(())
+    async fn set_item_checked(
+        &self,
+        list_id: &str,
+        item_id: &str,
+        checked: bool,
+    ) -> Result<OperationOutcome> {
+        let mut batch = self.batch();
+        batch.set_item_checked(list_id, item_id, checked);
+        batch.commit().await
     }
 
     /// Delete all crossed-off (checked) items from a list
@@ -317,13 +187,13 @@ impl AnyListClient {
     /// # Arguments
     ///
     /// * `list_id` - The ID of the list to clear crossed-off items from
-    pub async fn delete_all_crossed_off_items(&self, list_id: &str) -> Result<()> {
+    pub async fn delete_all_crossed_off_items(&self, list_id: &str) -> Result<OperationOutcome> {
         let list = self.get_list_by_id(list_id).await?;
         let checked_items: Vec<&ListItem> =
             list.items().iter().filter(|i| i.is_checked()).collect();
 
         if checked_items.is_empty() {
-            return Ok(());
+            return Ok(OperationOutcome::default());
         }
 
         // Use bulk remove operation for all checked items at once
@@ -350,12 +220,7 @@ impl AnyListClient {
 
         let operation_list = crate::operations::build_bulk_remove_items_operation(params);
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update", buf).await?;
-        Ok(())
+        self.post_edit("data/shopping-lists/update", &operation_list)
+            .await
     }
 }
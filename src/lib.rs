@@ -33,21 +33,36 @@
 //! }
 //! ```
 
+pub mod account;
+pub mod batch;
+pub mod cache;
+pub mod cassette;
 pub mod categories;
 pub mod client;
 pub mod collections;
+pub mod delta;
+pub mod diff;
 pub mod error;
 pub mod favourites;
 pub mod icalendar;
 pub mod items;
 pub mod lists;
+pub mod live;
 pub mod login;
 pub mod meal_planning;
+pub mod offline;
 pub mod operations;
+pub mod outcome;
 pub mod realtime;
 pub mod recipes;
+pub mod retry;
 pub mod stores;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod token_store;
+pub mod transport;
 mod utils;
+pub mod watch_sync;
 
 pub mod protobuf {
     pub mod anylist {
@@ -56,18 +71,30 @@ pub mod protobuf {
 }
 
 // Re-export commonly used types
-pub use client::{AnyListClient, AuthEvent, SavedTokens};
+pub use client::{AnyListClient, AnyListClientBuilder, AuthEvent, SavedTokens};
 pub use error::{AnyListError, Result};
+pub use retry::RetryPolicy;
 
 // Re-export data structures
+pub use account::AccountInfo;
+pub use batch::OperationBatch;
+pub use cache::UserDataSection;
 pub use categories::{Category, CategoryGroup};
 pub use collections::RecipeCollection;
+pub use delta::UserDataState;
 pub use favourites::{FavouriteItem, FavouritesList};
 pub use icalendar::ICalendarInfo;
 pub use lists::{List, ListItem};
+pub use live::{LiveAnyList, LiveChange};
 pub use meal_planning::MealPlanEvent;
-pub use realtime::{ConnectionState, RealtimeSync, SyncEvent};
+pub use offline::{FailedOperations, ReplayReport};
+pub use outcome::OperationOutcome;
+pub use realtime::{
+    CloseReason, ConnectionState, RealtimeConfig, RealtimeHealth, RealtimeSync, SyncEvent,
+};
 pub use recipes::{
     Ingredient, Recipe, RecipeBuilder, RecipeIngredientEntry, RecipeIngredientSection,
 };
 pub use stores::{Store, StoreFilter};
+pub use token_store::{EncryptedFileTokenStore, JsonFileTokenStore, TokenStore};
+pub use watch_sync::WatchSyncDelta;
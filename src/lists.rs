@@ -1,7 +1,10 @@
+use crate::cache::UserDataSection;
 use crate::client::AnyListClient;
+use crate::delta::UserDataState;
 use crate::error::{AnyListError, Result};
+use crate::outcome::OperationOutcome;
 use crate::protobuf::anylist::{
-    PbEmailUserIdPair, PbListItem, PbShoppingListsResponse, PbUserDataResponse,
+    PbEmailUserIdPair, PbListItem, PbShoppingList, PbShoppingListsResponse, PbUserDataResponse,
 };
 use crate::utils::{current_timestamp, generate_id};
 use prost::Message;
@@ -128,7 +131,9 @@ impl AnyListClient {
     /// # }
     /// ```
     pub async fn get_lists(&self) -> Result<Vec<List>> {
-        let data = self.get_user_data().await?;
+        let data = self
+            .cached_user_data(&[UserDataSection::ShoppingLists])
+            .await?;
         let lists = match data.shopping_lists_response {
             Some(ref res) => lists_from_response(res.clone()),
             None => Vec::new(),
@@ -199,12 +204,8 @@ impl AnyListClient {
         let operation_list = crate::operations::build_create_list_operation(params);
 
         // Imperative shell: side effects
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update", buf).await?;
+        self.post_edit("data/shopping-lists/update", &operation_list)
+            .await?;
 
         Ok(List {
             id: list_id,
@@ -215,8 +216,10 @@ impl AnyListClient {
     }
 
     /// Delete a shopping list
-    pub async fn delete_list(&self, list_id: &str) -> Result<()> {
-        let user_data = self.get_user_data().await?;
+    pub async fn delete_list(&self, list_id: &str) -> Result<OperationOutcome> {
+        let user_data = self
+            .cached_user_data(&[UserDataSection::ListFolders, UserDataSection::ListSettings])
+            .await?;
 
         let list_data_id = user_data
             .list_folders_response
@@ -246,11 +249,9 @@ impl AnyListClient {
             user_id: self.user_id(),
         };
         let folder_operation_list = crate::operations::build_delete_folder_items_operation(folder_params);
-        let mut folder_buf = Vec::new();
-        folder_operation_list.encode(&mut folder_buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode folder operation: {}", e))
-        })?;
-        self.post("data/list-folders/update", folder_buf).await?;
+        let outcome = self
+            .post_edit("data/list-folders/update", &folder_operation_list)
+            .await?;
 
         let settings_params = crate::operations::RemoveListSettingsParams {
             settings_id,
@@ -259,13 +260,11 @@ impl AnyListClient {
             user_id: self.user_id(),
         };
         let settings_operation_list = crate::operations::build_remove_list_settings_operation(settings_params);
-        let mut settings_buf = Vec::new();
-        settings_operation_list.encode(&mut settings_buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode settings operation: {}", e))
-        })?;
-        self.post("data/list-settings/update", settings_buf).await?;
+        let settings_outcome = self
+            .post_edit("data/list-settings/update", &settings_operation_list)
+            .await?;
 
-        Ok(())
+        Ok(outcome.merge(settings_outcome))
     }
 
     /// Rename a shopping list
@@ -274,7 +273,7 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the list to rename
     /// * `new_name` - The new name for the list
-    pub async fn rename_list(&self, list_id: &str, new_name: &str) -> Result<()> {
+    pub async fn rename_list(&self, list_id: &str, new_name: &str) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         // Get the current list to preserve other fields
@@ -294,24 +293,33 @@ impl AnyListClient {
         let operation_list = crate::operations::build_rename_list_operation(params);
 
         // Imperative shell: side effects
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update", buf).await?;
-        Ok(())
+        self.post_edit("data/shopping-lists/update", &operation_list)
+            .await
     }
 
     /// Get user data from the API
+    ///
+    /// Always downloads the data, and refreshes the cache if it is enabled.
     pub async fn get_user_data(&self) -> Result<PbUserDataResponse> {
+        let generation = self
+            .user_data_cache
+            .as_ref()
+            .map(|cache| cache.lock().unwrap().generation());
+
         let bytes = self.post("data/user-data/get", vec![]).await?;
         let data = PbUserDataResponse::decode(bytes.as_ref())?;
+
+        if let (Some(cache), Some(generation)) = (&self.user_data_cache, generation) {
+            cache
+                .lock()
+                .unwrap()
+                .store(UserDataState::from_data(data.clone()), generation);
+        }
         Ok(data)
     }
 }
 
-fn transform_api_list_item(items: Vec<PbListItem>) -> Vec<ListItem> {
+pub(crate) fn transform_api_list_item(items: Vec<PbListItem>) -> Vec<ListItem> {
     let mut result: Vec<ListItem> = Vec::new();
     for item in items {
         if let (Some(name), Some(list_id)) = (item.name, item.list_id) {
@@ -333,30 +341,33 @@ fn transform_api_list_item(items: Vec<PbListItem>) -> Vec<ListItem> {
 }
 
 fn transform_shared_users(users: Vec<PbEmailUserIdPair>) -> Vec<UserInfo> {
-    users
-        .into_iter()
-        .map(|user| UserInfo {
-            user_id: user.user_id.unwrap_or_default(),
-            email: user.email,
-            full_name: user.full_name,
-        })
-        .collect()
+    users.into_iter().map(transform_user).collect()
 }
 
-fn lists_from_response(response: PbShoppingListsResponse) -> Vec<List> {
-    let mut lists: Vec<List> = Vec::new();
-    for list in response.new_lists {
-        if let Some(name) = list.name {
-            let list = List {
-                id: list.identifier,
-                name,
-                items: transform_api_list_item(list.items),
-                shared_users: transform_shared_users(list.shared_users),
-            };
-            lists.push(list);
-        }
+pub(crate) fn transform_user(user: PbEmailUserIdPair) -> UserInfo {
+    UserInfo {
+        user_id: user.user_id.unwrap_or_default(),
+        email: user.email,
+        full_name: user.full_name,
     }
-    lists
+}
+
+/// Convert a list, skipping it if it has no name
+pub(crate) fn transform_api_list(list: PbShoppingList) -> Option<List> {
+    Some(List {
+        id: list.identifier,
+        name: list.name?,
+        items: transform_api_list_item(list.items),
+        shared_users: transform_shared_users(list.shared_users),
+    })
+}
+
+fn lists_from_response(response: PbShoppingListsResponse) -> Vec<List> {
+    response
+        .new_lists
+        .into_iter()
+        .filter_map(transform_api_list)
+        .collect()
 }
 
 #[cfg(test)]
@@ -0,0 +1,519 @@
+//! Realtime sync that delivers what changed, not just that something did.
+//!
+//! [`RealtimeSync`] only reports which section of the user data changed
+//! (`ShoppingListsChanged`, `RecipeDataChanged`, ...). [`LiveAnyList`] keeps
+//! the last copy of the lists, recipes and meal plan, re-fetches it on every
+//! such event, and emits a [`LiveChange`] for each difference
+//! [`crate::diff`] finds.
+//!
+//! The re-fetch goes through the client's user data cache when it is
+//! enabled (see [`crate::cache`]), so only the changed sections are
+//! downloaded.
+
+use crate::cache::UserDataSection;
+use crate::client::AnyListClient;
+use crate::diff::{
+    diff_lists, diff_meal_plan_events, diff_recipes, ListChange, MealPlanEventChange, RecipeChange,
+};
+use crate::error::Result;
+use crate::lists::{transform_api_list, List, ListItem};
+use crate::meal_planning::{meal_plan_event_from_pb, MealPlanEvent};
+use crate::protobuf::anylist::PbUserDataResponse;
+use crate::realtime::{ConnectionState, RealtimeConfig, RealtimeSync, SyncEvent};
+use crate::recipes::{recipes_from_response, Recipe};
+use futures_util::Stream;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+
+/// How many changes a subscriber can fall behind before it misses some
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// Sections a [`LiveAnyList`] keeps a copy of
+const SECTIONS: [UserDataSection; 3] = [
+    UserDataSection::ShoppingLists,
+    UserDataSection::RecipeData,
+    UserDataSection::MealPlanningCalendar,
+];
+
+/// One difference between two copies of the user data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiveChange {
+    /// A list was created, with the items it already has
+    ListAdded {
+        list: List,
+    },
+    ListRemoved {
+        list_id: String,
+    },
+    ListRenamed {
+        list_id: String,
+        name: String,
+    },
+    ItemAdded {
+        list_id: String,
+        item: ListItem,
+    },
+    ItemRemoved {
+        list_id: String,
+        item_id: String,
+    },
+    /// An item was crossed off
+    ItemChecked {
+        list_id: String,
+        item: ListItem,
+    },
+    /// A crossed-off item was put back on the list
+    ItemUnchecked {
+        list_id: String,
+        item: ListItem,
+    },
+    /// An item's name, quantity, details or category changed
+    ItemUpdated {
+        list_id: String,
+        item: ListItem,
+    },
+    RecipeAdded {
+        recipe: Box<Recipe>,
+    },
+    RecipeRemoved {
+        recipe_id: String,
+    },
+    RecipeUpdated {
+        recipe: Box<Recipe>,
+    },
+    MealPlanEventAdded {
+        event: MealPlanEvent,
+    },
+    MealPlanEventRemoved {
+        event_id: String,
+    },
+    /// An event was moved to another date
+    MealPlanEventMoved {
+        event: MealPlanEvent,
+        from_date: String,
+    },
+    MealPlanEventUpdated {
+        event: MealPlanEvent,
+    },
+}
+
+/// The parts of the user data a [`LiveAnyList`] tracks
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Snapshot {
+    lists: Vec<List>,
+    recipes: Vec<Recipe>,
+    meal_plan_events: Vec<MealPlanEvent>,
+}
+
+impl Snapshot {
+    fn from_data(data: PbUserDataResponse) -> Self {
+        Snapshot {
+            lists: data
+                .shopping_lists_response
+                .map(|r| {
+                    r.new_lists
+                        .into_iter()
+                        .filter_map(transform_api_list)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            recipes: data
+                .recipe_data_response
+                .map(recipes_from_response)
+                .unwrap_or_default(),
+            meal_plan_events: data
+                .meal_planning_calendar_response
+                .map(|r| r.events.iter().map(meal_plan_event_from_pb).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Everything that differs in `new`, in the order lists, items,
+    /// recipes, meal plan events. Reordering is not reported.
+    fn changes(&self, new: &Snapshot) -> Vec<LiveChange> {
+        let mut changes = Vec::new();
+
+        for change in diff_lists(&self.lists, &new.lists) {
+            let item = |list_id: &str, item_id: &str| {
+                new.lists
+                    .iter()
+                    .find(|l| l.id == list_id)
+                    .and_then(|l| l.items.iter().find(|i| i.id == item_id))
+                    .cloned()
+            };
+            let live = match change {
+                ListChange::ListAdded { list } => LiveChange::ListAdded { list },
+                ListChange::ListRemoved { list } => LiveChange::ListRemoved { list_id: list.id },
+                ListChange::ListRenamed {
+                    list_id, new_name, ..
+                } => LiveChange::ListRenamed {
+                    list_id,
+                    name: new_name,
+                },
+                ListChange::ItemAdded { list_id, item } => LiveChange::ItemAdded { list_id, item },
+                ListChange::ItemRemoved { list_id, item } => LiveChange::ItemRemoved {
+                    list_id,
+                    item_id: item.id,
+                },
+                ListChange::ItemCheckedChanged {
+                    list_id,
+                    item_id,
+                    is_checked,
+                } => {
+                    let Some(item) = item(&list_id, &item_id) else {
+                        continue;
+                    };
+                    if is_checked {
+                        LiveChange::ItemChecked { list_id, item }
+                    } else {
+                        LiveChange::ItemUnchecked { list_id, item }
+                    }
+                }
+                ListChange::ItemRenamed {
+                    list_id, item_id, ..
+                }
+                | ListChange::ItemQuantityChanged {
+                    list_id, item_id, ..
+                }
+                | ListChange::ItemDetailsChanged {
+                    list_id, item_id, ..
+                }
+                | ListChange::ItemCategoryChanged {
+                    list_id, item_id, ..
+                } => {
+                    let Some(item) = item(&list_id, &item_id) else {
+                        continue;
+                    };
+                    LiveChange::ItemUpdated { list_id, item }
+                }
+                ListChange::ListsReordered { .. } | ListChange::ItemsReordered { .. } => continue,
+            };
+            push_once(&mut changes, live);
+        }
+
+        for change in diff_recipes(&self.recipes, &new.recipes) {
+            let live = match change {
+                RecipeChange::RecipeAdded { recipe } => LiveChange::RecipeAdded { recipe },
+                RecipeChange::RecipeRemoved { recipe } => LiveChange::RecipeRemoved {
+                    recipe_id: recipe.id().to_string(),
+                },
+                RecipeChange::RecipeRenamed { recipe_id, .. } => {
+                    let Some(recipe) = new.recipes.iter().find(|r| r.id() == recipe_id) else {
+                        continue;
+                    };
+                    LiveChange::RecipeUpdated {
+                        recipe: Box::new(recipe.clone()),
+                    }
+                }
+                RecipeChange::RecipeUpdated { new, .. } => {
+                    LiveChange::RecipeUpdated { recipe: new }
+                }
+                RecipeChange::RecipesReordered { .. } => continue,
+            };
+            push_once(&mut changes, live);
+        }
+
+        for change in diff_meal_plan_events(&self.meal_plan_events, &new.meal_plan_events) {
+            let live = match change {
+                MealPlanEventChange::EventAdded { event } => {
+                    LiveChange::MealPlanEventAdded { event }
+                }
+                MealPlanEventChange::EventRemoved { event } => LiveChange::MealPlanEventRemoved {
+                    event_id: event.id().to_string(),
+                },
+                MealPlanEventChange::EventMoved {
+                    event_id,
+                    from_date,
+                    ..
+                } => {
+                    let Some(event) = new.meal_plan_events.iter().find(|e| e.id() == event_id)
+                    else {
+                        continue;
+                    };
+                    LiveChange::MealPlanEventMoved {
+                        event: event.clone(),
+                        from_date,
+                    }
+                }
+                MealPlanEventChange::EventUpdated { new, .. } => {
+                    // A moved event already carries its other edits
+                    if matches!(
+                        changes.last(),
+                        Some(LiveChange::MealPlanEventMoved { event, .. }) if event.id() == new.id()
+                    ) {
+                        continue;
+                    }
+                    LiveChange::MealPlanEventUpdated { event: new }
+                }
+            };
+            push_once(&mut changes, live);
+        }
+        changes
+    }
+}
+
+/// Add `change` unless it was just added: several field changes to the same
+/// item or recipe become one update
+fn push_once(changes: &mut Vec<LiveChange>, change: LiveChange) {
+    if changes.last() != Some(&change) {
+        changes.push(change);
+    }
+}
+
+/// State shared between a [`LiveAnyList`] and its background task
+struct Shared {
+    client: Arc<AnyListClient>,
+    snapshot: Mutex<Snapshot>,
+    changes: broadcast::Sender<LiveChange>,
+}
+
+impl Shared {
+    /// Fetch the user data again and emit what changed since the last fetch
+    async fn refresh(&self) -> Result<Vec<LiveChange>> {
+        // Held across the fetch so concurrent refreshes diff in order
+        let mut snapshot = self.snapshot.lock().await;
+        let new = Snapshot::from_data(self.client.cached_user_data(&SECTIONS).await?);
+        let changes = snapshot.changes(&new);
+        *snapshot = new;
+
+        for change in &changes {
+            // No subscribers is not an error
+            let _ = self.changes.send(change.clone());
+        }
+        Ok(changes)
+    }
+}
+
+/// Lists, recipes and meal plan kept up to date by realtime sync, with a
+/// stream of what changed.
+///
+/// # Example
+///
+/// ```no_run
+/// use anylist_rs::{AnyListClient, LiveAnyList, LiveChange};
+/// use futures_util::StreamExt;
+/// use std::sync::Arc;
+///
+/// # async fn example() -> anylist_rs::Result<()> {
+/// let client = Arc::new(AnyListClient::login("user@example.com", "password").await?);
+/// let live = LiveAnyList::start(client).await?;
+///
+/// let mut changes = Box::pin(live.changes());
+/// while let Some(change) = changes.next().await {
+///     match change {
+///         LiveChange::ItemAdded { list_id, item } => {
+///             println!("{} added to {}", item.name(), list_id);
+///         }
+///         LiveChange::ItemChecked { item, .. } => println!("{} crossed off", item.name()),
+///         _ => {}
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct LiveAnyList {
+    shared: Arc<Shared>,
+    sync: RealtimeSync,
+    task: JoinHandle<()>,
+}
+
+impl LiveAnyList {
+    /// Fetch the user data and connect to realtime sync
+    pub async fn start(client: Arc<AnyListClient>) -> Result<Self> {
+        let mut live = Self::load(client).await?;
+        live.connect().await?;
+        Ok(live)
+    }
+
+    /// Fetch the user data without connecting.
+    ///
+    /// Changes are only detected by [`refresh`](Self::refresh) until
+    /// [`connect`](Self::connect) is called.
+    pub async fn load(client: Arc<AnyListClient>) -> Result<Self> {
+        let snapshot = Snapshot::from_data(client.cached_user_data(&SECTIONS).await?);
+        let (changes, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+        let shared = Arc::new(Shared {
+            client: Arc::clone(&client),
+            snapshot: Mutex::new(snapshot),
+            changes,
+        });
+
+        let sync = RealtimeSync::without_callback(client, RealtimeConfig::default());
+        let task = Self::spawn_refetcher(Arc::clone(&shared), sync.subscribe());
+
+        Ok(Self { shared, sync, task })
+    }
+
+    /// Connect to realtime sync, so changes are picked up as they happen
+    pub async fn connect(&mut self) -> Result<()> {
+        self.sync.connect().await
+    }
+
+    /// The underlying realtime sync, e.g. to watch its connection state
+    pub fn realtime(&self) -> &RealtimeSync {
+        &self.sync
+    }
+
+    /// Receive every change from now on.
+    ///
+    /// A subscriber that falls more than 256 changes behind skips the
+    /// oldest ones and gets
+    /// [`RecvError::Lagged`](broadcast::error::RecvError::Lagged).
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveChange> {
+        self.shared.changes.subscribe()
+    }
+
+    /// Every change from now on as a [`Stream`]. Changes missed by a
+    /// lagging stream are skipped silently.
+    pub fn changes(&self) -> impl Stream<Item = LiveChange> + Send + 'static {
+        futures_util::stream::unfold(self.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(change) => return Some((change, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Fetch the user data now and emit what changed, without waiting for a
+    /// realtime event
+    pub async fn refresh(&self) -> Result<Vec<LiveChange>> {
+        self.shared.refresh().await
+    }
+
+    pub async fn lists(&self) -> Vec<List> {
+        self.shared.snapshot.lock().await.lists.clone()
+    }
+
+    pub async fn recipes(&self) -> Vec<Recipe> {
+        self.shared.snapshot.lock().await.recipes.clone()
+    }
+
+    /// Every event in the meal planning calendar
+    pub async fn meal_plan_events(&self) -> Vec<MealPlanEvent> {
+        self.shared.snapshot.lock().await.meal_plan_events.clone()
+    }
+
+    /// Disconnect from realtime sync and stop re-fetching
+    pub async fn stop(mut self) -> Result<()> {
+        self.task.abort();
+        self.sync.disconnect().await
+    }
+
+    /// Re-fetch on every event for a tracked section, and after every
+    /// reconnection, since events sent while disconnected were missed
+    fn spawn_refetcher(
+        shared: Arc<Shared>,
+        mut events: broadcast::Receiver<SyncEvent>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut connected_before = false;
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    // Missed events: re-fetch to be safe
+                    Err(broadcast::error::RecvError::Lagged(_)) => SyncEvent::ShoppingListsChanged,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                let refetch = match event {
+                    SyncEvent::ConnectionStateChanged(ConnectionState::Connected) => {
+                        std::mem::replace(&mut connected_before, true)
+                    }
+                    event => UserDataSection::for_event(&event)
+                        .is_some_and(|section| SECTIONS.contains(&section)),
+                };
+                if refetch {
+                    // A failed fetch is retried on the next event
+                    let _ = shared.refresh().await;
+                }
+            }
+        })
+    }
+}
+
+impl Drop for LiveAnyList {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, name: &str, checked: bool) -> ListItem {
+        ListItem {
+            id: id.to_string(),
+            list_id: "list-1".to_string(),
+            name: name.to_string(),
+            details: String::new(),
+            is_checked: checked,
+            quantity: None,
+            category: None,
+            user_id: None,
+            product_upc: None,
+        }
+    }
+
+    fn list(name: &str, items: Vec<ListItem>) -> List {
+        List {
+            id: "list-1".to_string(),
+            name: name.to_string(),
+            items,
+            shared_users: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_list_and_item_changes() {
+        let old = Snapshot {
+            lists: vec![list(
+                "Groceries",
+                vec![item("milk", "Milk", false), item("eggs", "Eggs", false)],
+            )],
+            ..Default::default()
+        };
+        let new = Snapshot {
+            lists: vec![list(
+                "Weekly Groceries",
+                vec![
+                    item("milk", "Oat Milk", true),
+                    item("flour", "Flour", false),
+                ],
+            )],
+            ..Default::default()
+        };
+
+        let list_id = "list-1".to_string();
+        assert_eq!(
+            old.changes(&new),
+            vec![
+                LiveChange::ListRenamed {
+                    list_id: list_id.clone(),
+                    name: "Weekly Groceries".to_string(),
+                },
+                LiveChange::ItemRemoved {
+                    list_id: list_id.clone(),
+                    item_id: "eggs".to_string(),
+                },
+                LiveChange::ItemUpdated {
+                    list_id: list_id.clone(),
+                    item: item("milk", "Oat Milk", true),
+                },
+                LiveChange::ItemChecked {
+                    list_id: list_id.clone(),
+                    item: item("milk", "Oat Milk", true),
+                },
+                LiveChange::ItemAdded {
+                    list_id,
+                    item: item("flour", "Flour", false),
+                },
+            ]
+        );
+        assert!(new.changes(&new).is_empty());
+    }
+}
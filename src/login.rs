@@ -1,3 +1,5 @@
+use crate::error::{AnyListError, Result};
+use crate::transport::{Method, MultipartForm, Transport, TransportRequest, TransportResponse};
 use reqwest::header::HeaderMap;
 use serde_derive::{Deserialize, Serialize};
 
@@ -18,10 +20,12 @@ pub(crate) struct LoginResult {
 }
 
 pub(crate) async fn login(
+    transport: &dyn Transport,
+    auth_base_url: &str,
     email: &str,
     password: &str,
     client_identifier: &str,
-) -> Result<LoginResult, Box<dyn std::error::Error>> {
+) -> Result<LoginResult> {
     let mut headers = HeaderMap::new();
     headers.insert("X-AnyLeaf-API-Version", "3".parse().unwrap());
     headers.insert(
@@ -29,38 +33,47 @@ pub(crate) async fn login(
         client_identifier.parse().unwrap(),
     );
 
-    let form = reqwest::multipart::Form::new()
-        .text("email", email.to_string())
-        .text("password", password.to_string());
+    let form = MultipartForm::new()
+        .text("email", email)
+        .text("password", password);
 
-    let client = reqwest::Client::new();
-    let res = client
-        .post("https://www.anylist.com/auth/token")
-        .headers(headers)
-        .multipart(form)
-        .send()
+    let res = transport
+        .send(TransportRequest {
+            method: Method::POST,
+            url: format!("{}/auth/token", auth_base_url),
+            headers,
+            body: Some(form),
+        })
         .await?;
 
-    if !res.status().is_success() {
-        let status = res.status();
-        let headers = res.headers().clone();
-        let body = res.text().await?;
+    match res.status {
+        401 => return Err(AnyListError::InvalidCredentials),
+        423 => return Err(account_locked(&res)),
+        403 if mentions_lockout(&res.body) => return Err(account_locked(&res)),
+        _ if !res.is_success() => return Err(AnyListError::from_response(&res)),
+        _ => {}
+    }
 
-        eprintln!("Login failed:");
-        eprintln!("  Status: {}", status);
-        eprintln!("  Headers: {:?}", headers);
-        eprintln!("  Body: {}", body);
+    let response: TokenResponse = serde_json::from_slice(&res.body)
+        .map_err(|e| AnyListError::InvalidResponse(format!("Invalid login response: {}", e)))?;
+    Ok(LoginResult {
+        access_token: response.access_token,
+        refresh_token: response.refresh_token,
+        user_id: response.user_id,
+        is_premium_user: response.is_premium_user,
+    })
+}
 
-        return Err(format!("Login failed with status: {}, body: {}", status, body).into());
+fn account_locked(res: &TransportResponse) -> AnyListError {
+    AnyListError::AccountLocked {
+        status: res.status,
+        body: String::from_utf8_lossy(&res.body).trim().to_string(),
     }
+}
 
-    match res.json::<TokenResponse>().await {
-        Ok(response) => Ok(LoginResult {
-            access_token: response.access_token,
-            refresh_token: response.refresh_token,
-            user_id: response.user_id,
-            is_premium_user: response.is_premium_user,
-        }),
-        Err(e) => Err(Box::new(e)),
-    }
+/// Whether a refusal's body says the account is locked or disabled. Other
+/// 403s (a blocked client identifier, say) stay plain client errors.
+fn mentions_lockout(body: &[u8]) -> bool {
+    let body = String::from_utf8_lossy(body).to_lowercase();
+    body.contains("locked") || body.contains("disabled")
 }
@@ -1,12 +1,13 @@
+use crate::cache::UserDataSection;
 use crate::client::AnyListClient;
 use crate::error::{AnyListError, Result};
+use crate::outcome::OperationOutcome;
 use crate::protobuf::anylist::{
     pb_operation_metadata::OperationClass, PbCalendarEvent, PbCalendarOperation,
     PbCalendarOperationList, PbOperationMetadata,
 };
 use crate::utils::generate_id;
 use chrono::NaiveDate;
-use prost::Message;
 use serde_derive::{Deserialize, Serialize};
 
 /// Represents a meal planning calendar event
@@ -14,7 +15,7 @@ use serde_derive::{Deserialize, Serialize};
 pub struct MealPlanEvent {
     id: String,
     calendar_id: Option<String>,
-    date: String,
+    pub(crate) date: String,
     title: Option<String>,
     recipe_id: Option<String>,
     label_id: Option<String>,
@@ -69,7 +70,9 @@ impl AnyListClient {
         let end = NaiveDate::parse_from_str(end_date, "%Y-%m-%d")
             .map_err(|e| AnyListError::Other(format!("Invalid end date: {}", e)))?;
 
-        let data = self.get_user_data().await?;
+        let data = self
+            .cached_user_data(&[UserDataSection::MealPlanningCalendar])
+            .await?;
         let events = match data.meal_planning_calendar_response {
             Some(ref res) => res
                 .events
@@ -148,12 +151,8 @@ impl AnyListClient {
             operations: vec![operation],
         };
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/meal-planning-calendar/update", buf).await?;
+        self.post_edit("data/meal-planning-calendar/update", &operation_list)
+            .await?;
 
         Ok(MealPlanEvent {
             id: event_id,
@@ -184,7 +183,7 @@ impl AnyListClient {
         recipe_id: Option<&str>,
         title: Option<&str>,
         label_id: Option<&str>,
-    ) -> Result<()> {
+    ) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         let updated_event = PbCalendarEvent {
@@ -222,13 +221,8 @@ impl AnyListClient {
             operations: vec![operation],
         };
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/meal-planning-calendar/update", buf).await?;
-        Ok(())
+        self.post_edit("data/meal-planning-calendar/update", &operation_list)
+            .await
     }
 
     /// Delete a meal plan event
@@ -237,7 +231,11 @@ impl AnyListClient {
     ///
     /// * `calendar_id` - The ID of the meal planning calendar
     /// * `event_id` - The ID of the event to delete
-    pub async fn delete_meal_plan_event(&self, calendar_id: &str, event_id: &str) -> Result<()> {
+    pub async fn delete_meal_plan_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+    ) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         let operation = PbCalendarOperation {
@@ -262,13 +260,8 @@ impl AnyListClient {
             operations: vec![operation],
         };
 
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/meal-planning-calendar/update", buf).await?;
-        Ok(())
+        self.post_edit("data/meal-planning-calendar/update", &operation_list)
+            .await
     }
 
     /// Add ingredients from meal plan to a shopping list for a date range
@@ -297,7 +290,7 @@ impl AnyListClient {
     }
 }
 
-fn meal_plan_event_from_pb(event: &PbCalendarEvent) -> MealPlanEvent {
+pub(crate) fn meal_plan_event_from_pb(event: &PbCalendarEvent) -> MealPlanEvent {
     MealPlanEvent {
         id: event.identifier.clone(),
         calendar_id: event.calendar_id.clone(),
@@ -0,0 +1,430 @@
+//! Offline queue for mutations that could not be sent.
+//!
+//! Every mutation carries operations with IDs generated on the client, and
+//! the server ignores an operation ID it has already processed, so a
+//! mutation that fails for lack of a network can safely be sent again later.
+//! With [`AnyListClientBuilder::offline_queue`](crate::AnyListClientBuilder::offline_queue)
+//! the client does exactly that:
+//!
+//! - A mutation that fails with a retryable error (see
+//!   [`AnyListError::is_retryable`]) is saved to the queue instead, and
+//!   returns an [`OperationOutcome`] whose
+//!   [`is_queued`](OperationOutcome::is_queued) is true.
+//! - Operations are stored as `PBSyncOperation`s, one after another in a
+//!   length-delimited file, and written out on every change, so they survive
+//!   a restart. Each write goes to a temporary file that is synced to disk
+//!   before it replaces the queue, so a crash leaves either the old queue or
+//!   the new one.
+//! - [`AnyListClient::replay_offline_queue`] sends them in order. The client
+//!   also replays the queue before each new mutation, and queues the new one
+//!   behind it if the network is still down, so changes reach the server in
+//!   the order they were made.
+//! - Operations the server rejects, or whose response can't be understood,
+//!   are dropped from the queue and reported as [`FailedOperations`], so
+//!   they can't hold up the operations queued behind them. Operations that
+//!   couldn't be sent because the client isn't authenticated stay queued.
+
+use crate::client::AnyListClient;
+use crate::error::{AnyListError, Result};
+use crate::outcome::{OperationList, OperationOutcome};
+use crate::protobuf::anylist::PbSyncOperation;
+use prost::Message;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Operations dropped from the offline queue because the server rejected
+/// them or answered with a response that couldn't be understood
+#[derive(Debug)]
+pub struct FailedOperations {
+    pub(crate) operation_ids: Vec<String>,
+    pub(crate) error: AnyListError,
+}
+
+impl FailedOperations {
+    /// IDs of the operations that were sent together and failed
+    pub fn operation_ids(&self) -> &[String] {
+        &self.operation_ids
+    }
+
+    pub fn error(&self) -> &AnyListError {
+        &self.error
+    }
+}
+
+/// Result of replaying the offline queue
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub(crate) replayed_operation_ids: Vec<String>,
+    pub(crate) failed: Vec<FailedOperations>,
+    pub(crate) pending: usize,
+}
+
+impl ReplayReport {
+    /// IDs of the operations the server processed
+    pub fn replayed_operation_ids(&self) -> &[String] {
+        &self.replayed_operation_ids
+    }
+
+    /// Operations dropped from the queue, including ones dropped while the
+    /// queue was replayed ahead of a new mutation
+    pub fn failed(&self) -> &[FailedOperations] {
+        &self.failed
+    }
+
+    /// Number of operations still queued because the network is still down
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+}
+
+/// Queued operations, mirrored to a file
+pub(crate) struct OfflineQueue {
+    path: PathBuf,
+    pending: Vec<PbSyncOperation>,
+    /// Dropped while replaying, until the next [`ReplayReport`] hands them out
+    failed: Vec<FailedOperations>,
+}
+
+impl OfflineQueue {
+    /// Load the queue saved at `path`, or start an empty one if there is no
+    /// file yet
+    pub(crate) fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let pending = match std::fs::read(&path) {
+            Ok(bytes) => decode_operations(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(AnyListError::Other(format!(
+                    "Failed to read offline queue {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        Ok(Self {
+            path,
+            pending,
+            failed: Vec::new(),
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Add operations to the back of the queue. They aren't saved until
+    /// the next [`snapshot`](Self::snapshot) is written.
+    pub(crate) fn push(&mut self, operations: Vec<PbSyncOperation>) {
+        self.pending.extend(operations);
+    }
+
+    /// The operations at the front of the queue that go to the same
+    /// endpoint, to be sent in one request
+    fn next_batch(&self) -> Vec<PbSyncOperation> {
+        let Some(first) = self.pending.first() else {
+            return Vec::new();
+        };
+        self.pending
+            .iter()
+            .take_while(|op| op.operation_queue_id == first.operation_queue_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Drop the first `count` operations, which have been sent
+    fn pop_front(&mut self, count: usize) {
+        self.pending.drain(..count.min(self.pending.len()));
+    }
+
+    /// The queue as it should be saved, to write without holding the lock
+    fn snapshot(&self) -> Result<QueueSnapshot> {
+        let mut bytes = Vec::new();
+        for op in &self.pending {
+            op.encode_length_delimited(&mut bytes)
+                .map_err(|e| AnyListError::Other(format!("Failed to encode operation: {}", e)))?;
+        }
+        Ok(QueueSnapshot {
+            path: self.path.clone(),
+            bytes,
+        })
+    }
+}
+
+/// Encoded contents of the queue file
+struct QueueSnapshot {
+    path: PathBuf,
+    bytes: Vec<u8>,
+}
+
+impl QueueSnapshot {
+    /// Write to a temporary file, sync it, and move it into place, so a
+    /// crash never leaves a half-written queue behind. Blocks.
+    fn write(&self) -> Result<()> {
+        let tmp = temporary_path(&self.path);
+        let written = (|| {
+            let mut file = std::fs::File::create(&tmp)?;
+            file.write_all(&self.bytes)?;
+            file.sync_all()?;
+            std::fs::rename(&tmp, &self.path)?;
+            sync_parent_directory(&self.path)
+        })();
+        written.map_err(|e| {
+            AnyListError::Other(format!(
+                "Failed to write offline queue {}: {}",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Make a rename in `path`'s directory durable. Directories can only be
+/// opened for syncing on Unix; elsewhere the rename is left to the OS.
+fn sync_parent_directory(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+fn decode_operations(mut bytes: &[u8]) -> Result<Vec<PbSyncOperation>> {
+    let mut operations = Vec::new();
+    while !bytes.is_empty() {
+        operations.push(PbSyncOperation::decode_length_delimited(&mut bytes)?);
+    }
+    Ok(operations)
+}
+
+/// Whether `error` means the client couldn't authenticate, rather than
+/// anything being wrong with the operations: they can be sent once it can
+fn is_authentication_error(error: &AnyListError) -> bool {
+    matches!(
+        error,
+        AnyListError::AuthenticationFailed(_)
+            | AnyListError::InvalidCredentials
+            | AnyListError::ClientError { status: 401, .. }
+    )
+}
+
+/// Encode queued operations as the operation list they came from.
+///
+/// Every `PB...OperationList` is a single `repeated ... operations = 1`
+/// field, so the list is the operations written as field 1, whatever their
+/// class.
+fn encode_operation_list(operations: &[PbSyncOperation]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for op in operations {
+        prost::encoding::bytes::encode(
+            1,
+            op.encoded_operation.as_ref().unwrap_or(&Vec::new()),
+            &mut buf,
+        );
+    }
+    buf
+}
+
+impl AnyListClient {
+    /// Number of operations waiting in the offline queue
+    pub fn offline_queue_len(&self) -> usize {
+        self.offline_queue
+            .as_ref()
+            .map_or(0, |queue| queue.lock().unwrap().len())
+    }
+
+    /// Send the queued operations in order, until the queue is empty or the
+    /// network fails again.
+    ///
+    /// Call this when connectivity returns. Fails if the queue file cannot
+    /// be written or the client cannot authenticate, leaving the operations
+    /// queued; operations that fail any other way are dropped and reported
+    /// in the [`ReplayReport`].
+    pub async fn replay_offline_queue(&self) -> Result<ReplayReport> {
+        let mut report = ReplayReport::default();
+        let Some(queue) = &self.offline_queue else {
+            return Ok(report);
+        };
+
+        let _replaying = self.offline_replay.lock().await;
+        self.replay_pending(&mut report.replayed_operation_ids)
+            .await?;
+
+        let mut queue = queue.lock().unwrap();
+        report.failed = std::mem::take(&mut queue.failed);
+        report.pending = queue.len();
+        Ok(report)
+    }
+
+    /// Send queued batches until the queue is empty or a batch fails with a
+    /// retryable error. Fails, leaving the batch queued, if the client can't
+    /// authenticate. A batch that fails any other way would fail again, so
+    /// it is moved to `failed` instead of blocking the rest of the queue.
+    ///
+    /// The caller must hold `offline_replay`.
+    async fn replay_pending(&self, replayed: &mut Vec<String>) -> Result<()> {
+        let Some(queue) = &self.offline_queue else {
+            return Ok(());
+        };
+
+        loop {
+            let batch = queue.lock().unwrap().next_batch();
+            let Some(endpoint) = batch.first().and_then(|op| op.operation_queue_id.clone()) else {
+                return Ok(());
+            };
+            let operation_ids: Vec<String> = batch
+                .iter()
+                .filter_map(|op| op.identifier.clone())
+                .collect();
+
+            let result = match self.post(&endpoint, encode_operation_list(&batch)).await {
+                Ok(response) => OperationOutcome::from_response(&response, &operation_ids),
+                Err(e) => Err(e),
+            };
+
+            {
+                let mut queue = queue.lock().unwrap();
+                match result {
+                    Ok(_) => replayed.extend(operation_ids),
+                    Err(e) if e.is_retryable() => return Ok(()),
+                    Err(e) if is_authentication_error(&e) => return Err(e),
+                    Err(AnyListError::OperationNotProcessed {
+                        operation_ids: dropped,
+                    }) => {
+                        replayed
+                            .extend(operation_ids.into_iter().filter(|id| !dropped.contains(id)));
+                        queue.failed.push(FailedOperations {
+                            operation_ids: dropped.clone(),
+                            error: AnyListError::OperationNotProcessed {
+                                operation_ids: dropped,
+                            },
+                        });
+                    }
+                    Err(error) => queue.failed.push(FailedOperations {
+                        operation_ids,
+                        error,
+                    }),
+                }
+                queue.pop_front(batch.len());
+            }
+            self.save_offline_queue().await?;
+        }
+    }
+
+    /// Write the offline queue to its file, on a blocking thread. The
+    /// caller must hold `offline_replay`, so writes happen in order.
+    async fn save_offline_queue(&self) -> Result<()> {
+        let Some(queue) = &self.offline_queue else {
+            return Ok(());
+        };
+        let snapshot = queue.lock().unwrap().snapshot()?;
+        tokio::task::spawn_blocking(move || snapshot.write())
+            .await
+            .map_err(|e| AnyListError::Other(format!("Offline queue task failed: {}", e)))?
+    }
+
+    /// Post an operation list, or queue it if the client has an offline
+    /// queue and the list cannot be sent now
+    pub(crate) async fn post_edit_or_queue<M: OperationList>(
+        &self,
+        endpoint: &str,
+        operation_list: &M,
+    ) -> Result<OperationOutcome> {
+        let Some(queue) = &self.offline_queue else {
+            return self.send_edit(endpoint, operation_list).await;
+        };
+        let _replaying = self.offline_replay.lock().await;
+
+        // Queued operations go first, so changes arrive in order
+        if !queue.lock().unwrap().is_empty() {
+            self.replay_pending(&mut Vec::new()).await?;
+        }
+
+        if queue.lock().unwrap().is_empty() {
+            match self.send_edit(endpoint, operation_list).await {
+                Err(e) if e.is_retryable() => {}
+                result => return result,
+            }
+        }
+
+        queue
+            .lock()
+            .unwrap()
+            .push(operation_list.sync_operations(endpoint));
+        self.save_offline_queue().await?;
+        Ok(OperationOutcome {
+            queued_operation_ids: operation_list.operation_ids(),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::anylist::{PbListOperation, PbListOperationList, PbOperationMetadata};
+
+    fn operation_list(ids: &[&str]) -> PbListOperationList {
+        PbListOperationList {
+            operations: ids
+                .iter()
+                .map(|id| PbListOperation {
+                    metadata: Some(PbOperationMetadata {
+                        operation_id: Some(id.to_string()),
+                        handler_id: Some("set-list-item-checked".to_string()),
+                        ..Default::default()
+                    }),
+                    list_id: Some("list-1".to_string()),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_queue_round_trips_through_its_file() {
+        let path =
+            std::env::temp_dir().join(format!("anylist_rs-offline-{}.queue", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut queue = OfflineQueue::open(&path).unwrap();
+        assert!(queue.is_empty());
+        queue.push(operation_list(&["op-1", "op-2"]).sync_operations("data/shopping-lists/update"));
+        queue.push(operation_list(&["op-3"]).sync_operations("data/list-settings/update"));
+        queue.snapshot().unwrap().write().unwrap();
+        assert!(!temporary_path(&path).exists());
+
+        let mut queue = OfflineQueue::open(&path).unwrap();
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.next_batch().len(), 2);
+        queue.pop_front(2);
+        queue.snapshot().unwrap().write().unwrap();
+
+        let queue = OfflineQueue::open(&path).unwrap();
+        assert_eq!(queue.pending[0].identifier.as_deref(), Some("op-3"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_queued_operations_encode_as_their_operation_list() {
+        let list = operation_list(&["op-1", "op-2"]);
+        let encoded = encode_operation_list(&list.sync_operations("data/shopping-lists/update"));
+        assert_eq!(PbListOperationList::decode(encoded.as_ref()).unwrap(), list);
+    }
+}
@@ -208,6 +208,80 @@ pub fn build_rename_list_operation(params: RenameListParams) -> PbListOperationL
 // Store Operations
 // ============================================================================
 
+/// Parameters for creating a store
+pub struct CreateStoreParams {
+    pub store_id: String,
+    pub list_id: String,
+    pub name: String,
+    pub operation_id: String,
+    pub user_id: String,
+}
+
+/// Build a new-store operation (pure function)
+pub fn build_create_store_operation(params: CreateStoreParams) -> PbListOperationList {
+    let pb_store = PbStore {
+        identifier: params.store_id,
+        logical_timestamp: None,
+        list_id: Some(params.list_id.clone()),
+        name: Some(params.name),
+        // TODO: set this as the lists num. of stores + 1
+        sort_index: Some(0),
+    };
+
+    let operation = PbListOperation {
+        metadata: Some(PbOperationMetadata {
+            operation_id: Some(params.operation_id),
+            handler_id: Some("new-store".to_string()),
+            user_id: Some(params.user_id),
+            operation_class: Some(OperationClass::Store as i32),
+        }),
+        list_id: Some(params.list_id),
+        updated_store: Some(pb_store),
+        ..Default::default()
+    };
+
+    PbListOperationList {
+        operations: vec![operation],
+    }
+}
+
+/// Parameters for renaming a store
+pub struct RenameStoreParams {
+    pub store_id: String,
+    pub list_id: String,
+    pub new_name: String,
+    pub operation_id: String,
+    pub user_id: String,
+}
+
+/// Build a set-store-name operation (pure function)
+pub fn build_rename_store_operation(params: RenameStoreParams) -> PbListOperationList {
+    let pb_store = PbStore {
+        identifier: params.store_id,
+        logical_timestamp: Some(1),
+        list_id: Some(params.list_id.clone()),
+        name: Some(params.new_name.clone()),
+        sort_index: Some(0),
+    };
+
+    let operation = PbListOperation {
+        metadata: Some(PbOperationMetadata {
+            operation_id: Some(params.operation_id),
+            handler_id: Some("set-store-name".to_string()),
+            user_id: Some(params.user_id),
+            operation_class: Some(OperationClass::Store as i32),
+        }),
+        list_id: Some(params.list_id),
+        updated_value: Some(params.new_name),
+        updated_store: Some(pb_store),
+        ..Default::default()
+    };
+
+    PbListOperationList {
+        operations: vec![operation],
+    }
+}
+
 /// Parameters for removing store from items
 pub struct RemoveStoreFromItemsParams {
     pub list_id: String,
@@ -367,6 +441,8 @@ pub struct AddItemParams {
     pub operation_id: String,
     pub user_id: String,
     pub name: String,
+    pub quantity: Option<String>,
+    pub details: Option<String>,
     pub category: Option<String>,
     pub category_match_id: Option<String>,
     pub category_assignment: Option<CategoryAssignment>,
@@ -396,8 +472,8 @@ pub fn build_add_item_operation(params: AddItemParams) -> PbListOperationList {
         server_mod_time: None,
         list_id: Some(params.list_id.clone()),
         name: Some(params.name),
-        quantity: None,
-        details: None,
+        quantity: params.quantity,
+        details: params.details,
         checked: None,
         recipe_id: None,
         raw_ingredient: None,
@@ -433,6 +509,94 @@ pub fn build_add_item_operation(params: AddItemParams) -> PbListOperationList {
     }
 }
 
+/// Parameters for replacing an item's name, quantity, details and category
+pub struct UpdateItemParams {
+    pub item_id: String,
+    pub list_id: String,
+    pub operation_id: String,
+    pub user_id: String,
+    pub timestamp: f64,
+    pub name: String,
+    pub quantity: Option<String>,
+    pub details: Option<String>,
+    pub category: Option<String>,
+}
+
+/// Build an update-list-item operation (pure function)
+pub fn build_update_item_operation(params: UpdateItemParams) -> PbListOperationList {
+    let pb_item = PbListItem {
+        identifier: params.item_id.clone(),
+        server_mod_time: Some(params.timestamp),
+        list_id: Some(params.list_id.clone()),
+        name: Some(params.name),
+        quantity: params.quantity,
+        details: params.details,
+        checked: Some(false),
+        recipe_id: None,
+        raw_ingredient: None,
+        price_matchup_tag: None,
+        price_id: None,
+        category: params.category,
+        user_id: Some(params.user_id.clone()),
+        category_match_id: None,
+        photo_ids: vec![],
+        event_id: None,
+        store_ids: vec![],
+        manual_sort_index: Some(0),
+        prices: vec![],
+        category_assignments: vec![],
+        product_upc: None,
+    };
+
+    // Note: "update-list-item" handler not found in API docs - may need to use specific handlers
+    // like set-list-item-name, set-list-item-quantity-v2, set-list-item-details
+    let operation = PbListOperation {
+        metadata: Some(PbOperationMetadata {
+            operation_id: Some(params.operation_id),
+            handler_id: Some("update-list-item".to_string()),
+            user_id: Some(params.user_id),
+            operation_class: Some(OperationClass::Undefined as i32),
+        }),
+        list_id: Some(params.list_id),
+        list_item_id: Some(params.item_id),
+        list_item: Some(pb_item),
+        ..Default::default()
+    };
+
+    PbListOperationList {
+        operations: vec![operation],
+    }
+}
+
+/// Parameters for checking or unchecking an item
+pub struct SetItemCheckedParams {
+    pub item_id: String,
+    pub list_id: String,
+    pub checked: bool,
+    pub operation_id: String,
+    pub user_id: String,
+}
+
+/// Build a set-list-item-checked operation (pure function)
+pub fn build_set_item_checked_operation(params: SetItemCheckedParams) -> PbListOperationList {
+    let operation = PbListOperation {
+        metadata: Some(PbOperationMetadata {
+            operation_id: Some(params.operation_id),
+            handler_id: Some("set-list-item-checked".to_string()),
+            user_id: Some(params.user_id),
+            operation_class: Some(OperationClass::Undefined as i32),
+        }),
+        list_id: Some(params.list_id),
+        list_item_id: Some(params.item_id),
+        updated_value: Some(if params.checked { "y" } else { "n" }.to_string()),
+        ..Default::default()
+    };
+
+    PbListOperationList {
+        operations: vec![operation],
+    }
+}
+
 /// Parameters for bulk removing items from a list
 pub struct BulkRemoveItemsParams {
     pub operation_id: String,
@@ -528,6 +692,94 @@ pub fn build_bulk_remove_items_operation(params: BulkRemoveItemsParams) -> PbLis
     }
 }
 
+// ============================================================================
+// Category Operations
+// ============================================================================
+
+use crate::protobuf::anylist::PbListCategory;
+
+/// Parameters for creating a category, or renaming one
+pub struct CategoryParams {
+    pub category_id: String,
+    pub category_group_id: String,
+    pub list_id: String,
+    pub name: String,
+    pub operation_id: String,
+    pub user_id: String,
+}
+
+/// Build a create-category operation (pure function)
+pub fn build_create_category_operation(params: CategoryParams) -> PbListOperationList {
+    PbListOperationList {
+        operations: vec![category_operation("create-category", params)],
+    }
+}
+
+/// Build a set-category-name operation (pure function)
+pub fn build_rename_category_operation(params: CategoryParams) -> PbListOperationList {
+    let mut operation = category_operation("set-category-name", params);
+    operation.updated_value = operation
+        .updated_category
+        .as_ref()
+        .and_then(|category| category.name.clone());
+
+    PbListOperationList {
+        operations: vec![operation],
+    }
+}
+
+fn category_operation(handler_id: &str, params: CategoryParams) -> PbListOperation {
+    let pb_category = PbListCategory {
+        identifier: Some(params.category_id),
+        logical_timestamp: Some(1),
+        category_group_id: Some(params.category_group_id),
+        list_id: Some(params.list_id.clone()),
+        name: Some(params.name),
+        icon: None,
+        system_category: None,
+        sort_index: Some(0),
+    };
+
+    PbListOperation {
+        metadata: Some(PbOperationMetadata {
+            operation_id: Some(params.operation_id),
+            handler_id: Some(handler_id.to_string()),
+            user_id: Some(params.user_id),
+            operation_class: Some(OperationClass::ListCategory as i32),
+        }),
+        list_id: Some(params.list_id),
+        updated_category: Some(pb_category),
+        ..Default::default()
+    }
+}
+
+/// Parameters for deleting a category
+pub struct DeleteCategoryParams {
+    pub category_id: String,
+    pub list_id: String,
+    pub operation_id: String,
+    pub user_id: String,
+}
+
+/// Build a remove-category operation (pure function)
+pub fn build_delete_category_operation(params: DeleteCategoryParams) -> PbListOperationList {
+    let operation = PbListOperation {
+        metadata: Some(PbOperationMetadata {
+            operation_id: Some(params.operation_id),
+            handler_id: Some("remove-category".to_string()),
+            user_id: Some(params.user_id),
+            operation_class: Some(OperationClass::ListCategory as i32),
+        }),
+        list_id: Some(params.list_id),
+        original_value: Some(params.category_id),
+        ..Default::default()
+    };
+
+    PbListOperationList {
+        operations: vec![operation],
+    }
+}
+
 // ============================================================================
 // Favourite Operations
 // ============================================================================
@@ -798,6 +1050,137 @@ mod tests {
         insta::assert_snapshot!(hex::encode(&buf));
     }
 
+    #[test]
+    fn test_create_store_operation_snapshot() {
+        let params = CreateStoreParams {
+            store_id: "store-123".to_string(),
+            list_id: "list-1".to_string(),
+            name: "Costco".to_string(),
+            operation_id: "op-create-store-1".to_string(),
+            user_id: "user-1".to_string(),
+        };
+
+        let operation_list = build_create_store_operation(params);
+        let mut buf = Vec::new();
+        operation_list.encode(&mut buf).unwrap();
+
+        insta::assert_snapshot!(hex::encode(&buf));
+    }
+
+    #[test]
+    fn test_rename_store_operation_snapshot() {
+        let params = RenameStoreParams {
+            store_id: "store-123".to_string(),
+            list_id: "list-1".to_string(),
+            new_name: "Safeway".to_string(),
+            operation_id: "op-rename-store-1".to_string(),
+            user_id: "user-1".to_string(),
+        };
+
+        let operation_list = build_rename_store_operation(params);
+        let mut buf = Vec::new();
+        operation_list.encode(&mut buf).unwrap();
+
+        insta::assert_snapshot!(hex::encode(&buf));
+    }
+
+    #[test]
+    fn test_update_item_operation_snapshot() {
+        let params = UpdateItemParams {
+            item_id: "item-1".to_string(),
+            list_id: "list-1".to_string(),
+            operation_id: "op-update-item-1".to_string(),
+            user_id: "user-1".to_string(),
+            timestamp: 1700000000.0,
+            name: "Milk".to_string(),
+            quantity: Some("2 litres".to_string()),
+            details: Some("Semi-skimmed".to_string()),
+            category: Some("dairy".to_string()),
+        };
+
+        let operation_list = build_update_item_operation(params);
+        let mut buf = Vec::new();
+        operation_list.encode(&mut buf).unwrap();
+
+        insta::assert_snapshot!(hex::encode(&buf));
+    }
+
+    #[test]
+    fn test_set_item_checked_uses_y_and_n() {
+        let params = |checked| SetItemCheckedParams {
+            item_id: "item-1".to_string(),
+            list_id: "list-1".to_string(),
+            checked,
+            operation_id: "op-check-1".to_string(),
+            user_id: "user-1".to_string(),
+        };
+
+        let unchecked = build_set_item_checked_operation(params(false));
+        assert_eq!(unchecked.operations[0].updated_value, Some("n".to_string()));
+
+        let operation_list = build_set_item_checked_operation(params(true));
+        assert_eq!(
+            operation_list.operations[0].updated_value,
+            Some("y".to_string())
+        );
+        let mut buf = Vec::new();
+        operation_list.encode(&mut buf).unwrap();
+        insta::assert_snapshot!(hex::encode(&buf));
+    }
+
+    fn test_category_params() -> CategoryParams {
+        CategoryParams {
+            category_id: "category-1".to_string(),
+            category_group_id: "group-1".to_string(),
+            list_id: "list-1".to_string(),
+            name: "Bakery".to_string(),
+            operation_id: "op-category-1".to_string(),
+            user_id: "user-1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_create_category_operation_snapshot() {
+        let operation_list = build_create_category_operation(test_category_params());
+        assert_eq!(operation_list.operations[0].updated_value, None);
+
+        let mut buf = Vec::new();
+        operation_list.encode(&mut buf).unwrap();
+        insta::assert_snapshot!(hex::encode(&buf));
+    }
+
+    #[test]
+    fn test_rename_category_operation_snapshot() {
+        let operation_list = build_rename_category_operation(test_category_params());
+        assert_eq!(
+            operation_list.operations[0].updated_value,
+            Some("Bakery".to_string())
+        );
+
+        let mut buf = Vec::new();
+        operation_list.encode(&mut buf).unwrap();
+        insta::assert_snapshot!(hex::encode(&buf));
+    }
+
+    #[test]
+    fn test_delete_category_uses_original_value_field() {
+        let params = DeleteCategoryParams {
+            category_id: "category-1".to_string(),
+            list_id: "list-1".to_string(),
+            operation_id: "op-delete-category-1".to_string(),
+            user_id: "user-1".to_string(),
+        };
+
+        let operation_list = build_delete_category_operation(params);
+        let op = &operation_list.operations[0];
+        assert_eq!(op.original_value, Some("category-1".to_string()));
+        assert_eq!(op.updated_value, None);
+
+        let mut buf = Vec::new();
+        operation_list.encode(&mut buf).unwrap();
+        insta::assert_snapshot!(hex::encode(&buf));
+    }
+
     #[test]
     fn test_webapp_add_shopping_list_item_2025_10_28() {
         let params = AddItemParams {
@@ -806,6 +1189,8 @@ mod tests {
             operation_id: "0da34b3d00f54ce1bd6fd501ddf62f99".to_string(),
             user_id: "cda21b0078644a01b640c84d3d74187e".to_string(),
             name: "nice new things".to_string(),
+            quantity: None,
+            details: None,
             category: Some("other".to_string()),
             category_match_id: Some("other".to_string()),
             category_assignment: Some(CategoryAssignment {
@@ -0,0 +1,283 @@
+//! Server acknowledgement of mutations.
+//!
+//! Every `.../update` endpoint answers with a `PBEditOperationResponse`
+//! listing the operations it processed and the timestamps of the containers
+//! (lists, recipe data, calendars, ...) they changed. Mutations decode it into
+//! an [`OperationOutcome`] and fail with
+//! [`AnyListError::OperationNotProcessed`] if any operation they sent is
+//! missing from it.
+
+use crate::error::{AnyListError, Result};
+use crate::protobuf::anylist::{
+    PbCalendarOperationList, PbEditOperationResponse, PbListFolderOperationList,
+    PbListOperationList, PbListSettingsOperationList, PbRecipeOperationList,
+    PbStarterListOperationList, PbSyncOperation,
+};
+use prost::Message;
+use serde_derive::{Deserialize, Serialize};
+
+/// Wall-clock modification timestamp of a server-side container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timestamp {
+    pub(crate) id: String,
+    pub(crate) value: f64,
+}
+
+impl Timestamp {
+    /// ID of the container (list, recipe data, calendar, ...)
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Seconds since the Unix epoch
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+/// Logical (monotonically increasing) timestamp of a server-side container
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogicalTimestamp {
+    pub(crate) id: String,
+    pub(crate) value: u64,
+}
+
+impl LogicalTimestamp {
+    /// ID of the container (list, recipe data, calendar, ...)
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+/// Result of a mutation, as confirmed by the server
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationOutcome {
+    pub(crate) processed_operation_ids: Vec<String>,
+    pub(crate) new_timestamps: Vec<Timestamp>,
+    pub(crate) logical_timestamps: Vec<LogicalTimestamp>,
+    pub(crate) full_refresh_ids: Vec<String>,
+    #[serde(default)]
+    pub(crate) queued_operation_ids: Vec<String>,
+}
+
+impl OperationOutcome {
+    /// IDs of the operations the server processed
+    pub fn processed_operation_ids(&self) -> &[String] {
+        &self.processed_operation_ids
+    }
+
+    /// Whether the server processed the operation with this ID
+    pub fn is_processed(&self, operation_id: &str) -> bool {
+        self.processed_operation_ids
+            .iter()
+            .any(|id| id == operation_id)
+    }
+
+    /// Modification timestamps of the containers the mutation changed
+    pub fn new_timestamps(&self) -> &[Timestamp] {
+        &self.new_timestamps
+    }
+
+    /// Current logical timestamps of the containers the mutation changed
+    pub fn logical_timestamps(&self) -> &[LogicalTimestamp] {
+        &self.logical_timestamps
+    }
+
+    /// Current logical timestamp of one container, if the mutation changed it
+    pub fn logical_timestamp(&self, id: &str) -> Option<u64> {
+        self.logical_timestamps
+            .iter()
+            .find(|t| t.id == id)
+            .map(|t| t.value)
+    }
+
+    /// IDs of containers whose local copy is stale and should be re-fetched
+    /// in full
+    pub fn full_refresh_ids(&self) -> &[String] {
+        &self.full_refresh_ids
+    }
+
+    /// IDs of operations that could not be sent and were saved to the
+    /// offline queue (see [`crate::offline`])
+    pub fn queued_operation_ids(&self) -> &[String] {
+        &self.queued_operation_ids
+    }
+
+    /// Whether the mutation was queued to be sent later rather than
+    /// processed by the server
+    pub fn is_queued(&self) -> bool {
+        !self.queued_operation_ids.is_empty()
+    }
+
+    /// Decode a `PBEditOperationResponse`, checking that every operation in
+    /// `sent_operation_ids` was processed
+    pub(crate) fn from_response(body: &[u8], sent_operation_ids: &[String]) -> Result<Self> {
+        let response = PbEditOperationResponse::decode(body)?;
+        let outcome = Self::from(response);
+
+        let dropped: Vec<String> = sent_operation_ids
+            .iter()
+            .filter(|id| !outcome.is_processed(id))
+            .cloned()
+            .collect();
+        if !dropped.is_empty() {
+            return Err(AnyListError::OperationNotProcessed {
+                operation_ids: dropped,
+            });
+        }
+
+        Ok(outcome)
+    }
+
+    /// Combine the outcomes of several requests made for one mutation
+    pub(crate) fn merge(mut self, other: OperationOutcome) -> Self {
+        self.processed_operation_ids
+            .extend(other.processed_operation_ids);
+        self.new_timestamps.extend(other.new_timestamps);
+        self.logical_timestamps.extend(other.logical_timestamps);
+        self.full_refresh_ids.extend(other.full_refresh_ids);
+        self.queued_operation_ids.extend(other.queued_operation_ids);
+        self
+    }
+}
+
+impl From<PbEditOperationResponse> for OperationOutcome {
+    fn from(response: PbEditOperationResponse) -> Self {
+        OperationOutcome {
+            processed_operation_ids: response.processed_operations,
+            new_timestamps: response
+                .new_timestamps
+                .into_iter()
+                .filter_map(|t| {
+                    Some(Timestamp {
+                        id: t.identifier?,
+                        value: t.timestamp.unwrap_or_default(),
+                    })
+                })
+                .collect(),
+            logical_timestamps: response
+                .current_logical_timestamps
+                .into_iter()
+                .filter_map(|t| {
+                    Some(LogicalTimestamp {
+                        id: t.identifier?,
+                        value: t.logical_timestamp.unwrap_or_default(),
+                    })
+                })
+                .collect(),
+            full_refresh_ids: response.full_refresh_timestamp_ids,
+            queued_operation_ids: Vec::new(),
+        }
+    }
+}
+
+/// An operation list that can be posted to an `.../update` endpoint
+pub(crate) trait OperationList: Message {
+    /// IDs of the operations in the list, from their metadata
+    fn operation_ids(&self) -> Vec<String>;
+
+    /// The operations one by one, as `PBSyncOperation`s for the offline queue
+    fn sync_operations(&self, endpoint: &str) -> Vec<PbSyncOperation>;
+}
+
+macro_rules! impl_operation_list {
+    ($($list:ty => $class:literal),* $(,)?) => {
+        $(
+            impl OperationList for $list {
+                fn operation_ids(&self) -> Vec<String> {
+                    self.operations
+                        .iter()
+                        .filter_map(|op| op.metadata.as_ref()?.operation_id.clone())
+                        .collect()
+                }
+
+                fn sync_operations(&self, endpoint: &str) -> Vec<PbSyncOperation> {
+                    self.operations
+                        .iter()
+                        .map(|op| PbSyncOperation {
+                            identifier: op.metadata.as_ref().and_then(|m| m.operation_id.clone()),
+                            operation_queue_id: Some(endpoint.to_string()),
+                            operation_class_name: Some($class.to_string()),
+                            encoded_operation: Some(op.encode_to_vec()),
+                        })
+                        .collect()
+                }
+            }
+        )*
+    };
+}
+
+impl_operation_list!(
+    PbListOperationList => "PBListOperation",
+    PbListFolderOperationList => "PBListFolderOperation",
+    PbListSettingsOperationList => "PBListSettingsOperation",
+    PbStarterListOperationList => "PBStarterListOperation",
+    PbRecipeOperationList => "PBRecipeOperation",
+    PbCalendarOperationList => "PBCalendarOperation",
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::anylist::{PbLogicalTimestamp, PbTimestamp};
+
+    fn response(processed: &[&str]) -> Vec<u8> {
+        PbEditOperationResponse {
+            processed_operations: processed.iter().map(|s| s.to_string()).collect(),
+            new_timestamps: vec![PbTimestamp {
+                identifier: Some("list-1".to_string()),
+                timestamp: Some(1700000000.5),
+            }],
+            current_logical_timestamps: vec![PbLogicalTimestamp {
+                identifier: Some("list-1".to_string()),
+                logical_timestamp: Some(42),
+                description: None,
+            }],
+            ..Default::default()
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_outcome_exposes_processed_operations_and_timestamps() {
+        let outcome =
+            OperationOutcome::from_response(&response(&["op-1"]), &["op-1".to_string()]).unwrap();
+
+        assert!(outcome.is_processed("op-1"));
+        assert_eq!(outcome.logical_timestamp("list-1"), Some(42));
+        assert_eq!(outcome.logical_timestamp("list-2"), None);
+        assert_eq!(outcome.new_timestamps()[0].value(), 1700000000.5);
+    }
+
+    #[test]
+    fn test_dropped_operation_is_an_error() {
+        let result = OperationOutcome::from_response(
+            &response(&["op-1"]),
+            &["op-1".to_string(), "op-2".to_string()],
+        );
+
+        match result {
+            Err(AnyListError::OperationNotProcessed { operation_ids }) => {
+                assert_eq!(operation_ids, vec!["op-2".to_string()]);
+            }
+            other => panic!("expected OperationNotProcessed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_operation_ids_come_from_metadata() {
+        let list =
+            crate::operations::build_create_list_operation(crate::operations::CreateListParams {
+                list_id: "list-1".to_string(),
+                operation_id: "op-1".to_string(),
+                user_id: "user-1".to_string(),
+                timestamp: 0.0,
+                name: "Groceries".to_string(),
+            });
+        assert_eq!(list.operation_ids(), vec!["op-1".to_string()]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+/// Heartbeat and reconnection settings for [`RealtimeSync`](super::RealtimeSync).
+///
+/// The defaults suit an app in the foreground: a heartbeat every 5 seconds,
+/// and reconnection attempts forever, backing off from 2 seconds to 2
+/// minutes. A battery-powered device might send heartbeats less often and
+/// give up sooner; a server-side worker might reconnect more eagerly.
+///
+/// # Example
+///
+/// ```no_run
+/// use anylist_rs::realtime::{RealtimeConfig, RealtimeSync};
+/// use anylist_rs::AnyListClient;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// # fn example(client: Arc<AnyListClient>) {
+/// let config = RealtimeConfig::new()
+///     .heartbeat_interval(Duration::from_secs(30))
+///     .max_missed_heartbeats(2)
+///     .max_reconnect_attempts(5);
+/// let sync = RealtimeSync::new(client, config, |event| println!("{:?}", event));
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RealtimeConfig {
+    pub(crate) heartbeat_interval: Duration,
+    pub(crate) max_missed_heartbeats: u8,
+    pub(crate) reconnect_initial_delay: Duration,
+    pub(crate) reconnect_max_delay: Duration,
+    pub(crate) reconnect_jitter: f64,
+    pub(crate) max_reconnect_attempts: Option<u32>,
+}
+
+impl Default for RealtimeConfig {
+    /// Heartbeats every 5s, reconnecting after 3 are missed; unlimited
+    /// reconnection attempts from 2s up to 120s, with 20% jitter
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(5),
+            max_missed_heartbeats: 3,
+            reconnect_initial_delay: Duration::from_secs(2),
+            reconnect_max_delay: Duration::from_secs(120),
+            reconnect_jitter: 0.2,
+            max_reconnect_attempts: None,
+        }
+    }
+}
+
+impl RealtimeConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often a heartbeat is sent (minimum 1ms)
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Heartbeats the server may leave unanswered before the connection is
+    /// considered dead and replaced (minimum 1)
+    pub fn max_missed_heartbeats(mut self, max_missed: u8) -> Self {
+        self.max_missed_heartbeats = max_missed.max(1);
+        self
+    }
+
+    /// Delay before the first reconnection attempt
+    pub fn reconnect_initial_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_initial_delay = delay;
+        self
+    }
+
+    /// Upper bound for the exponential backoff between reconnection attempts
+    pub fn reconnect_max_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_max_delay = delay;
+        self
+    }
+
+    /// Fraction (0.0 to 1.0) by which each reconnection delay may be
+    /// randomly shortened, so clients dropped by the same outage don't all
+    /// come back at once
+    pub fn reconnect_jitter(mut self, jitter: f64) -> Self {
+        self.reconnect_jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Reconnection attempts in a row before giving up and moving to
+    /// [`ConnectionState::Closed`](super::ConnectionState::Closed). Unlimited
+    /// by default.
+    pub fn max_reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.max_reconnect_attempts = Some(attempts);
+        self
+    }
+}
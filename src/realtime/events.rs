@@ -1,3 +1,5 @@
+use crate::realtime::sync::ConnectionState;
+
 /// Events that can be received via real-time sync
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SyncEvent {
@@ -45,10 +47,23 @@ pub enum SyncEvent {
 
     /// Heartbeat received (internal, usually not exposed to consumers)
     Heartbeat,
+
+    /// A text message this version of the crate does not recognise, such
+    /// as a `refresh-*` message for data added to AnyList since
+    Unknown(String),
+
+    /// A binary frame, passed on as is. The listener is not known to send
+    /// any.
+    Binary(Vec<u8>),
+
+    /// The realtime connection moved to a new state. Generated by
+    /// [`RealtimeSync`](crate::RealtimeSync), never sent by the server.
+    ConnectionStateChanged(ConnectionState),
 }
 
 impl SyncEvent {
-    /// Parse a WebSocket message into a SyncEvent
+    /// Parse a WebSocket message into a SyncEvent, or `None` if the message
+    /// is not one this crate knows
     pub fn from_message(msg: &str) -> Option<Self> {
         match msg {
             "--heartbeat--" => Some(SyncEvent::Heartbeat),
@@ -69,6 +84,12 @@ impl SyncEvent {
             _ => None,
         }
     }
+
+    /// Parse a WebSocket message into a SyncEvent, keeping an unrecognised
+    /// message as [`SyncEvent::Unknown`]
+    pub fn from_text(msg: &str) -> Self {
+        Self::from_message(msg).unwrap_or_else(|| SyncEvent::Unknown(msg.to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -104,6 +125,18 @@ mod tests {
         assert_eq!(SyncEvent::from_message("unknown-message"), None);
     }
 
+    #[test]
+    fn test_unknown_message_is_kept() {
+        assert_eq!(
+            SyncEvent::from_text("refresh-pantry-items"),
+            SyncEvent::Unknown("refresh-pantry-items".to_string())
+        );
+        assert_eq!(
+            SyncEvent::from_text("refresh-shopping-lists"),
+            SyncEvent::ShoppingListsChanged
+        );
+    }
+
     #[test]
     fn test_all_known_messages() {
         let messages = vec![
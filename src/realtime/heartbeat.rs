@@ -1,3 +1,4 @@
+use crate::realtime::RealtimeConfig;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
@@ -21,12 +22,18 @@ impl Default for HeartbeatManager {
 }
 
 impl HeartbeatManager {
-    /// Create a new heartbeat manager
+    /// Create a new heartbeat manager with the default interval (5s) and
+    /// limit (3 missed)
     pub fn new() -> Self {
+        Self::from_config(&RealtimeConfig::default())
+    }
+
+    /// Create a heartbeat manager with the interval and limit from `config`
+    pub fn from_config(config: &RealtimeConfig) -> Self {
         Self {
             missed_count: Arc::new(Mutex::new(0)),
-            max_missed: 3,
-            interval: Duration::from_secs(5),
+            max_missed: config.max_missed_heartbeats,
+            interval: config.heartbeat_interval,
         }
     }
 
@@ -73,4 +80,26 @@ mod tests {
         mgr.reset().await;
         assert!(!mgr.is_dead().await);
     }
+
+    #[tokio::test]
+    async fn test_heartbeat_manager_from_config() {
+        let config = RealtimeConfig::new()
+            .heartbeat_interval(Duration::from_secs(30))
+            .max_missed_heartbeats(1);
+        let mgr = HeartbeatManager::from_config(&config);
+
+        assert_eq!(mgr.interval(), Duration::from_secs(30));
+        mgr.increment_missed().await;
+        assert!(mgr.is_dead().await);
+    }
+
+    #[tokio::test]
+    async fn test_zero_heartbeat_interval_is_raised_to_minimum() {
+        let config = RealtimeConfig::new().heartbeat_interval(Duration::ZERO);
+        let mgr = HeartbeatManager::from_config(&config);
+
+        assert_eq!(mgr.interval(), Duration::from_millis(1));
+        // A zero period would make this panic
+        tokio::time::interval(mgr.interval());
+    }
 }
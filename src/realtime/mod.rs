@@ -11,8 +11,13 @@
 //! data changed.
 //!
 //! This library **does not** retain any state. Instead, it notifies your
-//! application via a callback, and you decide what action to take (re-fetch
-//! data, update a cache, notify the user, etc.).
+//! application via a callback or a stream of events, and you decide what
+//! action to take (re-fetch data, update a cache, notify the user, etc.).
+//!
+//! The one exception is the client's opt-in user data cache (see
+//! [`crate::cache`]): every event invalidates the cached section it names
+//! before your callback runs, so re-fetching from the callback sees the
+//! change.
 //!
 //! # Examples
 //!
@@ -54,7 +59,7 @@
 //!
 //! ```no_run
 //! use anylist_rs::{AnyListClient, SyncEvent};
-//! use anylist_rs::realtime::RealtimeSync;
+//! use anylist_rs::realtime::{RealtimeConfig, RealtimeSync};
 //! use std::sync::Arc;
 //!
 //! #[tokio::main]
@@ -64,7 +69,7 @@
 //!     );
 //!
 //!     // Create without connecting
-//!     let mut sync = RealtimeSync::new(client, |event| {
+//!     let mut sync = RealtimeSync::new(client, RealtimeConfig::default(), |event| {
 //!         println!("Event: {:?}", event);
 //!     });
 //!
@@ -77,30 +82,87 @@
 //! }
 //! ```
 //!
+//! ## Streams
+//!
+//! Any number of subscribers can receive the same events, either as a
+//! [`Stream`](futures_util::Stream) or as a `tokio::sync::broadcast`
+//! receiver, which combine with `tokio::select!`. Connection state changes
+//! arrive in the same stream as [`SyncEvent::ConnectionStateChanged`]:
+//!
+//! ```no_run
+//! use anylist_rs::{AnyListClient, ConnectionState, SyncEvent};
+//! use anylist_rs::realtime::{RealtimeConfig, RealtimeSync};
+//! use futures_util::StreamExt;
+//! use std::sync::Arc;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let client = Arc::new(
+//!         AnyListClient::login("user@example.com", "password").await?
+//!     );
+//!
+//!     let mut sync = RealtimeSync::without_callback(client, RealtimeConfig::default());
+//!     let mut events = Box::pin(sync.events());
+//!     let mut log = sync.subscribe();
+//!     sync.connect().await?;
+//!
+//!     loop {
+//!         tokio::select! {
+//!             Some(event) = events.next() => match event {
+//!                 SyncEvent::ShoppingListsChanged => println!("Lists changed"),
+//!                 SyncEvent::ConnectionStateChanged(ConnectionState::Reconnecting) => {
+//!                     println!("Connection lost, reconnecting");
+//!                 }
+//!                 _ => {}
+//!             },
+//!             Ok(event) = log.recv() => println!("Event: {:?}", event),
+//!         }
+//!     }
+//! }
+//! ```
+//!
 //! # Connection Management
 //!
 //! The connection is managed automatically:
 //! - **Heartbeats**: Sent every 5 seconds to keep the connection alive
 //! - **Auto-reconnect**: If the connection drops, it will automatically
-//!   reconnect with exponential backoff
+//!   reconnect with exponential backoff and jitter
 //! - **Token refresh**: If the access token expires (close code 4010), it will
 //!   refresh and reconnect
+//! - **Dead credentials**: If the account is deleted or the refresh token is
+//!   rejected, the sync closes for good (see [`CloseReason`]) and the
+//!   client's auth event callback is told
+//!
+//! The heartbeat interval, the number of missed heartbeats tolerated, the
+//! backoff and an optional limit on reconnection attempts are set with
+//! [`RealtimeConfig`].
+//!
+//! [`RealtimeSync::watch_state`] follows the connection state without
+//! polling, and [`RealtimeSync::health`] reports the last heartbeat, the
+//! number of reconnections, the last close code and the last error, for
+//! monitoring.
 //!
 //! # Event Types
 //!
 //! See [`SyncEvent`] for the full list of events you can receive.
+//! Messages this crate does not recognise yet arrive as
+//! [`SyncEvent::Unknown`] and binary frames as [`SyncEvent::Binary`], so new
+//! server messages can be logged or handled before the crate learns about
+//! them.
 //!
 //! # Thread Safety
 //!
 //! All types in this module are thread-safe and can be used across async tasks.
 
+mod config;
 pub mod events;
 pub mod heartbeat;
 mod reconnect;
 pub mod sync;
 
+pub use config::RealtimeConfig;
 pub use events::SyncEvent;
-pub use sync::{ConnectionState, RealtimeSync};
+pub use sync::{CloseReason, ConnectionState, RealtimeHealth, RealtimeSync};
 
 /// Callback type for sync events
 pub type SyncCallback = std::sync::Arc<dyn Fn(SyncEvent) + Send + Sync>;
@@ -1,8 +1,10 @@
+use crate::realtime::RealtimeConfig;
+use crate::retry::random_fraction;
 use std::time::Duration;
 
 /// Manages reconnection backoff strategy
 pub struct ReconnectionStrategy {
-    /// Current retry delay
+    /// Current retry delay, before jitter
     current_delay: Duration,
 
     /// Initial delay for first retry
@@ -10,20 +12,38 @@ pub struct ReconnectionStrategy {
 
     /// Maximum delay between retries
     max_delay: Duration,
+
+    /// Fraction by which each delay may be randomly shortened
+    jitter: f64,
+
+    /// Attempts since the last successful connection
+    attempts: u32,
+
+    /// Attempts allowed before giving up, if limited
+    max_attempts: Option<u32>,
 }
 
 impl ReconnectionStrategy {
-    /// Create a new reconnection strategy
-    pub fn new() -> Self {
+    /// Create a reconnection strategy with the backoff from `config`
+    pub fn new(config: &RealtimeConfig) -> Self {
         Self {
             current_delay: Duration::from_secs(0),
-            initial_delay: Duration::from_secs(2),
-            max_delay: Duration::from_secs(120), // 2 minutes
+            initial_delay: config.reconnect_initial_delay,
+            max_delay: config.reconnect_max_delay,
+            jitter: config.reconnect_jitter,
+            attempts: 0,
+            max_attempts: config.max_reconnect_attempts,
         }
     }
 
-    /// Get the next delay duration and update internal state
-    pub fn next_delay(&mut self) -> Duration {
+    /// Get the delay before the next attempt and update internal state, or
+    /// `None` once every allowed attempt has been made
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| self.attempts >= max) {
+            return None;
+        }
+        self.attempts += 1;
+
         if self.current_delay.is_zero() {
             // First retry
             self.current_delay = self.initial_delay;
@@ -37,12 +57,16 @@ impl ReconnectionStrategy {
             }
         }
 
-        self.current_delay
+        Some(
+            self.current_delay
+                .mul_f64(1.0 - self.jitter * random_fraction()),
+        )
     }
 
     /// Reset the backoff (call on successful connection)
     pub fn reset(&mut self) {
         self.current_delay = Duration::from_secs(0);
+        self.attempts = 0;
     }
 }
 
@@ -52,18 +76,36 @@ mod tests {
 
     #[test]
     fn test_exponential_backoff() {
-        let mut strategy = ReconnectionStrategy::new();
+        let mut strategy = ReconnectionStrategy::new(&RealtimeConfig::new().reconnect_jitter(0.0));
+
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(2)));
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(4)));
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(8)));
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(16)));
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(32)));
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(64)));
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(120))); // capped
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(120))); // stays capped
+
+        strategy.reset();
+        assert_eq!(strategy.next_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_jitter_and_attempt_limit() {
+        let config = RealtimeConfig::new()
+            .reconnect_initial_delay(Duration::from_secs(10))
+            .reconnect_jitter(0.5)
+            .max_reconnect_attempts(2);
+        let mut strategy = ReconnectionStrategy::new(&config);
 
-        assert_eq!(strategy.next_delay(), Duration::from_secs(2));
-        assert_eq!(strategy.next_delay(), Duration::from_secs(4));
-        assert_eq!(strategy.next_delay(), Duration::from_secs(8));
-        assert_eq!(strategy.next_delay(), Duration::from_secs(16));
-        assert_eq!(strategy.next_delay(), Duration::from_secs(32));
-        assert_eq!(strategy.next_delay(), Duration::from_secs(64));
-        assert_eq!(strategy.next_delay(), Duration::from_secs(120)); // capped
-        assert_eq!(strategy.next_delay(), Duration::from_secs(120)); // stays capped
+        let first = strategy.next_delay().unwrap();
+        assert!(first > Duration::from_secs(5) && first <= Duration::from_secs(10));
+        let second = strategy.next_delay().unwrap();
+        assert!(second > Duration::from_secs(10) && second <= Duration::from_secs(20));
+        assert_eq!(strategy.next_delay(), None);
 
         strategy.reset();
-        assert_eq!(strategy.next_delay(), Duration::from_secs(2));
+        assert!(strategy.next_delay().is_some());
     }
 }
@@ -1,15 +1,17 @@
-use crate::client::AnyListClient;
+use crate::client::{AnyListClient, AuthEvent};
 use crate::error::{AnyListError, Result};
 use crate::realtime::events::SyncEvent;
 use crate::realtime::heartbeat::HeartbeatManager;
 use crate::realtime::reconnect::ReconnectionStrategy;
-use crate::realtime::SyncCallback;
-use futures_util::{SinkExt, StreamExt};
+use crate::realtime::{RealtimeConfig, SyncCallback};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, Stream, StreamExt};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
-use tokio::task::JoinHandle;
-use tokio::time::interval;
+use tokio::sync::{broadcast, watch, Mutex};
+use tokio::task::{AbortHandle, JoinHandle};
+use tokio::time::{interval, Instant};
 use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
 /// Connection state for real-time sync
@@ -27,191 +29,464 @@ pub enum ConnectionState {
     Closed,
 }
 
+/// Why a [`RealtimeSync`] moved to [`ConnectionState::Closed`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloseReason {
+    /// [`RealtimeSync::disconnect`] was called
+    Disconnected,
+    /// The server reported that the account was deleted
+    AccountDeleted,
+    /// The refresh token was rejected, so reconnecting cannot succeed until
+    /// the user logs in again
+    AuthenticationFailed(String),
+    /// Every reconnection attempt allowed by
+    /// [`RealtimeConfig::max_reconnect_attempts`] failed
+    ReconnectAttemptsExhausted,
+}
+
+/// A snapshot of how the realtime connection is doing, from
+/// [`RealtimeSync::health`].
+///
+/// Times are [`tokio::time::Instant`]s, so they follow tokio's clock when
+/// it is paused in tests.
+///
+/// # Example
+///
+/// ```no_run
+/// # use anylist_rs::{ConnectionState, RealtimeSync};
+/// # use std::time::Duration;
+/// # fn example(sync: &RealtimeSync) {
+/// let health = sync.health();
+/// if health.state() == ConnectionState::Reconnecting
+///     && health.state_since().elapsed() > Duration::from_secs(600)
+/// {
+///     eprintln!(
+///         "Sync stuck reconnecting (last close code {:?}, last error {:?})",
+///         health.last_close_code(),
+///         health.last_error()
+///     );
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RealtimeHealth {
+    pub(crate) state: ConnectionState,
+    pub(crate) state_since: Instant,
+    pub(crate) last_heartbeat: Option<Instant>,
+    pub(crate) reconnect_count: u32,
+    pub(crate) last_close_code: Option<u16>,
+    pub(crate) last_error: Option<String>,
+    pub(crate) close_reason: Option<CloseReason>,
+}
+
+impl RealtimeHealth {
+    fn new() -> Self {
+        Self {
+            state: ConnectionState::Disconnected,
+            state_since: Instant::now(),
+            last_heartbeat: None,
+            reconnect_count: 0,
+            last_close_code: None,
+            last_error: None,
+            close_reason: None,
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// When the connection entered its current state
+    pub fn state_since(&self) -> Instant {
+        self.state_since
+    }
+
+    /// When the server last answered a heartbeat
+    pub fn last_heartbeat(&self) -> Option<Instant> {
+        self.last_heartbeat
+    }
+
+    /// Number of times the connection was re-established after dropping
+    pub fn reconnect_count(&self) -> u32 {
+        self.reconnect_count
+    }
+
+    /// Code of the last close frame received, such as 4010 when the access
+    /// token expired
+    pub fn last_close_code(&self) -> Option<u16> {
+        self.last_close_code
+    }
+
+    /// The last connection, reconnection or WebSocket error
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Why the sync closed, once it is [`ConnectionState::Closed`]
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+}
+
 type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+type WsSink = SplitSink<WsStream, Message>;
+
+/// How many events a subscriber can fall behind before it misses some
+const EVENT_CHANNEL_CAPACITY: usize = 64;
 
 /// Real-time sync manager
 pub struct RealtimeSync {
+    shared: Arc<Shared>,
+}
+
+/// State shared between a [`RealtimeSync`] and its background tasks
+struct Shared {
     /// Reference to the AnyList client
     client: Arc<AnyListClient>,
 
-    /// Current connection state
-    state: Arc<Mutex<ConnectionState>>,
+    /// Heartbeat and reconnection settings
+    config: RealtimeConfig,
+
+    /// Current connection state, for watchers
+    state: watch::Sender<ConnectionState>,
+
+    /// Connection statistics
+    health: std::sync::Mutex<RealtimeHealth>,
 
     /// Callback for sync events
     callback: SyncCallback,
 
-    /// WebSocket stream (if connected)
-    ws_stream: Arc<Mutex<Option<WsStream>>>,
+    /// Every event, for subscribers
+    events: broadcast::Sender<SyncEvent>,
+
+    /// Sending half of the WebSocket (if connected)
+    sink: Mutex<Option<WsSink>>,
+
+    /// Bumped on every new connection, so tasks left over from an earlier
+    /// one stop instead of talking to the new socket or reporting events and
+    /// state changes for a connection that has been replaced
+    connection_id: AtomicU64,
+
+    /// Message receiver of the current connection, aborted when the
+    /// connection is replaced so it stops reading the old socket
+    receiver: std::sync::Mutex<Option<AbortHandle>>,
+
+    /// Whether the reconnection monitor has been started
+    monitoring: AtomicBool,
+
+    /// Set when the listener rejects the access token (close code 4010), so
+    /// the next reconnection refreshes it first
+    token_rejected: AtomicBool,
 
     /// Background tasks handles
-    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
 
     /// Shutdown signal
-    shutdown_tx: tokio::sync::broadcast::Sender<()>,
+    shutdown_tx: broadcast::Sender<()>,
+}
+
+impl Shared {
+    /// Move to `new_state`, announcing the change to the callback and
+    /// subscribers. A closed sync stays closed.
+    async fn set_state(&self, new_state: ConnectionState) {
+        let changed = self.state.send_if_modified(|state| {
+            if *state == new_state || *state == ConnectionState::Closed {
+                return false;
+            }
+            *state = new_state;
+            true
+        });
+        if !changed {
+            return;
+        }
+
+        {
+            let mut health = self.health.lock().unwrap();
+            health.state = new_state;
+            health.state_since = Instant::now();
+        }
+        self.emit(SyncEvent::ConnectionStateChanged(new_state));
+    }
+
+    fn state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// Update the connection statistics
+    fn record(&self, f: impl FnOnce(&mut RealtimeHealth)) {
+        f(&mut self.health.lock().unwrap());
+    }
+
+    /// Close for good: stop the background tasks and close the socket. Only
+    /// the first reason is kept.
+    async fn close(&self, reason: CloseReason) {
+        self.record(|h| {
+            h.close_reason.get_or_insert(reason);
+        });
+        self.set_state(ConnectionState::Closed).await;
+
+        // Send shutdown signal to all tasks
+        let _ = self.shutdown_tx.send(());
+
+        // Close WebSocket with normal closure code
+        let mut sink_guard = self.sink.lock().await;
+        if let Some(sink) = sink_guard.as_mut() {
+            let _ = sink.close().await;
+        }
+        *sink_guard = None;
+    }
+
+    /// Hand an event to the callback and every subscriber
+    fn emit(&self, event: SyncEvent) {
+        (self.callback)(event.clone());
+        // No subscribers is not an error
+        let _ = self.events.send(event);
+    }
+
+    fn is_current(&self, connection_id: u64) -> bool {
+        self.connection_id.load(Ordering::SeqCst) == connection_id
+    }
 }
 
 impl RealtimeSync {
     /// Create a new real-time sync instance, without connecting
     ///
     /// Call `connect()` to establish the WebSocket connection.
-    pub fn new<F>(client: Arc<AnyListClient>, callback: F) -> Self
+    pub fn new<F>(client: Arc<AnyListClient>, config: RealtimeConfig, callback: F) -> Self
     where
         F: Fn(SyncEvent) + Send + Sync + 'static,
     {
-        let (shutdown_tx, _) = tokio::sync::broadcast::channel(1);
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
 
         Self {
-            client,
-            state: Arc::new(Mutex::new(ConnectionState::Disconnected)),
-            callback: Arc::new(callback),
-            ws_stream: Arc::new(Mutex::new(None)),
-            tasks: Arc::new(Mutex::new(Vec::new())),
-            shutdown_tx,
+            shared: Arc::new(Shared {
+                client,
+                config,
+                state: watch::channel(ConnectionState::Disconnected).0,
+                health: std::sync::Mutex::new(RealtimeHealth::new()),
+                callback: Arc::new(callback),
+                events,
+                sink: Mutex::new(None),
+                connection_id: AtomicU64::new(0),
+                receiver: std::sync::Mutex::new(None),
+                monitoring: AtomicBool::new(false),
+                token_rejected: AtomicBool::new(false),
+                tasks: Mutex::new(Vec::new()),
+                shutdown_tx,
+            }),
         }
     }
 
+    /// Create a real-time sync instance whose events are only delivered to
+    /// subscribers (see [`subscribe`](Self::subscribe) and
+    /// [`events`](Self::events))
+    pub fn without_callback(client: Arc<AnyListClient>, config: RealtimeConfig) -> Self {
+        Self::new(client, config, |_| {})
+    }
+
+    /// Receive every event from now on, connection state changes included.
+    ///
+    /// Each call creates an independent subscriber. A subscriber that falls
+    /// more than 64 events behind skips the oldest ones and gets
+    /// [`RecvError::Lagged`](broadcast::error::RecvError::Lagged).
+    pub fn subscribe(&self) -> broadcast::Receiver<SyncEvent> {
+        self.shared.events.subscribe()
+    }
+
+    /// Every event from now on as a [`Stream`], connection state changes
+    /// included.
+    ///
+    /// Built on [`subscribe`](Self::subscribe): events missed by a lagging
+    /// stream are skipped silently. The stream ends when the sync is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use anylist_rs::{AnyListClient, SyncEvent};
+    /// # use anylist_rs::realtime::{RealtimeConfig, RealtimeSync};
+    /// # use futures_util::StreamExt;
+    /// # use std::sync::Arc;
+    /// # async fn example(client: Arc<AnyListClient>) -> anylist_rs::Result<()> {
+    /// let mut sync = RealtimeSync::without_callback(client, RealtimeConfig::default());
+    /// let mut events = Box::pin(sync.events());
+    /// sync.connect().await?;
+    ///
+    /// while let Some(event) = events.next().await {
+    ///     match event {
+    ///         SyncEvent::ShoppingListsChanged => println!("Lists changed"),
+    ///         SyncEvent::ConnectionStateChanged(state) => println!("Now {:?}", state),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn events(&self) -> impl Stream<Item = SyncEvent> + Send + 'static {
+        futures_util::stream::unfold(self.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
     /// Connect to the WebSocket server.
     ///
     /// Idempotent (returns `Ok()`), unless previously closed via
     /// `disconnect()`.
     pub async fn connect(&mut self) -> Result<()> {
-        // Validate state transition
-        {
-            let mut state = self.state.lock().await;
-            match *state {
-                ConnectionState::Connected => {
-                    // Already connected, nothing to do
-                    return Ok(());
-                }
-                ConnectionState::Connecting => {
-                    // Already connecting, nothing to do
-                    return Ok(());
-                }
-                ConnectionState::Closed => {
-                    return Err(AnyListError::NetworkError(
-                        "Cannot connect after permanent closure".to_string(),
-                    ));
-                }
-                _ => {
-                    *state = ConnectionState::Connecting;
-                }
+        match self.shared.state() {
+            ConnectionState::Connected | ConnectionState::Connecting => return Ok(()),
+            ConnectionState::Closed => {
+                return Err(AnyListError::NetworkError(
+                    "Cannot connect after permanent closure".to_string(),
+                ));
             }
+            _ => {}
+        }
+        self.shared.set_state(ConnectionState::Connecting).await;
+
+        // Don't connect with a token the listener would reject in a moment.
+        // Only a rejected refresh token fails here; after any other refresh
+        // error the current token is tried
+        if let Err(e) = self.shared.client.refresh_if_expiring().await {
+            self.shared.record(|h| h.last_error = Some(e.to_string()));
+            self.shared.set_state(ConnectionState::Disconnected).await;
+            return Err(e);
         }
 
-        let url = self.build_websocket_url().await?;
+        let url = self.shared.client.websocket_url();
+        let ws_stream = match connect_async(&url).await {
+            Ok((ws_stream, _response)) => ws_stream,
+            Err(e) => {
+                let error = format!("WebSocket connection failed: {}", e);
+                self.shared.record(|h| h.last_error = Some(error.clone()));
+                // Reset state on connection failure
+                self.shared.set_state(ConnectionState::Disconnected).await;
+                return Err(AnyListError::NetworkError(error));
+            }
+        };
 
-        let (ws_stream, _response) = connect_async(&url).await.map_err(|e| {
-            // Reset state on connection failure
-            let state = self.state.clone();
-            tokio::spawn(async move {
-                let mut s = state.lock().await;
-                *s = ConnectionState::Disconnected;
-            });
-            AnyListError::NetworkError(format!("WebSocket connection failed: {}", e))
-        })?;
+        Self::start_connection(&self.shared, ws_stream).await;
+        self.shared.set_state(ConnectionState::Connected).await;
 
-        // Store stream
-        {
-            let mut stream_guard = self.ws_stream.lock().await;
-            *stream_guard = Some(ws_stream);
+        if !self.shared.monitoring.swap(true, Ordering::SeqCst) {
+            let reconnect_task = Self::spawn_reconnection_monitor(Arc::clone(&self.shared));
+            self.shared.tasks.lock().await.push(reconnect_task);
         }
 
-        // Update state
-        {
-            let mut state = self.state.lock().await;
-            *state = ConnectionState::Connected;
-        }
-
-        self.start_background_tasks().await;
-
         Ok(())
     }
 
-    /// Build the WebSocket URL with auth parameters
-    async fn build_websocket_url(&self) -> Result<String> {
-        let tokens = self.client.export_tokens()?;
-
-        let url = format!(
-            "wss://www.anylist.com/data/add-user-listener?client_id={}&access_token={}",
-            urlencoding::encode(self.client.client_identifier()),
-            urlencoding::encode(&tokens.access_token)
-        );
-
-        Ok(url)
-    }
-
     pub async fn state(&self) -> ConnectionState {
-        *self.state.lock().await
+        self.shared.state()
     }
 
     pub async fn is_connected(&self) -> bool {
-        *self.state.lock().await == ConnectionState::Connected
+        self.shared.state() == ConnectionState::Connected
     }
 
-    /// Start background tasks (message receiver, heartbeat sender, reconnection monitor)
-    async fn start_background_tasks(&mut self) {
-        let mut tasks = self.tasks.lock().await;
-
-        let heartbeat_mgr = Arc::new(HeartbeatManager::new());
-
-        let receiver_task = self.spawn_message_receiver(Arc::clone(&heartbeat_mgr));
-        tasks.push(receiver_task);
+    /// Watch the connection state: the receiver always holds the current
+    /// state, and [`changed`](watch::Receiver::changed) wakes on every
+    /// change
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.shared.state.subscribe()
+    }
 
-        let sender_task = self.spawn_heartbeat_sender(Arc::clone(&heartbeat_mgr));
-        tasks.push(sender_task);
+    /// Connection statistics as of now
+    pub fn health(&self) -> RealtimeHealth {
+        self.shared.health.lock().unwrap().clone()
+    }
 
-        let reconnect_task = self.spawn_reconnection_monitor();
-        tasks.push(reconnect_task);
+    /// Why the sync closed, once it is [`ConnectionState::Closed`]
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.shared.health.lock().unwrap().close_reason.clone()
     }
 
-    /// Receive next message from WebSocket stream
-    async fn recv_message(
-        ws_stream: &Arc<Mutex<Option<WsStream>>>,
-    ) -> Option<std::result::Result<Message, tokio_tungstenite::tungstenite::Error>> {
-        let mut stream_guard = ws_stream.lock().await;
-        if let Some(stream) = stream_guard.as_mut() {
-            stream.next().await
-        } else {
-            None
+    /// Take over a freshly opened socket: keep its sending half and start
+    /// the message receiver and heartbeat sender for it
+    async fn start_connection(shared: &Arc<Shared>, ws_stream: WsStream) {
+        let connection_id = shared.connection_id.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(old_receiver) = shared.receiver.lock().unwrap().take() {
+            old_receiver.abort();
         }
+        let (sink, stream) = ws_stream.split();
+        *shared.sink.lock().await = Some(sink);
+
+        let heartbeat_mgr = Arc::new(HeartbeatManager::from_config(&shared.config));
+        let receiver_task = Self::spawn_message_receiver(
+            Arc::clone(shared),
+            stream,
+            Arc::clone(&heartbeat_mgr),
+            connection_id,
+        );
+        *shared.receiver.lock().unwrap() = Some(receiver_task.abort_handle());
+        let sender_task =
+            Self::spawn_heartbeat_sender(Arc::clone(shared), heartbeat_mgr, connection_id);
+
+        let mut tasks = shared.tasks.lock().await;
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(receiver_task);
+        tasks.push(sender_task);
     }
 
     /// Handle text message by parsing and dispatching event
+    /// Returns true if we should continue, false if the sync closed
     async fn handle_text_message(
         text: &str,
-        heartbeat_mgr: &Arc<HeartbeatManager>,
-        callback: &SyncCallback,
-    ) {
-        if let Some(event) = SyncEvent::from_message(text) {
-            if event == SyncEvent::Heartbeat {
+        shared: &Shared,
+        heartbeat_mgr: &HeartbeatManager,
+    ) -> bool {
+        match SyncEvent::from_text(text) {
+            SyncEvent::Heartbeat => {
                 // Reset heartbeat counter
                 heartbeat_mgr.reset().await;
-            } else {
-                // Dispatch non-heartbeat events to callback
-                callback(event);
+                shared.record(|h| h.last_heartbeat = Some(Instant::now()));
+            }
+            SyncEvent::AccountDeleted => {
+                shared.client.apply_sync_event(&SyncEvent::AccountDeleted);
+                shared.emit(SyncEvent::AccountDeleted);
+                // The credentials are dead: don't reconnect with them
+                shared.client.notify_auth_event(AuthEvent::AccountDeleted);
+                shared.close(CloseReason::AccountDeleted).await;
+                return false;
+            }
+            event => {
+                // Invalidate cached user data before the callback re-fetches it
+                shared.client.apply_sync_event(&event);
+                // Dispatch non-heartbeat events
+                shared.emit(event);
             }
         }
+        true
     }
 
     /// Handle WebSocket close event and update connection state
     async fn handle_close(
         frame: Option<tokio_tungstenite::tungstenite::protocol::CloseFrame<'_>>,
-        state: &Arc<Mutex<ConnectionState>>,
+        shared: &Shared,
     ) {
-        let code = frame.as_ref().map(|f| f.code.into());
-        let mut state_guard = state.lock().await;
+        let code: Option<u16> = frame.as_ref().map(|f| f.code.into());
+        if code.is_some() {
+            shared.record(|h| h.last_close_code = code);
+        }
         match code {
             Some(1000) => {
                 // Normal closure
-                *state_guard = ConnectionState::Disconnected;
+                shared.set_state(ConnectionState::Disconnected).await;
             }
             Some(4010) => {
                 // Token expired - need to refresh
-                *state_guard = ConnectionState::Reconnecting;
+                shared.token_rejected.store(true, Ordering::SeqCst);
+                shared.set_state(ConnectionState::Reconnecting).await;
             }
             _ => {
                 // Abnormal closure - will reconnect
-                *state_guard = ConnectionState::Reconnecting;
+                shared.set_state(ConnectionState::Reconnecting).await;
             }
         }
     }
@@ -219,37 +494,41 @@ impl RealtimeSync {
     /// Handle incoming WebSocket message
     async fn handle_message(
         msg_result: std::result::Result<Message, tokio_tungstenite::tungstenite::Error>,
-        heartbeat_mgr: &Arc<HeartbeatManager>,
-        callback: &SyncCallback,
-        state: &Arc<Mutex<ConnectionState>>,
+        shared: &Shared,
+        heartbeat_mgr: &HeartbeatManager,
     ) -> bool {
         match msg_result {
             Ok(Message::Text(text)) => {
-                Self::handle_text_message(&text, heartbeat_mgr, callback).await;
+                Self::handle_text_message(&text, shared, heartbeat_mgr).await
+            }
+            Ok(Message::Binary(data)) => {
+                shared.emit(SyncEvent::Binary(data));
                 true // Continue loop
             }
             Ok(Message::Close(frame)) => {
-                Self::handle_close(frame, state).await;
+                Self::handle_close(frame, shared).await;
                 false // Exit loop
             }
-            Err(_e) => {
+            Err(e) => {
                 // WebSocket error - trigger reconnection
-                let mut state_guard = state.lock().await;
-                *state_guard = ConnectionState::Reconnecting;
+                shared.record(|h| h.last_error = Some(format!("WebSocket error: {}", e)));
+                shared.set_state(ConnectionState::Reconnecting).await;
                 false // Exit loop
             }
             _ => {
-                // Ignore binary, ping, pong messages
+                // Ignore ping and pong messages
                 true // Continue loop
             }
         }
     }
 
-    fn spawn_message_receiver(&self, heartbeat_mgr: Arc<HeartbeatManager>) -> JoinHandle<()> {
-        let ws_stream = Arc::clone(&self.ws_stream);
-        let callback = Arc::clone(&self.callback);
-        let state = Arc::clone(&self.state);
-        let mut shutdown_rx = self.shutdown_tx.subscribe();
+    fn spawn_message_receiver(
+        shared: Arc<Shared>,
+        mut stream: SplitStream<WsStream>,
+        heartbeat_mgr: Arc<HeartbeatManager>,
+        connection_id: u64,
+    ) -> JoinHandle<()> {
+        let mut shutdown_rx = shared.shutdown_tx.subscribe();
 
         tokio::spawn(async move {
             loop {
@@ -257,20 +536,18 @@ impl RealtimeSync {
                     _ = shutdown_rx.recv() => {
                         break;
                     }
-                    msg_result = Self::recv_message(&ws_stream) => {
-                        if let Some(msg_result) = msg_result {
-                            let should_continue = Self::handle_message(
-                                msg_result,
-                                &heartbeat_mgr,
-                                &callback,
-                                &state,
-                            ).await;
-
-                            if !should_continue {
-                                break;
-                            }
-                        } else {
-                            // Stream is None, exit
+                    msg_result = stream.next() => {
+                        // A replaced connection must not emit events or
+                        // change the state of the one that replaced it
+                        if !shared.is_current(connection_id) {
+                            break;
+                        }
+                        let Some(msg_result) = msg_result else {
+                            // Stream ended without a close frame
+                            shared.set_state(ConnectionState::Reconnecting).await;
+                            break;
+                        };
+                        if !Self::handle_message(msg_result, &shared, &heartbeat_mgr).await {
                             break;
                         }
                     }
@@ -281,16 +558,12 @@ impl RealtimeSync {
 
     /// Check if too many heartbeats have been missed
     /// Returns true if we should continue, false if we should reconnect
-    async fn check_heartbeat_timeout(
-        heartbeat_mgr: &Arc<HeartbeatManager>,
-        state: &Arc<Mutex<ConnectionState>>,
-    ) -> bool {
-        let missed = heartbeat_mgr.increment_missed().await;
+    async fn check_heartbeat_timeout(heartbeat_mgr: &HeartbeatManager, shared: &Shared) -> bool {
+        heartbeat_mgr.increment_missed().await;
 
-        if missed >= 3 {
+        if heartbeat_mgr.is_dead().await {
             // Too many missed heartbeats, trigger reconnection
-            let mut state_guard = state.lock().await;
-            *state_guard = ConnectionState::Reconnecting;
+            shared.set_state(ConnectionState::Reconnecting).await;
             false
         } else {
             true
@@ -299,20 +572,14 @@ impl RealtimeSync {
 
     /// Send heartbeat message over WebSocket
     /// Returns true if we should continue, false if we should stop
-    async fn send_heartbeat(
-        ws_stream: &Arc<Mutex<Option<WsStream>>>,
-        state: &Arc<Mutex<ConnectionState>>,
-    ) -> bool {
-        let mut stream_guard = ws_stream.lock().await;
-
-        if let Some(stream) = stream_guard.as_mut() {
-            if let Err(_e) = stream
-                .send(Message::Text("--heartbeat--".to_string()))
-                .await
-            {
-                drop(stream_guard);
-                let mut state_guard = state.lock().await;
-                *state_guard = ConnectionState::Reconnecting;
+    async fn send_heartbeat(shared: &Shared) -> bool {
+        let mut sink_guard = shared.sink.lock().await;
+
+        if let Some(sink) = sink_guard.as_mut() {
+            if let Err(e) = sink.send(Message::Text("--heartbeat--".to_string())).await {
+                drop(sink_guard);
+                shared.record(|h| h.last_error = Some(format!("Failed to send heartbeat: {}", e)));
+                shared.set_state(ConnectionState::Reconnecting).await;
                 false
             } else {
                 true
@@ -323,10 +590,12 @@ impl RealtimeSync {
         }
     }
 
-    fn spawn_heartbeat_sender(&self, heartbeat_mgr: Arc<HeartbeatManager>) -> JoinHandle<()> {
-        let ws_stream = Arc::clone(&self.ws_stream);
-        let state = Arc::clone(&self.state);
-        let mut shutdown_rx = self.shutdown_tx.subscribe();
+    fn spawn_heartbeat_sender(
+        shared: Arc<Shared>,
+        heartbeat_mgr: Arc<HeartbeatManager>,
+        connection_id: u64,
+    ) -> JoinHandle<()> {
+        let mut shutdown_rx = shared.shutdown_tx.subscribe();
 
         tokio::spawn(async move {
             let mut ticker = interval(heartbeat_mgr.interval());
@@ -338,19 +607,20 @@ impl RealtimeSync {
                         break;
                     }
                     _ = ticker.tick() => {
-                        // Check if connection is still alive
-                        let current_state = *state.lock().await;
-                        if current_state != ConnectionState::Connected {
+                        // Check if this connection is still the live one
+                        if !shared.is_current(connection_id)
+                            || shared.state() != ConnectionState::Connected
+                        {
                             break;
                         }
 
                         // Check for heartbeat timeout
-                        if !Self::check_heartbeat_timeout(&heartbeat_mgr, &state).await {
+                        if !Self::check_heartbeat_timeout(&heartbeat_mgr, &shared).await {
                             break;
                         }
 
                         // Send heartbeat message
-                        if !Self::send_heartbeat(&ws_stream, &state).await {
+                        if !Self::send_heartbeat(&shared).await {
                             break;
                         }
                     }
@@ -359,17 +629,11 @@ impl RealtimeSync {
         })
     }
 
-    fn spawn_reconnection_monitor(&self) -> JoinHandle<()> {
-        let state = Arc::clone(&self.state);
-        let ws_stream = Arc::clone(&self.ws_stream);
-        let client = Arc::clone(&self.client);
-        let callback = Arc::clone(&self.callback);
-        let shutdown_tx = self.shutdown_tx.clone();
-        let tasks = Arc::clone(&self.tasks);
-        let mut shutdown_rx = self.shutdown_tx.subscribe();
+    fn spawn_reconnection_monitor(shared: Arc<Shared>) -> JoinHandle<()> {
+        let mut shutdown_rx = shared.shutdown_tx.subscribe();
 
         tokio::spawn(async move {
-            let mut strategy = ReconnectionStrategy::new();
+            let mut strategy = ReconnectionStrategy::new(&shared.config);
 
             loop {
                 tokio::select! {
@@ -377,36 +641,30 @@ impl RealtimeSync {
                         break;
                     }
                     _ = tokio::time::sleep(Duration::from_secs(1)) => {
-                        let current_state = *state.lock().await;
-
-                        if current_state == ConnectionState::Reconnecting {
-                            let delay = strategy.next_delay();
-
-                            tokio::time::sleep(delay).await;
-
-                            match Self::attempt_reconnect(
-                                Arc::clone(&client),
-                                Arc::clone(&state),
-                                Arc::clone(&ws_stream),
-                                Arc::clone(&callback),
-                                shutdown_tx.clone(),
-                                Arc::clone(&tasks),
-                            ).await {
-                                Ok(_) => {
+                        match shared.state() {
+                            ConnectionState::Reconnecting => {
+                                let Some(delay) = strategy.next_delay() else {
+                                    // Out of attempts: give up for good
+                                    shared.close(CloseReason::ReconnectAttemptsExhausted).await;
+                                    break;
+                                };
+
+                                tokio::time::sleep(delay).await;
+
+                                if Self::attempt_reconnect(&shared).await.is_ok() {
                                     strategy.reset();
-                                    let mut state_guard = state.lock().await;
-                                    *state_guard = ConnectionState::Connected;
-                                }
-                                Err(_e) => {
-                                    // Will retry on next loop iteration
                                 }
+                                // On failure, retry on the next loop iteration
                             }
-                        } else if current_state == ConnectionState::Connected {
-                            // Reset backoff on successful connection
-                            strategy.reset();
-                        } else if current_state == ConnectionState::Closed {
-                            // Permanently closed, exit
-                            break;
+                            ConnectionState::Connected => {
+                                // Reset backoff on successful connection
+                                strategy.reset();
+                            }
+                            ConnectionState::Closed => {
+                                // Permanently closed, exit
+                                break;
+                            }
+                            _ => {}
                         }
                     }
                 }
@@ -415,181 +673,46 @@ impl RealtimeSync {
     }
 
     /// Attempt to reconnect (used by reconnection monitor)
-    async fn attempt_reconnect(
-        client: Arc<AnyListClient>,
-        state: Arc<Mutex<ConnectionState>>,
-        ws_stream: Arc<Mutex<Option<WsStream>>>,
-        callback: SyncCallback,
-        shutdown_tx: tokio::sync::broadcast::Sender<()>,
-        tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
-    ) -> Result<()> {
-        // Try to refresh tokens first (in case token expired)
-        match client.refresh_tokens().await {
-            Ok(_) => {}
-            Err(_) => {
-                // Continue anyway - maybe the token is still valid
-            }
-        }
-
-        // Build URL
-        let tokens = client.export_tokens()?;
-        let url = format!(
-            "wss://www.anylist.com/data/add-user-listener?client_id={}&access_token={}",
-            urlencoding::encode(client.client_identifier()),
-            urlencoding::encode(&tokens.access_token)
-        );
-
-        // Connect
-        let (new_stream, _) = connect_async(&url)
-            .await
-            .map_err(|e| AnyListError::NetworkError(format!("Reconnection failed: {}", e)))?;
-
-        // Replace stream
-        {
-            let mut stream_guard = ws_stream.lock().await;
-            *stream_guard = Some(new_stream);
-        }
-
-        // Update state
-        {
-            let mut state_guard = state.lock().await;
-            *state_guard = ConnectionState::Connected;
-        }
-
-        // Restart background tasks (heartbeat sender and message receiver)
-        let heartbeat_mgr = Arc::new(HeartbeatManager::new());
-
-        // Spawn new message receiver
-        let receiver_task = {
-            let ws_stream = Arc::clone(&ws_stream);
-            let callback = Arc::clone(&callback);
-            let state = Arc::clone(&state);
-            let mut shutdown_rx = shutdown_tx.subscribe();
-            let heartbeat_mgr = Arc::clone(&heartbeat_mgr);
-
-            tokio::spawn(async move {
-                loop {
-                    tokio::select! {
-                        _ = shutdown_rx.recv() => {
-                            break;
-                        }
-                        msg_result = async {
-                            let mut stream_guard = ws_stream.lock().await;
-                            if let Some(stream) = stream_guard.as_mut() {
-                                stream.next().await
-                            } else {
-                                None
-                            }
-                        } => {
-                            if let Some(msg_result) = msg_result {
-                                match msg_result {
-                                    Ok(Message::Text(text)) => {
-                                        if let Some(event) = SyncEvent::from_message(&text) {
-                                            if event == SyncEvent::Heartbeat {
-                                                heartbeat_mgr.reset().await;
-                                            } else {
-                                                callback(event);
-                                            }
-                                        }
-                                    }
-                                    Ok(Message::Close(_)) => {
-                                        let mut state_guard = state.lock().await;
-                                        *state_guard = ConnectionState::Reconnecting;
-                                        break;
-                                    }
-                                    Err(_) => {
-                                        let mut state_guard = state.lock().await;
-                                        *state_guard = ConnectionState::Reconnecting;
-                                        break;
-                                    }
-                                    _ => {}
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-                    }
-                }
-            })
+    async fn attempt_reconnect(shared: &Arc<Shared>) -> Result<()> {
+        // Refresh tokens first if the listener rejected the access token or
+        // it is about to expire. A transient failure is ignored - maybe the
+        // token is still valid - but a rejected refresh token will never
+        // work again
+        let refreshed = if shared.token_rejected.swap(false, Ordering::SeqCst) {
+            shared.client.refresh_tokens().await
+        } else {
+            shared.client.refresh_if_expiring().await
         };
+        if let Err(AnyListError::AuthenticationFailed(message)) = refreshed {
+            shared
+                .close(CloseReason::AuthenticationFailed(message.clone()))
+                .await;
+            return Err(AnyListError::AuthenticationFailed(message));
+        }
 
-        // Spawn new heartbeat sender
-        let sender_task = {
-            let ws_stream = Arc::clone(&ws_stream);
-            let state = Arc::clone(&state);
-            let mut shutdown_rx = shutdown_tx.subscribe();
-
-            tokio::spawn(async move {
-                let mut ticker = interval(heartbeat_mgr.interval());
-                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-
-                loop {
-                    tokio::select! {
-                        _ = shutdown_rx.recv() => {
-                            break;
-                        }
-                        _ = ticker.tick() => {
-                            let current_state = *state.lock().await;
-                            if current_state != ConnectionState::Connected {
-                                break;
-                            }
-
-                            let missed = heartbeat_mgr.increment_missed().await;
-                            if missed >= 3 {
-                                let mut state_guard = state.lock().await;
-                                *state_guard = ConnectionState::Reconnecting;
-                                break;
-                            }
-
-                            let mut stream_guard = ws_stream.lock().await;
-                            if let Some(stream) = stream_guard.as_mut() {
-                                if (stream.send(Message::Text("--heartbeat--".to_string())).await).is_err() {
-                                    drop(stream_guard);
-                                    let mut state_guard = state.lock().await;
-                                    *state_guard = ConnectionState::Reconnecting;
-                                    break;
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-                    }
-                }
-            })
+        let url = shared.client.websocket_url();
+        let new_stream = match connect_async(&url).await {
+            Ok((new_stream, _)) => new_stream,
+            Err(e) => {
+                let error = format!("Reconnection failed: {}", e);
+                shared.record(|h| h.last_error = Some(error.clone()));
+                return Err(AnyListError::NetworkError(error));
+            }
         };
 
-        // Add tasks to the list
-        {
-            let mut tasks_guard = tasks.lock().await;
-            tasks_guard.push(receiver_task);
-            tasks_guard.push(sender_task);
-        }
+        Self::start_connection(shared, new_stream).await;
+        shared.record(|h| h.reconnect_count += 1);
+        shared.set_state(ConnectionState::Connected).await;
 
         Ok(())
     }
 
     /// Disconnect and stop all background tasks
     pub async fn disconnect(&mut self) -> Result<()> {
-        // Update state
-        {
-            let mut state = self.state.lock().await;
-            *state = ConnectionState::Closed;
-        }
-
-        // Send shutdown signal to all tasks
-        let _ = self.shutdown_tx.send(());
-
-        // Close WebSocket with normal closure code
-        {
-            let mut stream_guard = self.ws_stream.lock().await;
-            if let Some(stream) = stream_guard.as_mut() {
-                let _ = stream.close(None).await;
-            }
-            *stream_guard = None;
-        }
+        self.shared.close(CloseReason::Disconnected).await;
 
         // Wait for all tasks to complete
-        let mut tasks = self.tasks.lock().await;
+        let mut tasks = self.shared.tasks.lock().await;
         for task in tasks.drain(..) {
             let _ = task.await;
         }
@@ -599,25 +722,19 @@ impl RealtimeSync {
 
     /// Manually trigger reconnection
     pub async fn reconnect(&mut self) -> Result<()> {
-        // Check if already connected
-        {
-            let state = self.state.lock().await;
-            if *state == ConnectionState::Connected {
-                return Ok(());
-            }
-            if *state == ConnectionState::Closed {
+        match self.shared.state() {
+            ConnectionState::Connected => return Ok(()),
+            ConnectionState::Closed => {
                 return Err(AnyListError::NetworkError(
                     "Cannot reconnect after permanent closure".to_string(),
                 ));
             }
+            _ => {}
         }
 
         // Trigger reconnection by setting state
         // The reconnection monitor will pick this up
-        {
-            let mut state = self.state.lock().await;
-            *state = ConnectionState::Reconnecting;
-        }
+        self.shared.set_state(ConnectionState::Reconnecting).await;
 
         Ok(())
     }
@@ -627,6 +744,97 @@ impl Drop for RealtimeSync {
     fn drop(&mut self) {
         // Best effort cleanup - can't use async in Drop
         // Send shutdown signal
-        let _ = self.shutdown_tx.send(());
+        let _ = self.shared.shutdown_tx.send(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SavedTokens;
+
+    fn sync() -> RealtimeSync {
+        let client =
+            AnyListClient::from_tokens(SavedTokens::new("access", "refresh", "user-1", false))
+                .unwrap();
+        RealtimeSync::without_callback(Arc::new(client), RealtimeConfig::default())
+    }
+
+    #[tokio::test]
+    async fn test_state_changes_reach_every_subscriber() {
+        let sync = sync();
+        let mut first = sync.subscribe();
+        let mut second = Box::pin(sync.events());
+
+        sync.shared.set_state(ConnectionState::Reconnecting).await;
+        // Repeating the current state is not a change
+        sync.shared.set_state(ConnectionState::Reconnecting).await;
+        sync.shared.emit(SyncEvent::ShoppingListsChanged);
+
+        let expected = [
+            SyncEvent::ConnectionStateChanged(ConnectionState::Reconnecting),
+            SyncEvent::ShoppingListsChanged,
+        ];
+        for event in &expected {
+            assert_eq!(first.recv().await.unwrap(), *event);
+            assert_eq!(second.next().await.unwrap(), *event);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_state_watch_and_health() {
+        let sync = sync();
+        let mut state = sync.watch_state();
+        assert_eq!(*state.borrow(), ConnectionState::Disconnected);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        sync.shared.set_state(ConnectionState::Reconnecting).await;
+        state.changed().await.unwrap();
+        assert_eq!(*state.borrow_and_update(), ConnectionState::Reconnecting);
+
+        sync.shared.record(|h| h.last_close_code = Some(4010));
+        let health = sync.health();
+        assert_eq!(health.state(), ConnectionState::Reconnecting);
+        assert_eq!(health.state_since(), Instant::now());
+        assert_eq!(health.last_close_code(), Some(4010));
+        assert_eq!(health.reconnect_count(), 0);
+        assert!(health.last_heartbeat().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_account_deletion_closes_the_sync() {
+        let sync = sync();
+        let mut events = sync.subscribe();
+        let heartbeat_mgr = HeartbeatManager::new();
+
+        assert!(
+            !RealtimeSync::handle_text_message("did-delete-account", &sync.shared, &heartbeat_mgr)
+                .await
+        );
+
+        assert_eq!(events.recv().await.unwrap(), SyncEvent::AccountDeleted);
+        assert_eq!(
+            events.recv().await.unwrap(),
+            SyncEvent::ConnectionStateChanged(ConnectionState::Closed)
+        );
+        assert_eq!(sync.close_reason(), Some(CloseReason::AccountDeleted));
+    }
+
+    #[tokio::test]
+    async fn test_closed_sync_stays_closed() {
+        let mut sync = sync();
+        let mut events = sync.subscribe();
+
+        sync.disconnect().await.unwrap();
+        sync.shared.set_state(ConnectionState::Reconnecting).await;
+
+        assert_eq!(sync.state().await, ConnectionState::Closed);
+        assert_eq!(sync.close_reason(), Some(CloseReason::Disconnected));
+        assert_eq!(
+            events.recv().await.unwrap(),
+            SyncEvent::ConnectionStateChanged(ConnectionState::Closed)
+        );
+        assert!(events.try_recv().is_err());
+        assert!(sync.connect().await.is_err());
     }
 }
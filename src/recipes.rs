@@ -1,10 +1,13 @@
+use crate::cache::UserDataSection;
 use crate::client::AnyListClient;
 use crate::error::{AnyListError, Result};
+use crate::outcome::OperationOutcome;
 use crate::protobuf::anylist::{
     pb_operation_metadata::OperationClass, PbIngredient, PbOperationMetadata, PbRecipe,
     PbRecipeDataResponse, PbRecipeOperation, PbRecipeOperationList,
 };
-use crate::utils::{current_timestamp, encode_operation_list, generate_id};
+use crate::transport::MultipartForm;
+use crate::utils::{current_timestamp, generate_id};
 use serde_derive::{Deserialize, Serialize};
 
 fn build_delete_recipe_operation(
@@ -178,7 +181,7 @@ impl RecipeIngredientEntry {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Recipe {
     id: String,
-    name: String,
+    pub(crate) name: String,
     ingredients: Vec<Ingredient>,
     #[serde(default)]
     ingredient_entries: Vec<RecipeIngredientEntry>,
@@ -592,9 +595,9 @@ impl RecipeBuilder {
             operations: vec![operation],
         };
 
-        let buf = encode_operation_list(&operation_list)?;
-
-        client.post("data/user-recipe-data/update", buf).await?;
+        client
+            .post_edit("data/user-recipe-data/update", &operation_list)
+            .await?;
 
         Ok(Recipe {
             id: recipe_id,
@@ -644,9 +647,9 @@ impl RecipeBuilder {
             operations: vec![operation],
         };
 
-        let buf = encode_operation_list(&operation_list)?;
-
-        client.post("data/user-recipe-data/update", buf).await?;
+        client
+            .post_edit("data/user-recipe-data/update", &operation_list)
+            .await?;
 
         Ok(Recipe {
             id: recipe_id,
@@ -688,7 +691,9 @@ impl AnyListClient {
     /// # }
     /// ```
     pub async fn get_recipes(&self) -> Result<Vec<Recipe>> {
-        let data = self.get_user_data().await?;
+        let data = self
+            .cached_user_data(&[UserDataSection::RecipeData])
+            .await?;
         let recipes = match data.recipe_data_response {
             Some(ref res) => recipes_from_response(res.clone()),
             None => Vec::new(),
@@ -817,10 +822,10 @@ impl AnyListClient {
     /// # Arguments
     ///
     /// * `recipe_id` - The ID of the recipe to delete
-    pub async fn delete_recipe(&self, recipe_id: &str) -> Result<()> {
+    pub async fn delete_recipe(&self, recipe_id: &str) -> Result<OperationOutcome> {
         let operation_id = generate_id();
         let recipe = self
-            .get_user_data()
+            .cached_user_data(&[UserDataSection::RecipeData])
             .await?
             .recipe_data_response
             .and_then(|response| {
@@ -832,10 +837,8 @@ impl AnyListClient {
 
         let operation_list =
             build_delete_recipe_operation(&operation_id, &self.user_id(), recipe_id, recipe);
-        let buf = encode_operation_list(&operation_list)?;
-
-        self.post("data/user-recipe-data/update", buf).await?;
-        Ok(())
+        self.post_edit("data/user-recipe-data/update", &operation_list)
+            .await
     }
 
     /// Add recipe ingredients to a shopping list
@@ -943,13 +946,9 @@ impl AnyListClient {
         let photo_id = generate_id();
         let server_filename = format!("{}.jpg", photo_id);
 
-        let photo_part = reqwest::multipart::Part::bytes(data)
-            .file_name(filename.to_string())
-            .mime_str(mime)?;
-
-        let form = reqwest::multipart::Form::new()
+        let form = MultipartForm::new()
             .text("filename", server_filename)
-            .part("photo", photo_part);
+            .file("photo", data, filename, mime);
 
         self.post_multipart_form("/data/photos/upload", form)
             .await?;
@@ -983,7 +982,7 @@ impl AnyListClient {
     /// # }
     /// ```
     pub async fn download_photo(&self, photo_id: &str) -> Result<Vec<u8>> {
-        let url = format!("https://photos.anylist.com/{photo_id}.jpg");
+        let url = self.photo_url(photo_id);
         self.get_bytes(&url).await
     }
 }
@@ -1006,7 +1005,7 @@ fn entries_for_ingredient_update(
     }
 }
 
-fn recipes_from_response(response: PbRecipeDataResponse) -> Vec<Recipe> {
+pub(crate) fn recipes_from_response(response: PbRecipeDataResponse) -> Vec<Recipe> {
     let mut recipes: Vec<Recipe> = Vec::new();
     for recipe in response.recipes {
         if let Some(name) = recipe.name {
@@ -1119,6 +1118,7 @@ fn sniff_image_mime(data: &[u8]) -> Option<&'static str> {
 mod tests {
     use super::*;
     use crate::client::SavedTokens;
+    use crate::utils::encode_operation_list;
     use prost::Message;
 
     #[test]
@@ -0,0 +1,250 @@
+//! Retrying REST calls that failed for transient reasons.
+//!
+//! Every API request the client sends is safe to repeat: reads have no side
+//! effects, and each mutation carries a client-generated operation ID that the
+//! server deduplicates, so a mutation whose response was lost is not applied
+//! twice. [`RetryPolicy`] decides how often and how long to wait.
+//!
+//! Only errors for which [`AnyListError::is_retryable`] is true are retried.
+//! A `Retry-After` sent with a 429 response takes precedence over the
+//! computed backoff; if it asks for longer than `max_delay`, the
+//! [`AnyListError::RateLimited`] is returned instead of waiting.
+
+use crate::error::{AnyListError, Result};
+use std::future::Future;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How REST calls are retried after a transient failure.
+///
+/// Delays grow exponentially from `initial_delay`, doubling on each retry up
+/// to `max_delay`. With `jitter` set, each delay is shortened by a random
+/// fraction of up to `jitter`, so many clients recovering from the same
+/// outage don't retry in lockstep.
+///
+/// # Example
+///
+/// ```no_run
+/// use anylist_rs::{AnyListClient, RetryPolicy, SavedTokens};
+/// use std::time::Duration;
+///
+/// # async fn example(tokens: SavedTokens) -> anylist_rs::Result<()> {
+/// let client = AnyListClient::builder()
+///     .retry_policy(
+///         RetryPolicy::new()
+///             .max_attempts(5)
+///             .initial_delay(Duration::from_millis(200))
+///             .max_delay(Duration::from_secs(5)),
+///     )
+///     .from_tokens(tokens)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, starting at 500ms and capped at 10s, with 20% jitter
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never retry: every request is sent exactly once
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Total number of attempts, including the first (minimum 1)
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Upper bound for the exponential backoff, and the longest `Retry-After`
+    /// the client will wait for
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Fraction (0.0 to 1.0) by which each delay may be randomly shortened
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Backoff before retry number `retry` (1-based), without jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// Delay before retry number `retry` after `error`, or `None` if the
+    /// server asked to wait longer than `max_delay`
+    fn delay_after(&self, retry: u32, error: &AnyListError) -> Option<Duration> {
+        if let AnyListError::RateLimited {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            return (*retry_after <= self.max_delay).then_some(*retry_after);
+        }
+
+        let backoff = self.backoff(retry);
+        Some(backoff.mul_f64(1.0 - self.jitter * random_fraction()))
+    }
+
+    /// Run `request` until it succeeds, fails with a non-retryable error, or
+    /// runs out of attempts
+    pub(crate) async fn run<T, F, Fut>(&self, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match request().await {
+                Err(error) if error.is_retryable() && attempt < self.max_attempts => {
+                    let Some(delay) = self.delay_after(attempt, &error) else {
+                        return Err(error);
+                    };
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Uniformly distributed value in `[0, 1)`
+pub(crate) fn random_fraction() -> f64 {
+    // The low 53 bits of a v4 UUID are all random
+    (Uuid::new_v4().as_u128() as u64 & ((1 << 53) - 1)) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let policy = RetryPolicy::new()
+            .initial_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5));
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_jitter_only_shortens_delay() {
+        let policy = RetryPolicy::new()
+            .initial_delay(Duration::from_secs(10))
+            .jitter(0.5);
+        let error = AnyListError::NetworkError("reset".into());
+
+        for _ in 0..100 {
+            let delay = policy.delay_after(1, &error).unwrap();
+            assert!(delay >= Duration::from_secs(5) && delay <= Duration::from_secs(10));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_overrides_backoff() {
+        let error = AnyListError::RateLimited {
+            retry_after: Some(Duration::from_secs(42)),
+            body: String::new(),
+        };
+        assert_eq!(
+            RetryPolicy::new()
+                .max_delay(Duration::from_secs(60))
+                .delay_after(1, &error),
+            Some(Duration::from_secs(42))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_beyond_max_delay_is_returned() {
+        let policy = RetryPolicy::new().max_delay(Duration::from_secs(10));
+
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = policy
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(AnyListError::RateLimited {
+                    retry_after: Some(Duration::from_secs(3600)),
+                    body: String::new(),
+                })
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(AnyListError::RateLimited {
+                retry_after: Some(_),
+                ..
+            })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_only_retryable_errors() {
+        let policy = RetryPolicy::new()
+            .max_attempts(3)
+            .initial_delay(Duration::from_millis(1));
+
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = policy
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(AnyListError::ServerError {
+                    status: 502,
+                    body: String::new(),
+                })
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = policy
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(AnyListError::NotFound("list".into()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
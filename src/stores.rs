@@ -1,18 +1,15 @@
+use crate::cache::UserDataSection;
 use crate::client::AnyListClient;
 use crate::error::{AnyListError, Result};
-use crate::protobuf::anylist::pb_operation_metadata::OperationClass;
-use crate::protobuf::anylist::{
-    PbListOperation, PbListOperationList, PbOperationMetadata, PbStore,
-};
+use crate::outcome::OperationOutcome;
 use crate::utils::generate_id;
-use prost::Message;
 use serde_derive::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Store {
-    id: String,
-    name: String,
-    sort_index: i32,
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) sort_index: i32,
 }
 
 impl Store {
@@ -31,9 +28,9 @@ impl Store {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StoreFilter {
-    id: String,
-    name: String,
-    store_ids: Vec<String>,
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) store_ids: Vec<String>,
 }
 
 impl StoreFilter {
@@ -57,7 +54,9 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the list
     pub async fn get_stores_for_list(&self, list_id: &str) -> Result<Vec<Store>> {
-        let data = self.get_user_data().await?;
+        let data = self
+            .cached_user_data(&[UserDataSection::ShoppingLists])
+            .await?;
 
         // Get the stores from the shopping lists response
         if let Some(shopping_lists_response) = data.shopping_lists_response {
@@ -98,46 +97,10 @@ impl AnyListClient {
     /// * `list_id` - The ID of the list
     /// * `name` - The name of the store
     pub async fn create_store(&self, list_id: &str, name: &str) -> Result<Store> {
-        let store_id = generate_id();
-        let operation_id = generate_id();
-
-        let new_store = PbStore {
-            identifier: store_id.clone(),
-            logical_timestamp: None,
-            list_id: Some(list_id.to_string()),
-            name: Some(name.to_string()),
-            // TODO: set this as the lists num. of stores + 1
-            sort_index: Some(0),
-        };
-
-        let operation = PbListOperation {
-            metadata: Some(PbOperationMetadata {
-                operation_id: Some(operation_id),
-                handler_id: Some("new-store".to_string()),
-                user_id: Some(self.user_id()),
-                operation_class: Some(OperationClass::Store as i32),
-            }),
-            list_id: Some(list_id.to_string()),
-            updated_store: Some(new_store),
-            ..Default::default()
-        };
-
-        let operation_list = PbListOperationList {
-            operations: vec![operation],
-        };
-
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update-v2", buf).await?;
-
-        Ok(Store {
-            id: store_id,
-            name: name.to_string(),
-            sort_index: 0,
-        })
+        let mut batch = self.batch();
+        let store = batch.create_store(list_id, name);
+        batch.commit().await?;
+        Ok(store)
     }
 
     /// Update a store's name
@@ -147,41 +110,15 @@ impl AnyListClient {
     /// * `list_id` - The ID of the list
     /// * `store_id` - The ID of the store
     /// * `new_name` - The new name for the store
-    pub async fn update_store(&self, list_id: &str, store_id: &str, new_name: &str) -> Result<()> {
-        let operation_id = generate_id();
-
-        let updated_store = PbStore {
-            identifier: store_id.to_string(),
-            logical_timestamp: Some(1),
-            list_id: Some(list_id.to_string()),
-            name: Some(new_name.to_string()),
-            sort_index: Some(0),
-        };
-
-        let operation = PbListOperation {
-            metadata: Some(PbOperationMetadata {
-                operation_id: Some(operation_id),
-                handler_id: Some("set-store-name".to_string()),
-                user_id: Some(self.user_id()),
-                operation_class: Some(OperationClass::Store as i32),
-            }),
-            list_id: Some(list_id.to_string()),
-            updated_value: Some(new_name.to_string()),
-            updated_store: Some(updated_store),
-            ..Default::default()
-        };
-
-        let operation_list = PbListOperationList {
-            operations: vec![operation],
-        };
-
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update-v2", buf).await?;
-        Ok(())
+    pub async fn update_store(
+        &self,
+        list_id: &str,
+        store_id: &str,
+        new_name: &str,
+    ) -> Result<OperationOutcome> {
+        let mut batch = self.batch();
+        batch.update_store(list_id, store_id, new_name);
+        batch.commit().await
     }
 
     /// Get all store filters for a specific list
@@ -190,7 +127,9 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the list
     pub async fn get_store_filters_for_list(&self, list_id: &str) -> Result<Vec<StoreFilter>> {
-        let data = self.get_user_data().await?;
+        let data = self
+            .cached_user_data(&[UserDataSection::ShoppingLists])
+            .await?;
 
         if let Some(shopping_lists_response) = data.shopping_lists_response {
             for list_response in shopping_lists_response.list_responses {
@@ -220,6 +159,43 @@ impl AnyListClient {
         )))
     }
 
+    /// Replace a store filter's name and stores
+    ///
+    /// # Arguments
+    ///
+    /// * `list_id` - The ID of the list
+    /// * `filter_id` - The ID of the store filter
+    /// * `name` - The name for the filter
+    /// * `store_ids` - The stores the filter shows
+    pub async fn update_store_filter(
+        &self,
+        list_id: &str,
+        filter_id: &str,
+        name: &str,
+        store_ids: &[String],
+    ) -> Result<OperationOutcome> {
+        let mut batch = self.batch();
+        batch.update_store_filter(list_id, filter_id, name, store_ids);
+        batch.commit().await
+    }
+
+    /// Delete a store filter
+    ///
+    /// # Arguments
+    ///
+    /// * `list_id` - The ID of the list
+    /// * `filter` - The store filter, as returned by
+    ///   [`get_store_filters_for_list`](AnyListClient::get_store_filters_for_list)
+    pub async fn delete_store_filter(
+        &self,
+        list_id: &str,
+        filter: &StoreFilter,
+    ) -> Result<OperationOutcome> {
+        let mut batch = self.batch();
+        batch.delete_store_filter(list_id, filter);
+        batch.commit().await
+    }
+
     /// Remove a store ID from all items in shopping lists
     ///
     /// # Arguments
@@ -230,7 +206,7 @@ impl AnyListClient {
         &self,
         list_id: &str,
         store_id: &str,
-    ) -> Result<()> {
+    ) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         // Imperative shell: gather runtime values
@@ -245,13 +221,8 @@ impl AnyListClient {
         let operation_list = crate::operations::build_remove_store_from_items_operation(params);
 
         // Imperative shell: side effects
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update", buf).await?;
-        Ok(())
+        self.post_edit("data/shopping-lists/update", &operation_list)
+            .await
     }
 
     /// Remove a store ID from all items in starter lists
@@ -264,7 +235,7 @@ impl AnyListClient {
         &self,
         list_id: &str,
         store_id: &str,
-    ) -> Result<()> {
+    ) -> Result<OperationOutcome> {
         let operation_id = generate_id();
 
         // Imperative shell: gather runtime values
@@ -279,13 +250,8 @@ impl AnyListClient {
         let operation_list = crate::operations::build_remove_store_from_items_operation(params);
 
         // Imperative shell: side effects
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/starter-lists/update", buf).await?;
-        Ok(())
+        self.post_edit("data/starter-lists/update", &operation_list)
+            .await
     }
 
     /// Update or delete store filters that contain a specific store ID
@@ -298,7 +264,11 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the list
     /// * `store_id` - The ID of the store to remove from filters
-    async fn delete_store_filters_with_store(&self, list_id: &str, store_id: &str) -> Result<()> {
+    async fn delete_store_filters_with_store(
+        &self,
+        list_id: &str,
+        store_id: &str,
+    ) -> Result<OperationOutcome> {
         let filters = self.get_store_filters_for_list(list_id).await?;
 
         // Find filters that contain this store_id
@@ -307,9 +277,7 @@ impl AnyListClient {
             .filter(|filter| filter.store_ids.contains(&store_id.to_string()))
             .collect();
 
-        if affected_filters.is_empty() {
-            return Ok(());
-        }
+        let mut outcome = OperationOutcome::default();
 
         // Process each affected filter
         for filter in affected_filters {
@@ -332,12 +300,10 @@ impl AnyListClient {
 
                 let operation_list = crate::operations::build_delete_store_filter_operation(params);
 
-                let mut buf = Vec::new();
-                operation_list.encode(&mut buf).map_err(|e| {
-                    AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-                })?;
-
-                self.post("data/shopping-lists/update-v2", buf).await?;
+                outcome = outcome.merge(
+                    self.post_edit("data/shopping-lists/update-v2", &operation_list)
+                        .await?,
+                );
             } else {
                 // UPDATE: Filter still has other stores
                 let params = crate::operations::UpdateStoreFilterParams {
@@ -351,16 +317,14 @@ impl AnyListClient {
 
                 let operation_list = crate::operations::build_update_store_filter_operation(params);
 
-                let mut buf = Vec::new();
-                operation_list.encode(&mut buf).map_err(|e| {
-                    AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-                })?;
-
-                self.post("data/shopping-lists/update-v2", buf).await?;
+                outcome = outcome.merge(
+                    self.post_edit("data/shopping-lists/update-v2", &operation_list)
+                        .await?,
+                );
             }
         }
 
-        Ok(())
+        Ok(outcome)
     }
 
     /// Delete a store (with proper cleanup of items and filters)
@@ -376,22 +340,29 @@ impl AnyListClient {
     ///
     /// * `list_id` - The ID of the list
     /// * `store_id` - The ID of the store to delete
-    pub async fn delete_store(&self, list_id: &str, store_id: &str) -> Result<()> {
+    pub async fn delete_store(&self, list_id: &str, store_id: &str) -> Result<OperationOutcome> {
         // Step 1: Remove store from all items in shopping lists
-        self.remove_store_from_shopping_list_items(list_id, store_id)
+        let mut outcome = self
+            .remove_store_from_shopping_list_items(list_id, store_id)
             .await?;
 
         // Step 2: Remove store from all items in starter lists
-        self.remove_store_from_starter_list_items(list_id, store_id)
-            .await?;
+        outcome = outcome.merge(
+            self.remove_store_from_starter_list_items(list_id, store_id)
+                .await?,
+        );
 
         // Step 3: Delete store filters containing this store
-        self.delete_store_filters_with_store(list_id, store_id)
-            .await?;
+        outcome = outcome.merge(
+            self.delete_store_filters_with_store(list_id, store_id)
+                .await?,
+        );
 
         // Step 4: Remove store from starter list items again
-        self.remove_store_from_starter_list_items(list_id, store_id)
-            .await?;
+        outcome = outcome.merge(
+            self.remove_store_from_starter_list_items(list_id, store_id)
+                .await?,
+        );
 
         // Step 5: Delete the store itself
         let operation_id = generate_id();
@@ -409,12 +380,9 @@ impl AnyListClient {
         let operation_list = crate::operations::build_delete_store_operation(params);
 
         // Imperative shell: side effects
-        let mut buf = Vec::new();
-        operation_list.encode(&mut buf).map_err(|e| {
-            AnyListError::ProtobufError(format!("Failed to encode operation: {}", e))
-        })?;
-
-        self.post("data/shopping-lists/update-v2", buf).await?;
-        Ok(())
+        let deleted = self
+            .post_edit("data/shopping-lists/update-v2", &operation_list)
+            .await?;
+        Ok(outcome.merge(deleted))
     }
 }
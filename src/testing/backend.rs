@@ -0,0 +1,1190 @@
+use crate::client::{AnyListClient, AnyListClientBuilder, SavedTokens};
+use crate::error::{AnyListError, Result};
+use crate::outcome::OperationList;
+use crate::protobuf::anylist::{
+    PbAccountInfoResponse, PbCalendarOperationList, PbCalendarResponse, PbEditOperationResponse,
+    PbListCategoryGroup, PbListCategoryGroupResponse, PbListFolder, PbListFolderItem,
+    PbListFolderOperationList, PbListFoldersResponse, PbListOperation, PbListOperationList,
+    PbListResponse, PbListSettings, PbListSettingsList, PbListSettingsOperationList,
+    PbLogicalTimestamp, PbRecipeDataResponse, PbRecipeOperationList, PbShoppingList,
+    PbShoppingListsResponse, PbStarterList, PbStarterListBatchResponse, PbStarterListOperationList,
+    PbStarterListResponse, PbStarterListsResponseV2, PbTimestamp, PbUserDataClientTimestamps,
+    PbUserDataResponse, PbWatchSyncMultipartResponse, PbWatchSyncResponse,
+};
+use crate::transport::{Method, MultipartForm, Transport, TransportRequest, TransportResponse};
+use crate::utils::url_path;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use futures_util::future::BoxFuture;
+use prost::Message;
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const BASE_URL: &str = "http://anylist.test";
+const PHOTOS_BASE_URL: &str = "http://photos.anylist.test";
+const WEBSOCKET_URL: &str = "ws://anylist.test/data/add-user-listener";
+
+/// Identifiers of the containers [`FakeAnyList::new`] seeds the account with
+const LIST_DATA_ID: &str = "fake-list-data";
+const ROOT_FOLDER_ID: &str = "fake-root-folder";
+const RECIPE_DATA_ID: &str = "fake-recipe-data";
+const CALENDAR_ID: &str = "fake-calendar";
+const FAVOURITES_LIST_ID: &str = "fake-favourites";
+
+/// In-memory fake of the AnyList service (see the [module docs](super)).
+///
+/// Cloning is cheap and every clone shares the same state, so a test can
+/// keep one handle to inspect or seed the data while a client uses another
+/// as its transport.
+#[derive(Clone)]
+pub struct FakeAnyList {
+    state: Arc<Mutex<FakeState>>,
+}
+
+struct FakeState {
+    user_data: PbUserDataResponse,
+    email: String,
+    password: String,
+    user_id: String,
+    is_premium_user: bool,
+    access_token: String,
+    refresh_token: String,
+    token_generation: u32,
+    /// Lifetime written into access tokens, if they are JWTs
+    access_token_lifetime: Option<Duration>,
+    /// Answer to `data/account/info`, if set
+    account_info: Option<PbAccountInfoResponse>,
+    photos: HashMap<String, Vec<u8>>,
+    failures: VecDeque<u16>,
+    drop_next_edit: bool,
+    logical_timestamps: HashMap<String, u64>,
+    /// Last modification time handed out, so every change gets a new one
+    clock: f64,
+    requests: Vec<TransportRequest>,
+}
+
+impl Default for FakeAnyList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeAnyList {
+    /// Create a fake account with no lists or recipes, one favourites list
+    /// and one meal planning calendar.
+    ///
+    /// It accepts the credentials `user@example.com` / `password`.
+    pub fn new() -> Self {
+        let mut state = FakeState {
+            user_data: seed_user_data(),
+            email: "user@example.com".to_string(),
+            password: "password".to_string(),
+            user_id: "fake-user".to_string(),
+            is_premium_user: true,
+            access_token: String::new(),
+            refresh_token: String::new(),
+            token_generation: 0,
+            access_token_lifetime: None,
+            account_info: None,
+            photos: HashMap::new(),
+            failures: VecDeque::new(),
+            drop_next_edit: false,
+            logical_timestamps: HashMap::new(),
+            clock: 0.0,
+            requests: Vec::new(),
+        };
+        state.rotate_tokens();
+
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Accept a different email and password for `/auth/token`
+    pub fn with_credentials(self, email: impl Into<String>, password: impl Into<String>) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.email = email.into();
+            state.password = password.into();
+        }
+        self
+    }
+
+    /// Issue JWT access tokens that expire `lifetime` after they are issued,
+    /// as the real service does, instead of opaque ones that never expire.
+    ///
+    /// The fake still accepts an access token until it is replaced or
+    /// [expired](Self::expire_access_token); the expiry only tells the
+    /// client when to refresh.
+    pub fn with_access_token_lifetime(self, lifetime: Duration) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.access_token_lifetime = Some(lifetime);
+            state.rotate_tokens();
+        }
+        self
+    }
+
+    /// Answer `data/account/info` with `info`. By default the answer only
+    /// has the account's email and premium flag.
+    pub fn with_account_info(self, info: PbAccountInfoResponse) -> Self {
+        self.state.lock().unwrap().account_info = Some(info);
+        self
+    }
+
+    /// Replace the account's data wholesale
+    pub fn with_user_data(self, user_data: PbUserDataResponse) -> Self {
+        self.state.lock().unwrap().user_data = user_data;
+        self
+    }
+
+    /// Snapshot of the account's current data
+    pub fn user_data(&self) -> PbUserDataResponse {
+        self.state.lock().unwrap().user_data.clone()
+    }
+
+    /// Modify the account's data in place, e.g. to simulate a change made
+    /// from another device.
+    ///
+    /// Every list, the recipe data and the calendar are marked as modified,
+    /// so incremental syncs pick the change up.
+    pub fn update_user_data<F>(&self, f: F)
+    where
+        F: FnOnce(&mut PbUserDataResponse),
+    {
+        let mut state = self.state.lock().unwrap();
+        f(&mut state.user_data);
+
+        let mut container_ids: Vec<String> = state
+            .user_data
+            .shopping_lists_response
+            .iter()
+            .flat_map(|r| r.new_lists.iter().map(|l| l.identifier.clone()))
+            .collect();
+        container_ids.push(RECIPE_DATA_ID.to_string());
+        container_ids.push(CALENDAR_ID.to_string());
+        let now = state.now();
+        for id in container_ids {
+            state.touch(&id, now);
+        }
+    }
+
+    /// The user ID of the fake account
+    pub fn user_id(&self) -> String {
+        self.state.lock().unwrap().user_id.clone()
+    }
+
+    /// The ID of the seeded meal planning calendar
+    pub fn calendar_id(&self) -> &'static str {
+        CALENDAR_ID
+    }
+
+    /// Tokens currently accepted by the fake
+    pub fn tokens(&self) -> SavedTokens {
+        let state = self.state.lock().unwrap();
+        SavedTokens::new(
+            state.access_token.clone(),
+            state.refresh_token.clone(),
+            state.user_id.clone(),
+            state.is_premium_user,
+        )
+    }
+
+    /// Invalidate the current access token, so the next request gets a 401
+    /// and the client has to refresh
+    pub fn expire_access_token(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.access_token = format!("expired-{}", state.access_token);
+    }
+
+    /// Invalidate the current tokens, as logging out elsewhere or a
+    /// password change would: the next refresh gets a 401
+    pub fn revoke_tokens(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.access_token = format!("revoked-{}", state.access_token);
+        state.refresh_token = format!("revoked-{}", state.refresh_token);
+    }
+
+    /// Answer the next API request, login or token refresh with `status` and
+    /// an empty body instead of handling it. Calls queue up, one failure per request.
+    pub fn fail_next(&self, status: u16) {
+        self.state.lock().unwrap().failures.push_back(status);
+    }
+
+    /// Accept the next mutation request without processing it: the response
+    /// lists no processed operations and the data is left unchanged
+    pub fn drop_next_edit(&self) {
+        self.state.lock().unwrap().drop_next_edit = true;
+    }
+
+    /// Current logical timestamp of a container (list, recipe data,
+    /// calendar, ...), incremented by every mutation of it
+    pub fn logical_timestamp(&self, id: &str) -> u64 {
+        self.state
+            .lock()
+            .unwrap()
+            .logical_timestamps
+            .get(id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Every request received so far, in order
+    pub fn requests(&self) -> Vec<TransportRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// A client builder with every URL and the transport pointed at this fake
+    pub fn client_builder(&self) -> AnyListClientBuilder {
+        AnyListClient::builder()
+            .api_base_url(BASE_URL)
+            .auth_base_url(BASE_URL)
+            .photos_base_url(PHOTOS_BASE_URL)
+            .websocket_url(WEBSOCKET_URL)
+            .transport(self.clone())
+    }
+
+    /// A client already authenticated against this fake
+    pub fn client(&self) -> AnyListClient {
+        self.client_builder()
+            .build_from_tokens(self.tokens())
+            .expect("restoring tokens cannot fail")
+    }
+}
+
+impl Transport for FakeAnyList {
+    fn send(&self, request: TransportRequest) -> BoxFuture<'_, Result<TransportResponse>> {
+        let response = {
+            let mut state = self.state.lock().unwrap();
+            state.requests.push(request.clone());
+            state.handle(request)
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+impl FakeState {
+    fn rotate_tokens(&mut self) {
+        self.token_generation += 1;
+        self.access_token = format!("fake-access-token-{}", self.token_generation);
+        if let Some(lifetime) = self.access_token_lifetime {
+            let expires_at = chrono::Utc::now() + lifetime;
+            let claims = serde_json::json!({
+                "sub": self.access_token,
+                "exp": expires_at.timestamp(),
+            });
+            self.access_token = format!(
+                "{}.{}.fake-signature",
+                URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#),
+                URL_SAFE_NO_PAD.encode(claims.to_string()),
+            );
+        }
+        self.refresh_token = format!("fake-refresh-token-{}", self.token_generation);
+    }
+
+    fn handle(&mut self, request: TransportRequest) -> TransportResponse {
+        let path = url_path(&request.url).to_string();
+        let form = request.body.unwrap_or_default();
+
+        if request.method == Method::GET {
+            return match path.strip_prefix('/').and_then(|p| p.strip_suffix(".jpg")) {
+                Some(photo_id) => match self.photos.get(photo_id) {
+                    Some(photo) => respond(200, photo.clone()),
+                    None => respond(404, b"no such photo".to_vec()),
+                },
+                None => respond(404, b"not found".to_vec()),
+            };
+        }
+
+        match path.as_str() {
+            "/auth/token" => return self.login(&form),
+            "/auth/token/refresh" => return self.refresh(&form),
+            _ => {}
+        }
+
+        if let Some(status) = self.failures.pop_front() {
+            return respond(status, Vec::new());
+        }
+
+        if !self.is_authorized(&request.headers) {
+            return respond(401, b"unauthorized".to_vec());
+        }
+
+        let result = match path.as_str() {
+            "/data/user-data/get" => match form.part("timestamps") {
+                Some(part) => PbUserDataClientTimestamps::decode(part.data())
+                    .map(|known| encode(&self.user_data_since(&known)))
+                    .map_err(AnyListError::from),
+                None => Ok(encode(&self.user_data)),
+            },
+            // The client's assumed request format, not observed traffic
+            crate::watch_sync::WATCH_SYNC_ENDPOINT => {
+                let since = text_field(&form, crate::watch_sync::WATCH_SYNC_TIMESTAMP_FIELD)
+                    .and_then(|t| t.parse().ok())
+                    .unwrap_or(0);
+                Ok(encode(&self.watch_sync_since(since)))
+            }
+            "/data/shopping-lists/update" | "/data/shopping-lists/update-v2" => {
+                self.edit(&form, Self::apply_list_operations)
+            }
+            "/data/list-folders/update" => self.edit(&form, Self::apply_list_folder_operations),
+            "/data/list-settings/update" => self.edit(&form, Self::apply_list_settings_operations),
+            "/data/starter-lists/update" => self.edit(&form, Self::apply_starter_list_operations),
+            "/data/user-recipe-data/update" => self.edit(&form, Self::apply_recipe_operations),
+            "/data/meal-planning-calendar/update" => {
+                self.edit(&form, Self::apply_calendar_operations)
+            }
+            "/data/photos/upload" => self.upload_photo(&form),
+            "/data/account/info" => Ok(encode(&self.account_info())),
+            _ => return respond(404, format!("unknown endpoint {}", path).into_bytes()),
+        };
+
+        match result {
+            Ok(body) => respond(200, body),
+            Err(e) => respond(400, e.to_string().into_bytes()),
+        }
+    }
+
+    fn account_info(&self) -> PbAccountInfoResponse {
+        self.account_info
+            .clone()
+            .unwrap_or_else(|| PbAccountInfoResponse {
+                email: Some(self.email.clone()),
+                is_premium_user: Some(self.is_premium_user),
+                ..Default::default()
+            })
+    }
+
+    fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        let expected = format!("Bearer {}", self.access_token);
+        headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v == expected)
+            .unwrap_or(false)
+    }
+
+    fn login(&mut self, form: &MultipartForm) -> TransportResponse {
+        if let Some(status) = self.failures.pop_front() {
+            return respond(status, Vec::new());
+        }
+        if text_field(form, "email") != Some(self.email.clone())
+            || text_field(form, "password") != Some(self.password.clone())
+        {
+            return respond(401, br#"{"error":"invalid credentials"}"#.to_vec());
+        }
+
+        self.rotate_tokens();
+        self.token_response()
+    }
+
+    fn refresh(&mut self, form: &MultipartForm) -> TransportResponse {
+        if let Some(status) = self.failures.pop_front() {
+            return respond(status, Vec::new());
+        }
+        if text_field(form, "refresh_token") != Some(self.refresh_token.clone()) {
+            return respond(401, br#"{"error":"invalid refresh token"}"#.to_vec());
+        }
+
+        self.rotate_tokens();
+        self.token_response()
+    }
+
+    fn token_response(&self) -> TransportResponse {
+        let body = serde_json::json!({
+            "access_token": self.access_token,
+            "refresh_token": self.refresh_token,
+            "user_id": self.user_id,
+            "is_premium_user": self.is_premium_user,
+        });
+        respond(200, body.to_string().into_bytes())
+    }
+
+    /// Apply a mutation request and answer with a `PBEditOperationResponse`
+    fn edit<M: FakeOperationList>(
+        &mut self,
+        form: &MultipartForm,
+        apply: fn(&mut Self, M) -> Result<()>,
+    ) -> Result<Vec<u8>> {
+        let ops = decode::<M>(form)?;
+        let operation_ids = ops.operation_ids();
+        let container_ids = ops.container_ids();
+
+        if self.drop_next_edit {
+            self.drop_next_edit = false;
+            return Ok(encode(&PbEditOperationResponse::default()));
+        }
+
+        apply(self, ops)?;
+
+        let now = self.now();
+        let mut response = PbEditOperationResponse {
+            processed_operations: operation_ids,
+            ..Default::default()
+        };
+        for id in container_ids {
+            let logical_timestamp = self.touch(&id, now);
+            response.new_timestamps.push(PbTimestamp {
+                identifier: Some(id.clone()),
+                timestamp: Some(now),
+            });
+            response
+                .current_logical_timestamps
+                .push(PbLogicalTimestamp {
+                    identifier: Some(id),
+                    logical_timestamp: Some(logical_timestamp),
+                    description: None,
+                });
+        }
+        Ok(encode(&response))
+    }
+
+    /// A modification time later than any handed out before
+    fn now(&mut self) -> f64 {
+        self.clock = crate::utils::current_timestamp().max(self.clock + 0.001);
+        self.clock
+    }
+
+    /// Record a change to a container, returning its new logical timestamp
+    fn touch(&mut self, id: &str, now: f64) -> u64 {
+        let logical_timestamp = self.logical_timestamps.entry(id.to_string()).or_insert(0);
+        *logical_timestamp += 1;
+        let logical_timestamp = *logical_timestamp;
+
+        let data = &mut self.user_data;
+        if let Some(lists) = data.shopping_lists_response.as_mut() {
+            if let Some(list) = lists.new_lists.iter_mut().find(|l| l.identifier == id) {
+                list.timestamp = Some(now);
+            }
+            if let Some(response) = lists
+                .list_responses
+                .iter_mut()
+                .find(|r| r.list_id.as_deref() == Some(id))
+            {
+                response.logical_timestamp = Some(logical_timestamp);
+            }
+        }
+        if let Some(recipes) = data.recipe_data_response.as_mut() {
+            if recipes.recipe_data_id.as_deref() == Some(id) {
+                recipes.timestamp = Some(now);
+            }
+        }
+        if let Some(calendar) = data.meal_planning_calendar_response.as_mut() {
+            if calendar.calendar_id == id {
+                calendar.logical_timestamp = Some(logical_timestamp);
+            }
+        }
+        logical_timestamp
+    }
+
+    /// The user data as a delta against what the client says it has.
+    ///
+    /// Shopping lists, list responses, recipe data and the calendar are left
+    /// out when unchanged; everything else is always sent in full.
+    fn user_data_since(&self, known: &PbUserDataClientTimestamps) -> PbUserDataResponse {
+        let mut delta = self.user_data.clone();
+
+        if let Some(lists) = delta.shopping_lists_response.as_mut() {
+            let known_lists = known
+                .shopping_list_timestamps
+                .as_ref()
+                .map(|t| t.timestamps.as_slice())
+                .unwrap_or_default();
+            let known_logical = known
+                .shopping_list_logical_timestamps
+                .as_ref()
+                .map(|t| t.timestamps.as_slice())
+                .unwrap_or_default();
+
+            let current = std::mem::take(&mut lists.new_lists);
+            lists.ordered_ids = current.iter().map(|l| l.identifier.clone()).collect();
+            lists.unknown_ids = known_lists
+                .iter()
+                .filter_map(|t| t.identifier.clone())
+                .filter(|id| !lists.ordered_ids.contains(id))
+                .collect();
+            for list in current {
+                match known_lists
+                    .iter()
+                    .find(|t| t.identifier.as_deref() == Some(&list.identifier))
+                {
+                    Some(t) if t.timestamp == list.timestamp => {
+                        lists.unmodified_ids.push(list.identifier)
+                    }
+                    Some(_) => lists.modified_lists.push(list),
+                    None => lists.new_lists.push(list),
+                }
+            }
+
+            lists.list_responses.retain_mut(|response| {
+                let unchanged = known_logical.iter().any(|t| {
+                    t.identifier == response.list_id
+                        && t.logical_timestamp == response.logical_timestamp
+                });
+                response.is_full_sync = Some(true);
+                !unchanged
+            });
+        }
+
+        let recipe_timestamp = delta.recipe_data_response.as_ref().map(|r| r.timestamp);
+        if known
+            .user_recipe_data_timestamp
+            .as_ref()
+            .map(|t| t.timestamp)
+            == recipe_timestamp
+        {
+            delta.recipe_data_response = None;
+        }
+
+        if let Some(calendar) = delta.meal_planning_calendar_response.as_mut() {
+            calendar.is_full_sync = Some(true);
+            if known
+                .meal_planning_calendar_timestamp
+                .as_ref()
+                .is_some_and(|t| t.logical_timestamp == calendar.logical_timestamp)
+            {
+                delta.meal_planning_calendar_response = None;
+            }
+        }
+
+        delta
+    }
+
+    /// The lists as a watch sync response split into small parts.
+    ///
+    /// The logical timestamp counts every change made so far. Clients that
+    /// are up to date get an empty delta; everyone else gets a full sync.
+    fn watch_sync_since(&self, since: u64) -> PbWatchSyncMultipartResponse {
+        let logical_timestamp = self.logical_timestamps.values().sum();
+        let mut response = PbWatchSyncResponse {
+            user_id: Some(self.user_id.clone()),
+            is_premium_user: Some(self.is_premium_user),
+            logical_timestamp: Some(logical_timestamp),
+            is_full_sync: Some(since != logical_timestamp),
+            ..Default::default()
+        };
+
+        if since != logical_timestamp {
+            let data = &self.user_data;
+            if let Some(lists) = &data.shopping_lists_response {
+                for list in &lists.new_lists {
+                    response.list_items.extend(list.items.iter().cloned());
+                    response.shopping_lists.push(PbShoppingList {
+                        items: Vec::new(),
+                        ..list.clone()
+                    });
+                }
+                for list_response in &lists.list_responses {
+                    response.stores.extend(list_response.stores.iter().cloned());
+                    response
+                        .store_filters
+                        .extend(list_response.store_filters.iter().cloned());
+                    for group_response in &list_response.category_group_responses {
+                        if let Some(group) = &group_response.category_group {
+                            response
+                                .list_categories
+                                .extend(group.categories.iter().cloned());
+                            response.list_category_groups.push(group.clone());
+                        }
+                    }
+                }
+            }
+            if let Some(settings) = &data.list_settings_response {
+                response.list_settings = settings.settings.clone();
+            }
+            if let Some(folders) = &data.list_folders_response {
+                response.root_folder_id = folders.root_folder_id.clone();
+                response.list_folders = folders.list_folders.clone();
+            }
+        }
+
+        crate::watch_sync::split(&response, 64)
+    }
+
+    fn upload_photo(&mut self, form: &MultipartForm) -> Result<Vec<u8>> {
+        let filename = text_field(form, "filename")
+            .ok_or_else(|| AnyListError::InvalidResponse("missing filename".to_string()))?;
+        let photo = form
+            .part("photo")
+            .ok_or_else(|| AnyListError::InvalidResponse("missing photo".to_string()))?;
+        let photo_id = filename.trim_end_matches(".jpg").to_string();
+
+        self.photos.insert(photo_id, photo.data().to_vec());
+        Ok(Vec::new())
+    }
+
+    // ========================================================================
+    // Shopping lists
+    // ========================================================================
+
+    fn apply_list_operations(&mut self, ops: PbListOperationList) -> Result<()> {
+        for op in ops.operations {
+            self.apply_list_operation(op)?;
+        }
+        Ok(())
+    }
+
+    fn apply_list_operation(&mut self, op: PbListOperation) -> Result<()> {
+        let handler = handler_id(&op.metadata);
+        let list_id = op.list_id.clone().unwrap_or_default();
+        let lists = self
+            .user_data
+            .shopping_lists_response
+            .get_or_insert_with(Default::default);
+
+        match handler.as_str() {
+            "new-shopping-list" => {
+                let list = required(op.list, "list")?;
+                lists.list_responses.push(PbListResponse {
+                    list_id: Some(list.identifier.clone()),
+                    ..Default::default()
+                });
+                lists.new_lists.push(list);
+
+                self.user_data
+                    .list_settings_response
+                    .get_or_insert_with(Default::default)
+                    .settings
+                    .push(PbListSettings {
+                        identifier: format!("settings-{}", list_id),
+                        list_id: Some(list_id.clone()),
+                        ..Default::default()
+                    });
+                if let Some(root) = self.root_folder() {
+                    root.items.push(PbListFolderItem {
+                        identifier: list_id,
+                        item_type: Some(0),
+                    });
+                }
+            }
+            "rename-list" => {
+                let renamed = required(op.list, "list")?;
+                find_list(lists, &list_id)?.name = renamed.name;
+            }
+            "add-shopping-list-item" => {
+                let mut item = required(op.list_item, "list_item")?;
+                item.list_id = Some(list_id.clone());
+                find_list(lists, &list_id)?.items.push(item);
+            }
+            "update-list-item" => {
+                let item = required(op.list_item, "list_item")?;
+                let list = find_list(lists, &list_id)?;
+                let existing = list
+                    .items
+                    .iter_mut()
+                    .find(|i| i.identifier == item.identifier)
+                    .ok_or_else(|| not_found("item", &item.identifier))?;
+                *existing = item;
+            }
+            "set-list-item-checked" => {
+                let item_id = op.list_item_id.unwrap_or_default();
+                let list = find_list(lists, &list_id)?;
+                let item = list
+                    .items
+                    .iter_mut()
+                    .find(|i| i.identifier == item_id)
+                    .ok_or_else(|| not_found("item", &item_id))?;
+                item.checked = Some(op.updated_value.as_deref() == Some("y"));
+            }
+            "bulk-remove-list-items" => {
+                let removed = required(op.list, "list")?;
+                let list = find_list(lists, &list_id)?;
+                list.items
+                    .retain(|i| !removed.items.iter().any(|r| r.identifier == i.identifier));
+            }
+            "remove-store-id-from-all-items" => {
+                let store_id = op.updated_value.unwrap_or_default();
+                for list in lists.new_lists.iter_mut() {
+                    for item in list.items.iter_mut() {
+                        item.store_ids.retain(|id| *id != store_id);
+                    }
+                }
+            }
+            "new-store" | "set-store-name" => {
+                let store = required(op.updated_store, "updated_store")?;
+                let stores = &mut find_list_response(lists, &list_id).stores;
+                match stores.iter_mut().find(|s| s.identifier == store.identifier) {
+                    Some(existing) => existing.name = store.name,
+                    None => stores.push(store),
+                }
+            }
+            "delete-store" => {
+                let store = required(op.updated_store, "updated_store")?;
+                find_list_response(lists, &list_id)
+                    .stores
+                    .retain(|s| s.identifier != store.identifier);
+            }
+            "update-store-filter" => {
+                let filter = required(op.updated_store_filter, "updated_store_filter")?;
+                let filters = &mut find_list_response(lists, &list_id).store_filters;
+                match filters
+                    .iter_mut()
+                    .find(|f| f.identifier == filter.identifier)
+                {
+                    Some(existing) => *existing = filter,
+                    None => filters.push(filter),
+                }
+            }
+            "delete-store-filter" => {
+                let filter = required(op.updated_store_filter, "updated_store_filter")?;
+                find_list_response(lists, &list_id)
+                    .store_filters
+                    .retain(|f| f.identifier != filter.identifier);
+            }
+            "create-category" => {
+                let category = required(op.updated_category, "updated_category")?;
+                let group_id = category.category_group_id.clone().unwrap_or_default();
+                let response = find_list_response(lists, &list_id);
+                let group = match response.category_group_responses.iter().position(|g| {
+                    g.category_group
+                        .as_ref()
+                        .map(|g| g.identifier.as_deref() == Some(&group_id))
+                        .unwrap_or(false)
+                }) {
+                    Some(index) => &mut response.category_group_responses[index],
+                    None => {
+                        response
+                            .category_group_responses
+                            .push(PbListCategoryGroupResponse {
+                                category_group: Some(PbListCategoryGroup {
+                                    identifier: Some(group_id.clone()),
+                                    list_id: Some(list_id.clone()),
+                                    ..Default::default()
+                                }),
+                                deleted_category_ids: vec![],
+                            });
+                        response.category_group_responses.last_mut().unwrap()
+                    }
+                };
+                group
+                    .category_group
+                    .get_or_insert_with(Default::default)
+                    .categories
+                    .push(category);
+            }
+            "set-category-name" => {
+                let category = required(op.updated_category, "updated_category")?;
+                for group in find_list_response(lists, &list_id)
+                    .category_group_responses
+                    .iter_mut()
+                    .filter_map(|g| g.category_group.as_mut())
+                {
+                    for existing in group.categories.iter_mut() {
+                        if existing.identifier == category.identifier {
+                            existing.name = category.name.clone();
+                        }
+                    }
+                }
+            }
+            "remove-category" => {
+                let category_id = op.original_value.unwrap_or_default();
+                for response in find_list_response(lists, &list_id)
+                    .category_group_responses
+                    .iter_mut()
+                {
+                    if let Some(group) = response.category_group.as_mut() {
+                        let before = group.categories.len();
+                        group
+                            .categories
+                            .retain(|c| c.identifier.as_deref() != Some(&category_id));
+                        if group.categories.len() != before {
+                            response.deleted_category_ids.push(category_id.clone());
+                        }
+                    }
+                }
+            }
+            other => return Err(unsupported(other)),
+        }
+
+        Ok(())
+    }
+
+    fn root_folder(&mut self) -> Option<&mut PbListFolder> {
+        self.user_data
+            .list_folders_response
+            .as_mut()?
+            .list_folders
+            .iter_mut()
+            .find(|f| f.identifier == ROOT_FOLDER_ID)
+    }
+
+    fn apply_list_folder_operations(&mut self, ops: PbListFolderOperationList) -> Result<()> {
+        for op in ops.operations {
+            match handler_id(&op.metadata).as_str() {
+                "delete-folder-items" => {
+                    for folder_item in op.folder_items {
+                        if let Some(root) = self.root_folder() {
+                            root.items
+                                .retain(|i| i.identifier != folder_item.identifier);
+                        }
+                        // Deleting a list's folder entry deletes the list itself
+                        if folder_item.item_type == Some(0) {
+                            if let Some(lists) = self.user_data.shopping_lists_response.as_mut() {
+                                lists
+                                    .new_lists
+                                    .retain(|l| l.identifier != folder_item.identifier);
+                                lists.list_responses.retain(|r| {
+                                    r.list_id.as_deref() != Some(&folder_item.identifier)
+                                });
+                            }
+                        }
+                    }
+                }
+                other => return Err(unsupported(other)),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_list_settings_operations(&mut self, ops: PbListSettingsOperationList) -> Result<()> {
+        for op in ops.operations {
+            match handler_id(&op.metadata).as_str() {
+                "remove-list-settings" => {
+                    let settings = required(op.updated_settings, "updated_settings")?;
+                    if let Some(list) = self.user_data.list_settings_response.as_mut() {
+                        list.settings
+                            .retain(|s| s.identifier != settings.identifier);
+                    }
+                }
+                other => return Err(unsupported(other)),
+            }
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Starter lists (favourites)
+    // ========================================================================
+
+    fn apply_starter_list_operations(&mut self, ops: PbStarterListOperationList) -> Result<()> {
+        for op in ops.operations {
+            let list_id = op.list_id.clone().unwrap_or_default();
+            match handler_id(&op.metadata).as_str() {
+                "add-starter-list-item" => {
+                    let item = required(op.list_item, "list_item")?;
+                    self.find_starter_list(&list_id)?.items.push(item);
+                }
+                "remove-starter-list-item" => {
+                    let item_id = op.list_item_id.unwrap_or_default();
+                    self.find_starter_list(&list_id)?
+                        .items
+                        .retain(|i| i.identifier != item_id);
+                }
+                "remove-store-id-from-all-items" => {
+                    let store_id = op.updated_value.unwrap_or_default();
+                    for list in self.starter_lists_mut() {
+                        for item in list.items.iter_mut() {
+                            item.store_ids.retain(|id| *id != store_id);
+                        }
+                    }
+                }
+                other => return Err(unsupported(other)),
+            }
+        }
+        Ok(())
+    }
+
+    fn starter_lists_mut(&mut self) -> impl Iterator<Item = &mut PbStarterList> {
+        self.user_data
+            .starter_lists_response
+            .iter_mut()
+            .flat_map(|r| {
+                [
+                    r.user_lists_response.as_mut(),
+                    r.recent_item_lists_response.as_mut(),
+                    r.favorite_item_lists_response.as_mut(),
+                ]
+            })
+            .flatten()
+            .flat_map(|batch| batch.list_responses.iter_mut())
+            .filter_map(|response| response.starter_list.as_mut())
+    }
+
+    fn find_starter_list(&mut self, list_id: &str) -> Result<&mut PbStarterList> {
+        self.starter_lists_mut()
+            .find(|l| l.identifier == list_id)
+            .ok_or_else(|| not_found("starter list", list_id))
+    }
+
+    // ========================================================================
+    // Recipes
+    // ========================================================================
+
+    fn apply_recipe_operations(&mut self, ops: PbRecipeOperationList) -> Result<()> {
+        let data = self
+            .user_data
+            .recipe_data_response
+            .get_or_insert_with(Default::default);
+
+        for op in ops.operations {
+            match handler_id(&op.metadata).as_str() {
+                "save-recipe" => {
+                    let recipe = required(op.recipe, "recipe")?;
+                    match data
+                        .recipes
+                        .iter_mut()
+                        .find(|r| r.identifier == recipe.identifier)
+                    {
+                        Some(existing) => *existing = recipe,
+                        None => data.recipes.push(recipe),
+                    }
+                }
+                "remove-recipe" => {
+                    data.recipes
+                        .retain(|r| !op.recipe_ids.contains(&r.identifier));
+                    for collection in data.recipe_collections.iter_mut() {
+                        collection
+                            .recipe_ids
+                            .retain(|id| !op.recipe_ids.contains(id));
+                    }
+                }
+                "new-recipe-collection" => {
+                    let collection = required(op.recipe_collection, "recipe_collection")?;
+                    data.recipe_collection_ids
+                        .push(collection.identifier.clone());
+                    data.recipe_collections.push(collection);
+                }
+                "remove-recipe-collection" => {
+                    data.recipe_collections
+                        .retain(|c| !op.recipe_collection_ids.contains(&c.identifier));
+                    data.recipe_collection_ids
+                        .retain(|id| !op.recipe_collection_ids.contains(id));
+                }
+                "add-recipes-to-collection" | "remove-recipes-from-collection" => {
+                    let collection = required(op.recipe_collection, "recipe_collection")?;
+                    let existing = data
+                        .recipe_collections
+                        .iter_mut()
+                        .find(|c| c.identifier == collection.identifier)
+                        .ok_or_else(|| not_found("collection", &collection.identifier))?;
+                    *existing = collection;
+                }
+                other => return Err(unsupported(other)),
+            }
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Meal planning calendar
+    // ========================================================================
+
+    fn apply_calendar_operations(&mut self, ops: PbCalendarOperationList) -> Result<()> {
+        let calendar = self
+            .user_data
+            .meal_planning_calendar_response
+            .get_or_insert_with(Default::default);
+
+        for op in ops.operations {
+            match handler_id(&op.metadata).as_str() {
+                "new-event" | "update-event" => {
+                    let event = required(op.updated_event, "updated_event")?;
+                    match calendar
+                        .events
+                        .iter_mut()
+                        .find(|e| e.identifier == event.identifier)
+                    {
+                        Some(existing) => *existing = event,
+                        None => calendar.events.push(event),
+                    }
+                }
+                "delete-event" => {
+                    calendar
+                        .events
+                        .retain(|e| !op.event_ids.contains(&e.identifier));
+                    calendar.deleted_event_ids.extend(op.event_ids);
+                }
+                other => return Err(unsupported(other)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An operation list the fake accepts, and the containers it modifies
+trait FakeOperationList: OperationList + Default {
+    fn container_ids(&self) -> Vec<String>;
+}
+
+impl FakeOperationList for PbListOperationList {
+    fn container_ids(&self) -> Vec<String> {
+        unique(self.operations.iter().filter_map(|op| op.list_id.clone()))
+    }
+}
+
+impl FakeOperationList for PbListFolderOperationList {
+    fn container_ids(&self) -> Vec<String> {
+        unique(
+            self.operations
+                .iter()
+                .filter_map(|op| op.list_data_id.clone()),
+        )
+    }
+}
+
+impl FakeOperationList for PbListSettingsOperationList {
+    fn container_ids(&self) -> Vec<String> {
+        unique(
+            self.operations
+                .iter()
+                .filter_map(|op| Some(op.updated_settings.as_ref()?.identifier.clone())),
+        )
+    }
+}
+
+impl FakeOperationList for PbStarterListOperationList {
+    fn container_ids(&self) -> Vec<String> {
+        unique(self.operations.iter().filter_map(|op| op.list_id.clone()))
+    }
+}
+
+impl FakeOperationList for PbRecipeOperationList {
+    fn container_ids(&self) -> Vec<String> {
+        unique(
+            self.operations
+                .iter()
+                .filter_map(|op| op.recipe_data_id.clone()),
+        )
+    }
+}
+
+impl FakeOperationList for PbCalendarOperationList {
+    fn container_ids(&self) -> Vec<String> {
+        unique(
+            self.operations
+                .iter()
+                .filter_map(|op| op.calendar_id.clone()),
+        )
+    }
+}
+
+fn unique(ids: impl Iterator<Item = String>) -> Vec<String> {
+    let mut unique = Vec::new();
+    for id in ids {
+        if !unique.contains(&id) {
+            unique.push(id);
+        }
+    }
+    unique
+}
+
+fn seed_user_data() -> PbUserDataResponse {
+    PbUserDataResponse {
+        shopping_lists_response: Some(PbShoppingListsResponse::default()),
+        list_folders_response: Some(PbListFoldersResponse {
+            list_data_id: Some(LIST_DATA_ID.to_string()),
+            root_folder_id: Some(ROOT_FOLDER_ID.to_string()),
+            includes_all_folders: Some(true),
+            list_folders: vec![PbListFolder {
+                identifier: ROOT_FOLDER_ID.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }),
+        recipe_data_response: Some(PbRecipeDataResponse {
+            recipe_data_id: Some(RECIPE_DATA_ID.to_string()),
+            ..Default::default()
+        }),
+        meal_planning_calendar_response: Some(PbCalendarResponse {
+            calendar_id: CALENDAR_ID.to_string(),
+            ..Default::default()
+        }),
+        starter_lists_response: Some(PbStarterListsResponseV2 {
+            favorite_item_lists_response: Some(PbStarterListBatchResponse {
+                list_responses: vec![PbStarterListResponse {
+                    starter_list: Some(PbStarterList {
+                        identifier: FAVOURITES_LIST_ID.to_string(),
+                        name: Some("Favourites".to_string()),
+                        starter_list_type: Some(2),
+                        ..Default::default()
+                    }),
+                }],
+                includes_all_lists: Some(true),
+                unknown_list_ids: vec![],
+            }),
+            ..Default::default()
+        }),
+        list_settings_response: Some(PbListSettingsList::default()),
+        ..Default::default()
+    }
+}
+
+fn find_list<'a>(
+    lists: &'a mut PbShoppingListsResponse,
+    list_id: &str,
+) -> Result<&'a mut crate::protobuf::anylist::PbShoppingList> {
+    lists
+        .new_lists
+        .iter_mut()
+        .find(|l| l.identifier == list_id)
+        .ok_or_else(|| not_found("list", list_id))
+}
+
+fn find_list_response<'a>(
+    lists: &'a mut PbShoppingListsResponse,
+    list_id: &str,
+) -> &'a mut PbListResponse {
+    let index = match lists
+        .list_responses
+        .iter()
+        .position(|r| r.list_id.as_deref() == Some(list_id))
+    {
+        Some(index) => index,
+        None => {
+            lists.list_responses.push(PbListResponse {
+                list_id: Some(list_id.to_string()),
+                ..Default::default()
+            });
+            lists.list_responses.len() - 1
+        }
+    };
+    &mut lists.list_responses[index]
+}
+
+fn handler_id(metadata: &Option<crate::protobuf::anylist::PbOperationMetadata>) -> String {
+    metadata
+        .as_ref()
+        .and_then(|m| m.handler_id.clone())
+        .unwrap_or_default()
+}
+
+fn text_field(form: &MultipartForm, name: &str) -> Option<String> {
+    form.part(name)
+        .map(|p| String::from_utf8_lossy(p.data()).into_owned())
+}
+
+fn decode<M: Message + Default>(form: &MultipartForm) -> Result<M> {
+    let part = form
+        .part("operations")
+        .ok_or_else(|| AnyListError::InvalidResponse("missing operations field".to_string()))?;
+    Ok(M::decode(part.data())?)
+}
+
+fn encode<M: Message>(message: &M) -> Vec<u8> {
+    message.encode_to_vec()
+}
+
+fn required<T>(value: Option<T>, field: &str) -> Result<T> {
+    value.ok_or_else(|| AnyListError::InvalidResponse(format!("operation is missing {}", field)))
+}
+
+fn not_found(kind: &str, id: &str) -> AnyListError {
+    AnyListError::NotFound(format!("{} {} does not exist", kind, id))
+}
+
+fn unsupported(handler: &str) -> AnyListError {
+    AnyListError::Other(format!(
+        "fake backend does not support handler '{}'",
+        handler
+    ))
+}
+
+fn respond(status: u16, body: Vec<u8>) -> TransportResponse {
+    TransportResponse {
+        status,
+        headers: HeaderMap::new(),
+        body,
+    }
+}
@@ -0,0 +1,47 @@
+//! Test support: an in-process fake of the AnyList service.
+//!
+//! Only available with the `testing` cargo feature:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! anylist_rs = { version = "*", features = ["testing"] }
+//! ```
+//!
+//! [`FakeAnyList`] is a [`Transport`](crate::transport::Transport) that
+//! decodes the operations the client sends, applies them to an in-memory
+//! `PbUserDataResponse`, and answers `data/user-data/get` from that state, so
+//! the whole [`AnyListClient`](crate::AnyListClient) API can be exercised
+//! end to end without network access.
+//!
+//! Every change gets a new timestamp, and `data/user-data/get` requests that
+//! carry the client's timestamps are answered with a delta, so incremental
+//! syncs (see [`crate::delta`]) behave as they do against the real service.
+//!
+//! [`FakeRealtimeServer`] is the realtime counterpart: a local WebSocket
+//! server speaking the listener protocol, for testing
+//! [`RealtimeSync`](crate::RealtimeSync).
+//!
+//! # Example
+//!
+//! ```
+//! use anylist_rs::testing::FakeAnyList;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> anylist_rs::Result<()> {
+//! let fake = FakeAnyList::new();
+//! let client = fake.client();
+//!
+//! let list = client.create_list("Groceries").await?;
+//! client.add_item(list.id(), "Milk").await?;
+//!
+//! let list = client.get_list_by_id(list.id()).await?;
+//! assert_eq!(list.items()[0].name(), "Milk");
+//! # Ok(())
+//! # }
+//! ```
+
+mod backend;
+mod realtime;
+
+pub use backend::FakeAnyList;
+pub use realtime::FakeRealtimeServer;
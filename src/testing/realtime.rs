@@ -0,0 +1,296 @@
+use crate::testing::FakeAnyList;
+use futures_util::{SinkExt, StreamExt};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+
+const HEARTBEAT: &str = "--heartbeat--";
+
+/// Close code the listener sends when the access token is no longer valid
+const TOKEN_EXPIRED: u16 = 4010;
+
+/// A local WebSocket server speaking the AnyList listener protocol, for
+/// exercising [`RealtimeSync`](crate::RealtimeSync) end to end.
+///
+/// It answers every `--heartbeat--` with one of its own, checks the
+/// `access_token` of each connection against the [`FakeAnyList`] it was
+/// started for (closing stale ones with code 4010, as the real listener
+/// does), and lets a test push messages, close connections with any code or
+/// drop them without a close frame.
+///
+/// Works with tokio's paused clock: heartbeats and reconnection delays
+/// elapse as soon as the test is waiting on the sync.
+///
+/// # Example
+///
+/// ```
+/// use anylist_rs::testing::{FakeAnyList, FakeRealtimeServer};
+/// use anylist_rs::{RealtimeConfig, RealtimeSync, SyncEvent};
+/// use std::sync::Arc;
+///
+/// # #[tokio::main]
+/// # async fn main() -> anylist_rs::Result<()> {
+/// let fake = FakeAnyList::new();
+/// let server = FakeRealtimeServer::start(&fake).await;
+/// let client = fake
+///     .client_builder()
+///     .websocket_url(server.url())
+///     .from_tokens(fake.tokens())
+///     .await?;
+///
+/// let mut sync = RealtimeSync::without_callback(Arc::new(client), RealtimeConfig::default());
+/// let mut events = sync.subscribe();
+/// sync.connect().await?;
+///
+/// server.send("refresh-shopping-lists");
+/// while events.recv().await.unwrap() != SyncEvent::ShoppingListsChanged {}
+/// # sync.disconnect().await
+/// # }
+/// ```
+pub struct FakeRealtimeServer {
+    shared: Arc<ServerShared>,
+    url: String,
+    accept_task: JoinHandle<()>,
+}
+
+struct ServerShared {
+    backend: FakeAnyList,
+    /// Every connection, by the order they were accepted. Commands to
+    /// closed ones are ignored.
+    connections: Mutex<Vec<mpsc::UnboundedSender<Command>>>,
+    /// Number of handshakes completed so far
+    accepted: watch::Sender<usize>,
+    /// Access token of every handshake, in order
+    access_tokens: Mutex<Vec<String>>,
+    heartbeats_received: Mutex<usize>,
+    answer_heartbeats: AtomicBool,
+    refuse_connections: AtomicBool,
+}
+
+/// Instruction from the test to one connection
+enum Command {
+    Send(Message),
+    /// Close with a close frame carrying this code
+    Close(u16),
+    /// Drop the socket without a close frame
+    Drop,
+}
+
+impl FakeRealtimeServer {
+    /// Listen on a free local port, accepting the access tokens `backend`
+    /// currently accepts
+    pub async fn start(backend: &FakeAnyList) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("binding a local port cannot fail");
+        let url = format!(
+            "ws://{}/data/add-user-listener",
+            listener
+                .local_addr()
+                .expect("a bound listener has an address")
+        );
+
+        let shared = Arc::new(ServerShared {
+            backend: backend.clone(),
+            connections: Mutex::new(Vec::new()),
+            accepted: watch::channel(0).0,
+            access_tokens: Mutex::new(Vec::new()),
+            heartbeats_received: Mutex::new(0),
+            answer_heartbeats: AtomicBool::new(true),
+            refuse_connections: AtomicBool::new(false),
+        });
+
+        let accept_task = tokio::spawn({
+            let shared = Arc::clone(&shared);
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    if shared.refuse_connections.load(Ordering::SeqCst) {
+                        // Hang up before the handshake
+                        continue;
+                    }
+                    tokio::spawn(serve(Arc::clone(&shared), stream));
+                }
+            }
+        });
+
+        Self {
+            shared,
+            url,
+            accept_task,
+        }
+    }
+
+    /// URL to pass to
+    /// [`AnyListClientBuilder::websocket_url`](crate::AnyListClientBuilder::websocket_url)
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// Send a text message, such as `refresh-shopping-lists`, to every open
+    /// connection
+    pub fn send(&self, message: &str) {
+        self.command(|| Command::Send(Message::Text(message.to_string())));
+    }
+
+    /// Send a binary frame to every open connection
+    pub fn send_binary(&self, data: &[u8]) {
+        self.command(|| Command::Send(Message::Binary(data.to_vec())));
+    }
+
+    /// Close every open connection with a close frame carrying `code`
+    pub fn close_connections(&self, code: u16) {
+        self.command(|| Command::Close(code));
+    }
+
+    /// Drop every open connection without a close frame, as a network
+    /// failure would
+    pub fn drop_connections(&self) {
+        self.command(|| Command::Drop);
+    }
+
+    /// Drop the `index`th connection accepted (counting from 0) without a
+    /// close frame, if it is still open
+    pub fn drop_connection(&self, index: usize) {
+        if let Some(connection) = self.shared.connections.lock().unwrap().get(index) {
+            let _ = connection.send(Command::Drop);
+        }
+    }
+
+    /// Whether to answer heartbeats (the default). A server that stops
+    /// answering looks dead to the client.
+    pub fn answer_heartbeats(&self, answer: bool) {
+        self.shared
+            .answer_heartbeats
+            .store(answer, Ordering::SeqCst);
+    }
+
+    /// Hang up on new connections before the WebSocket handshake
+    pub fn refuse_connections(&self, refuse: bool) {
+        self.shared
+            .refuse_connections
+            .store(refuse, Ordering::SeqCst);
+    }
+
+    /// Number of connections accepted so far, including ones closed since
+    pub fn connection_count(&self) -> usize {
+        *self.shared.accepted.borrow()
+    }
+
+    /// Wait until `count` connections have been accepted in total
+    pub async fn wait_for_connections(&self, count: usize) {
+        let mut accepted = self.shared.accepted.subscribe();
+        let _ = accepted.wait_for(|accepted| *accepted >= count).await;
+    }
+
+    /// Access token each connection was opened with, in order
+    pub fn access_tokens(&self) -> Vec<String> {
+        self.shared.access_tokens.lock().unwrap().clone()
+    }
+
+    /// Number of heartbeats received from clients
+    pub fn heartbeats_received(&self) -> usize {
+        *self.shared.heartbeats_received.lock().unwrap()
+    }
+
+    fn command(&self, command: impl Fn() -> Command) {
+        self.shared
+            .connections
+            .lock()
+            .unwrap()
+            .iter()
+            .for_each(|connection| {
+                let _ = connection.send(command());
+            });
+    }
+}
+
+impl Drop for FakeRealtimeServer {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        self.drop_connections();
+    }
+}
+
+/// Run one connection until it closes or the test drops it
+async fn serve(shared: Arc<ServerShared>, stream: TcpStream) {
+    let query = Arc::new(Mutex::new(String::new()));
+    // The error type is tungstenite's, not ours
+    #[allow(clippy::result_large_err)]
+    let callback = {
+        let query = Arc::clone(&query);
+        move |request: &Request, response: Response| {
+            *query.lock().unwrap() = request.uri().query().unwrap_or_default().to_string();
+            Ok(response)
+        }
+    };
+    let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, callback).await else {
+        return;
+    };
+
+    let access_token = access_token(&query.lock().unwrap());
+    shared
+        .access_tokens
+        .lock()
+        .unwrap()
+        .push(access_token.clone());
+    let (commands, mut incoming_commands) = mpsc::unbounded_channel();
+    shared.connections.lock().unwrap().push(commands);
+    shared.accepted.send_modify(|accepted| *accepted += 1);
+
+    if access_token != shared.backend.tokens().access_token() {
+        let _ = socket.close(Some(close_frame(TOKEN_EXPIRED))).await;
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            command = incoming_commands.recv() => match command {
+                Some(Command::Send(message)) => {
+                    if socket.send(message).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Command::Close(code)) => {
+                    let _ = socket.close(Some(close_frame(code))).await;
+                    return;
+                }
+                Some(Command::Drop) | None => return,
+            },
+            message = socket.next() => match message {
+                Some(Ok(Message::Text(text))) if text == HEARTBEAT => {
+                    *shared.heartbeats_received.lock().unwrap() += 1;
+                    if shared.answer_heartbeats.load(Ordering::SeqCst)
+                        && socket.send(Message::Text(HEARTBEAT.to_string())).await.is_err()
+                    {
+                        return;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+fn close_frame(code: u16) -> CloseFrame<'static> {
+    CloseFrame {
+        code: CloseCode::from(code),
+        reason: "".into(),
+    }
+}
+
+/// The `access_token` parameter of a listener URL's query string
+fn access_token(query: &str) -> String {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("access_token="))
+        .and_then(|token| urlencoding::decode(token).ok())
+        .map(|token| token.into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,305 @@
+//! Persisting a session's tokens across restarts.
+//!
+//! A refresh hands out a new refresh token and invalidates the old one, so
+//! tokens saved once after login go stale the first time the client
+//! refreshes. A client built with
+//! [`AnyListClientBuilder::token_store`](crate::AnyListClientBuilder::token_store)
+//! writes its tokens to the store after login and after every refresh, so
+//! the store always holds a working session.
+//!
+//! Two stores are included:
+//!
+//! - [`JsonFileTokenStore`] writes [`SavedTokens`] as plain JSON.
+//! - [`EncryptedFileTokenStore`] encrypts them with a key derived from a
+//!   passphrase (PBKDF2-HMAC-SHA256, then XChaCha20-Poly1305).
+//!
+//! Both write to a temporary file and move it into place, and on Unix make
+//! the file readable by its owner only. Implement [`TokenStore`] to keep
+//! tokens somewhere else, such as the system keychain.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anylist_rs::{AnyListClient, JsonFileTokenStore};
+//!
+//! # async fn example() -> anylist_rs::Result<()> {
+//! let builder = AnyListClient::builder().token_store(JsonFileTokenStore::new("session.json"));
+//!
+//! let client = match builder.clone().from_stored_tokens().await? {
+//!     Some(client) => client,
+//!     None => builder.login("user@example.com", "password").await?,
+//! };
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::SavedTokens;
+use crate::error::{AnyListError, Result};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use sha2::Sha256;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Somewhere to keep a client's tokens between runs
+pub trait TokenStore: Send + Sync {
+    /// The saved tokens, or `None` if none have been saved
+    fn load(&self) -> Result<Option<SavedTokens>>;
+
+    /// Replace the saved tokens
+    fn save(&self, tokens: &SavedTokens) -> Result<()>;
+
+    /// Forget the saved tokens
+    fn clear(&self) -> Result<()>;
+}
+
+/// Stores tokens as JSON in a file
+#[derive(Debug, Clone)]
+pub struct JsonFileTokenStore {
+    path: PathBuf,
+}
+
+impl JsonFileTokenStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl TokenStore for JsonFileTokenStore {
+    fn load(&self) -> Result<Option<SavedTokens>> {
+        let Some(bytes) = read_if_exists(&self.path)? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&bytes).map(Some).map_err(|e| {
+            AnyListError::Other(format!("Invalid token file {}: {}", self.path.display(), e))
+        })
+    }
+
+    fn save(&self, tokens: &SavedTokens) -> Result<()> {
+        let json = serde_json::to_vec_pretty(tokens)
+            .map_err(|e| AnyListError::Other(format!("Failed to encode tokens: {}", e)))?;
+        write_private(&self.path, &json)
+    }
+
+    fn clear(&self) -> Result<()> {
+        remove_if_exists(&self.path)
+    }
+}
+
+/// Identifies an encrypted token file, and its format version
+const MAGIC: &[u8; 5] = b"ALTS1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// Stores tokens in a file encrypted with a passphrase.
+///
+/// The file holds a random salt, a random nonce and the JSON tokens
+/// encrypted with XChaCha20-Poly1305 under a key derived from the
+/// passphrase and salt with PBKDF2-HMAC-SHA256. Deriving the key is
+/// deliberately slow, so it is done once and reused while the salt stays
+/// the same.
+pub struct EncryptedFileTokenStore {
+    path: PathBuf,
+    passphrase: String,
+    rounds: u32,
+    /// The salt in use and the key derived from it
+    key: Mutex<Option<([u8; SALT_LEN], Key)>>,
+}
+
+impl EncryptedFileTokenStore {
+    pub fn new(path: impl Into<PathBuf>, passphrase: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            passphrase: passphrase.into(),
+            rounds: PBKDF2_ROUNDS,
+            key: Mutex::new(None),
+        }
+    }
+
+    /// Derive keys with fewer rounds, so tests don't spend seconds on it
+    #[cfg(test)]
+    fn with_rounds(mut self, rounds: u32) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    fn cipher(&self, salt: &[u8; SALT_LEN]) -> XChaCha20Poly1305 {
+        if let Some((cached_salt, key)) = self.key.lock().unwrap().as_ref() {
+            if cached_salt == salt {
+                return XChaCha20Poly1305::new(key);
+            }
+        }
+
+        // Derived without the lock held, so other callers aren't stuck
+        // behind it
+        let mut key = Key::default();
+        pbkdf2::pbkdf2_hmac::<Sha256>(self.passphrase.as_bytes(), salt, self.rounds, &mut key);
+        *self.key.lock().unwrap() = Some((*salt, key));
+        XChaCha20Poly1305::new(&key)
+    }
+
+    /// The salt of the last key derived, or a new one
+    fn salt(&self) -> [u8; SALT_LEN] {
+        if let Some((salt, _)) = self.key.lock().unwrap().as_ref() {
+            return *salt;
+        }
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        salt
+    }
+
+    fn invalid(&self, reason: &str) -> AnyListError {
+        AnyListError::Other(format!(
+            "Invalid token file {}: {}",
+            self.path.display(),
+            reason
+        ))
+    }
+}
+
+impl std::fmt::Debug for EncryptedFileTokenStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedFileTokenStore")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TokenStore for EncryptedFileTokenStore {
+    fn load(&self) -> Result<Option<SavedTokens>> {
+        let Some(bytes) = read_if_exists(&self.path)? else {
+            return Ok(None);
+        };
+        let rest = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| self.invalid("not an encrypted token file"))?;
+        if rest.len() < SALT_LEN + NONCE_LEN {
+            return Err(self.invalid("truncated"));
+        }
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let salt: [u8; SALT_LEN] = salt.try_into().expect("split at SALT_LEN");
+        let json = self
+            .cipher(&salt)
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| self.invalid("wrong passphrase or corrupted"))?;
+        serde_json::from_slice(&json)
+            .map(Some)
+            .map_err(|e| self.invalid(&e.to_string()))
+    }
+
+    fn save(&self, tokens: &SavedTokens) -> Result<()> {
+        let json = serde_json::to_vec(tokens)
+            .map_err(|e| AnyListError::Other(format!("Failed to encode tokens: {}", e)))?;
+        let salt = self.salt();
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher(&salt)
+            .encrypt(&nonce, json.as_slice())
+            .map_err(|_| AnyListError::Other("Failed to encrypt tokens".to_string()))?;
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&salt);
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+        write_private(&self.path, &bytes)
+    }
+
+    fn clear(&self) -> Result<()> {
+        remove_if_exists(&self.path)
+    }
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(AnyListError::Other(format!(
+            "Failed to read token file {}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AnyListError::Other(format!(
+            "Failed to remove token file {}: {}",
+            path.display(),
+            e
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Write `bytes` to a temporary file only the owner can read, then move it
+/// into place, so a crash never leaves a half-written file behind
+fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(&tmp)
+        .and_then(|mut file| file.write_all(bytes))
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| {
+            AnyListError::Other(format!(
+                "Failed to write token file {}: {}",
+                path.display(),
+                e
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("anylist_rs-{}-{}", name, std::process::id()))
+    }
+
+    fn tokens() -> SavedTokens {
+        SavedTokens::new("access", "refresh", "user-1", true)
+    }
+
+    #[test]
+    fn test_json_store_round_trip() {
+        let store = JsonFileTokenStore::new(path("tokens.json"));
+        assert_eq!(store.load().unwrap(), None);
+
+        store.save(&tokens()).unwrap();
+        assert_eq!(store.load().unwrap(), Some(tokens()));
+
+        store.clear().unwrap();
+        assert_eq!(store.load().unwrap(), None);
+        store.clear().unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_store_needs_the_passphrase() {
+        let path = path("tokens.enc");
+        let open = |passphrase| EncryptedFileTokenStore::new(&path, passphrase).with_rounds(1000);
+        let store = open("correct horse");
+        store.save(&tokens()).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&bytes).contains("refresh"));
+        assert_eq!(open("correct horse").load().unwrap(), Some(tokens()));
+        assert!(open("wrong").load().is_err());
+
+        store.clear().unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+//! Pluggable HTTP transport.
+//!
+//! Every request the client makes (REST calls, auth, photo uploads and CDN
+//! downloads) goes through a [`Transport`]. The default is
+//! [`ReqwestTransport`]; supply your own with
+//! [`AnyListClientBuilder::transport`](crate::AnyListClientBuilder::transport)
+//! to record traffic, fake the server in tests, or add middleware such as a
+//! proxy, mTLS or custom DNS.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use anylist_rs::transport::{
+//!     ReqwestTransport, Transport, TransportRequest, TransportResponse,
+//! };
+//! use anylist_rs::{AnyListClient, Result, SavedTokens};
+//! use futures_util::future::BoxFuture;
+//!
+//! /// Logs every request before handing it to reqwest
+//! struct LoggingTransport(ReqwestTransport);
+//!
+//! impl Transport for LoggingTransport {
+//!     fn send(&self, request: TransportRequest) -> BoxFuture<'_, Result<TransportResponse>> {
+//!         println!("{} {}", request.method, request.url);
+//!         self.0.send(request)
+//!     }
+//! }
+//!
+//! # async fn example(tokens: SavedTokens) -> Result<()> {
+//! let client = AnyListClient::builder()
+//!     .transport(LoggingTransport(ReqwestTransport::new()))
+//!     .from_tokens(tokens)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{AnyListError, Result};
+use futures_util::future::BoxFuture;
+use reqwest::header::HeaderMap;
+
+pub use reqwest::Method;
+
+/// One part of a `multipart/form-data` body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipartPart {
+    pub(crate) name: String,
+    pub(crate) data: Vec<u8>,
+    pub(crate) filename: Option<String>,
+    pub(crate) mime_type: Option<String>,
+}
+
+impl MultipartPart {
+    /// Form field name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Raw part contents
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Filename sent in the part's `Content-Disposition`, if any
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Part `Content-Type`, if any
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+}
+
+/// A `multipart/form-data` body.
+///
+/// Unlike `reqwest::multipart::Form`, this is a plain description of the
+/// parts, so it can be inspected, cloned and re-sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MultipartForm {
+    pub(crate) parts: Vec<MultipartPart>,
+}
+
+impl MultipartForm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a text field
+    pub fn text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.bytes(name, value.into().into_bytes())
+    }
+
+    /// Add a binary field with no filename or content type
+    pub fn bytes(mut self, name: impl Into<String>, data: Vec<u8>) -> Self {
+        self.parts.push(MultipartPart {
+            name: name.into(),
+            data,
+            filename: None,
+            mime_type: None,
+        });
+        self
+    }
+
+    /// Add a file field with a filename and content type
+    pub fn file(
+        mut self,
+        name: impl Into<String>,
+        data: Vec<u8>,
+        filename: impl Into<String>,
+        mime_type: impl Into<String>,
+    ) -> Self {
+        self.parts.push(MultipartPart {
+            name: name.into(),
+            data,
+            filename: Some(filename.into()),
+            mime_type: Some(mime_type.into()),
+        });
+        self
+    }
+
+    pub fn parts(&self) -> &[MultipartPart] {
+        &self.parts
+    }
+
+    /// Get the first part with the given field name
+    pub fn part(&self, name: &str) -> Option<&MultipartPart> {
+        self.parts.iter().find(|p| p.name == name)
+    }
+}
+
+/// An HTTP request handed to a [`Transport`]
+#[derive(Debug, Clone)]
+pub struct TransportRequest {
+    pub method: Method,
+    /// Absolute URL
+    pub url: String,
+    pub headers: HeaderMap,
+    /// `multipart/form-data` body, or `None` for no body
+    pub body: Option<MultipartForm>,
+}
+
+/// An HTTP response returned by a [`Transport`]
+#[derive(Debug, Clone)]
+pub struct TransportResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl TransportResponse {
+    /// Whether the status is 2xx
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends HTTP requests on behalf of an [`AnyListClient`](crate::AnyListClient).
+///
+/// Implementations should only return `Err` when no response was received
+/// (connection failures, timeouts, ...); non-2xx responses are returned as
+/// `Ok` and interpreted by the client.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: TransportRequest) -> BoxFuture<'_, Result<TransportResponse>>;
+}
+
+/// The default [`Transport`], backed by a `reqwest::Client`
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use a preconfigured `reqwest::Client` (proxies, timeouts, TLS settings, ...)
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: TransportRequest) -> BoxFuture<'_, Result<TransportResponse>> {
+        Box::pin(async move {
+            let mut builder = self
+                .client
+                .request(request.method, &request.url)
+                .headers(request.headers);
+
+            if let Some(body) = request.body {
+                builder = builder.multipart(to_reqwest_form(body)?);
+            }
+
+            let response = builder.send().await?;
+            let status = response.status().as_u16();
+            let headers = response.headers().clone();
+            let body = response.bytes().await?.to_vec();
+
+            Ok(TransportResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+fn to_reqwest_form(form: MultipartForm) -> Result<reqwest::multipart::Form> {
+    let mut reqwest_form = reqwest::multipart::Form::new();
+    for part in form.parts {
+        let mut reqwest_part = reqwest::multipart::Part::bytes(part.data);
+        if let Some(filename) = part.filename {
+            reqwest_part = reqwest_part.file_name(filename);
+        }
+        if let Some(mime_type) = part.mime_type {
+            reqwest_part = reqwest_part.mime_str(&mime_type).map_err(|e| {
+                AnyListError::Other(format!("Invalid MIME type '{}': {}", mime_type, e))
+            })?;
+        }
+        reqwest_form = reqwest_form.part(part.name, reqwest_part);
+    }
+    Ok(reqwest_form)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multipart_form_keeps_parts_in_order() {
+        let form = MultipartForm::new().text("filename", "abc.jpg").file(
+            "photo",
+            vec![1, 2, 3],
+            "pasta.jpg",
+            "image/jpeg",
+        );
+
+        assert_eq!(form.parts().len(), 2);
+        assert_eq!(form.parts()[0].name(), "filename");
+        assert_eq!(form.parts()[0].data(), b"abc.jpg");
+        assert_eq!(form.parts()[0].filename(), None);
+
+        let photo = form.part("photo").unwrap();
+        assert_eq!(photo.data(), &[1, 2, 3]);
+        assert_eq!(photo.filename(), Some("pasta.jpg"));
+        assert_eq!(photo.mime_type(), Some("image/jpeg"));
+    }
+
+    #[test]
+    fn test_invalid_mime_type_is_rejected() {
+        let form = MultipartForm::new().file("photo", vec![], "x", "not a mime type");
+        assert!(to_reqwest_form(form).is_err());
+    }
+}
@@ -1,5 +1,9 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+
 use uuid::Uuid;
 
 use crate::error::{AnyListError, Result};
@@ -22,3 +26,23 @@ pub fn current_timestamp() -> f64 {
         .unwrap()
         .as_secs_f64()
 }
+
+/// The `exp` claim of a JWT, or `None` if the token isn't a JWT or has no
+/// expiry. The signature is not checked: this is only used to schedule
+/// refreshes.
+pub(crate) fn jwt_expiry(token: &str) -> Option<DateTime<Utc>> {
+    let payload = token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    DateTime::from_timestamp(claims.get("exp")?.as_i64()?, 0)
+}
+
+/// Path component of an absolute URL, without the host or query string
+pub(crate) fn url_path(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let path = without_scheme
+        .find('/')
+        .map(|i| &without_scheme[i..])
+        .unwrap_or("/");
+    path.split('?').next().unwrap_or(path)
+}
@@ -0,0 +1,461 @@
+//! Compact list sync for low-powered clients.
+//!
+//! `data/user-data/get` returns every section of the user data, recipes and
+//! meal plan included. The watch sync endpoint only covers shopping lists and
+//! what hangs off them (items, stores, store filters, list settings,
+//! categories and folders), keyed on a single logical timestamp: a client
+//! sends the timestamp of its last sync and gets back what changed since.
+//!
+//! The server splits its `PBWatchSyncResponse` into parts, wrapped in a
+//! `PBWatchSyncMultipartResponse`.
+//! [`WatchSyncDelta::from_multipart_response`] puts them back together,
+//! checks the result against the `fullResponseHash` and decodes it. The
+//! hash is taken to be a hex SHA-256 of the joined parts; that is an
+//! assumption, not something the protobuf definitions say, so a response
+//! hashed some other way is rejected as [`AnyListError::InvalidResponse`].
+//!
+//! Only the response format comes from the protobuf definitions. The
+//! request's endpoint and form field are an unconfirmed guess, so
+//! `AnyListClient::watch_sync`, which sends it, is only built with the
+//! `unconfirmed-watch-sync` feature. The fake backend in `crate::testing`
+//! mirrors that guess rather than the real service.
+
+use crate::categories::{Category, CategoryGroup};
+#[cfg(feature = "unconfirmed-watch-sync")]
+use crate::client::AnyListClient;
+use crate::error::{AnyListError, Result};
+use crate::lists::{transform_api_list, transform_api_list_item, List, ListItem};
+use crate::protobuf::anylist::{
+    PbListCategory, PbListFolder, PbListSettings, PbWatchSyncMultipartResponse, PbWatchSyncResponse,
+};
+use crate::stores::{Store, StoreFilter};
+use prost::Message;
+use sha2::{Digest, Sha256};
+
+/// Endpoint the watch sync request posts to. Assumed, not confirmed against
+/// the real service.
+#[cfg(any(feature = "unconfirmed-watch-sync", feature = "testing"))]
+pub(crate) const WATCH_SYNC_ENDPOINT: &str = "/data/watch-sync";
+
+/// Form field carrying the logical timestamp to sync from. Assumed, not
+/// confirmed against the real service.
+#[cfg(any(feature = "unconfirmed-watch-sync", feature = "testing"))]
+pub(crate) const WATCH_SYNC_TIMESTAMP_FIELD: &str = "logical_timestamp";
+
+/// Most parts a multipart response may have or claim to have. Checked
+/// before the parts are sorted and joined; decoding them is already bounded
+/// by the size of the response.
+const MAX_PARTS: usize = 10_000;
+
+/// Changes to the user's lists since a watch sync logical timestamp
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchSyncDelta {
+    pub(crate) logical_timestamp: u64,
+    pub(crate) is_full_sync: bool,
+    pub(crate) root_folder_id: Option<String>,
+    pub(crate) lists: Vec<List>,
+    pub(crate) deleted_list_ids: Vec<String>,
+    pub(crate) items: Vec<ListItem>,
+    pub(crate) deleted_item_ids: Vec<String>,
+    pub(crate) stores: Vec<Store>,
+    pub(crate) deleted_store_ids: Vec<String>,
+    pub(crate) store_filters: Vec<StoreFilter>,
+    pub(crate) deleted_store_filter_ids: Vec<String>,
+    pub(crate) list_settings: Vec<PbListSettings>,
+    pub(crate) deleted_list_settings_ids: Vec<String>,
+    pub(crate) categories: Vec<Category>,
+    pub(crate) deleted_category_ids: Vec<String>,
+    pub(crate) category_groups: Vec<CategoryGroup>,
+    pub(crate) deleted_category_group_ids: Vec<String>,
+    pub(crate) list_folders: Vec<PbListFolder>,
+    pub(crate) deleted_list_folder_ids: Vec<String>,
+    pub(crate) processed_operation_ids: Vec<String>,
+}
+
+impl WatchSyncDelta {
+    /// Logical timestamp to pass as `since` on the next sync
+    pub fn logical_timestamp(&self) -> u64 {
+        self.logical_timestamp
+    }
+
+    /// Whether this delta replaces everything the client had, rather than
+    /// adding to it
+    pub fn is_full_sync(&self) -> bool {
+        self.is_full_sync
+    }
+
+    pub fn root_folder_id(&self) -> Option<&str> {
+        self.root_folder_id.as_deref()
+    }
+
+    /// New or changed lists. Their items are reported in
+    /// [`items`](Self::items), not on the lists themselves.
+    pub fn lists(&self) -> &[List] {
+        &self.lists
+    }
+
+    pub fn deleted_list_ids(&self) -> &[String] {
+        &self.deleted_list_ids
+    }
+
+    /// New or changed items, across all lists
+    pub fn items(&self) -> &[ListItem] {
+        &self.items
+    }
+
+    pub fn deleted_item_ids(&self) -> &[String] {
+        &self.deleted_item_ids
+    }
+
+    pub fn stores(&self) -> &[Store] {
+        &self.stores
+    }
+
+    pub fn deleted_store_ids(&self) -> &[String] {
+        &self.deleted_store_ids
+    }
+
+    pub fn store_filters(&self) -> &[StoreFilter] {
+        &self.store_filters
+    }
+
+    pub fn deleted_store_filter_ids(&self) -> &[String] {
+        &self.deleted_store_filter_ids
+    }
+
+    pub fn list_settings(&self) -> &[PbListSettings] {
+        &self.list_settings
+    }
+
+    pub fn deleted_list_settings_ids(&self) -> &[String] {
+        &self.deleted_list_settings_ids
+    }
+
+    /// New or changed list categories, in any category group
+    pub fn categories(&self) -> &[Category] {
+        &self.categories
+    }
+
+    pub fn deleted_category_ids(&self) -> &[String] {
+        &self.deleted_category_ids
+    }
+
+    pub fn category_groups(&self) -> &[CategoryGroup] {
+        &self.category_groups
+    }
+
+    pub fn deleted_category_group_ids(&self) -> &[String] {
+        &self.deleted_category_group_ids
+    }
+
+    pub fn list_folders(&self) -> &[PbListFolder] {
+        &self.list_folders
+    }
+
+    pub fn deleted_list_folder_ids(&self) -> &[String] {
+        &self.deleted_list_folder_ids
+    }
+
+    /// IDs of operations the server has processed since the last sync
+    pub fn processed_operation_ids(&self) -> &[String] {
+        &self.processed_operation_ids
+    }
+}
+
+impl From<PbWatchSyncResponse> for WatchSyncDelta {
+    fn from(response: PbWatchSyncResponse) -> Self {
+        WatchSyncDelta {
+            logical_timestamp: response.logical_timestamp.unwrap_or_default(),
+            is_full_sync: response.is_full_sync.unwrap_or(false),
+            root_folder_id: response.root_folder_id,
+            lists: response
+                .shopping_lists
+                .into_iter()
+                .filter_map(transform_api_list)
+                .collect(),
+            deleted_list_ids: response.deleted_shopping_list_ids,
+            items: transform_api_list_item(response.list_items),
+            deleted_item_ids: response.deleted_list_item_ids,
+            stores: response
+                .stores
+                .into_iter()
+                .filter_map(|pb_store| {
+                    pb_store.name.map(|name| Store {
+                        id: pb_store.identifier,
+                        name,
+                        sort_index: pb_store.sort_index.unwrap_or(0),
+                    })
+                })
+                .collect(),
+            deleted_store_ids: response.deleted_stores_ids,
+            store_filters: response
+                .store_filters
+                .into_iter()
+                .filter_map(|pb_filter| {
+                    pb_filter.name.map(|name| StoreFilter {
+                        id: pb_filter.identifier,
+                        name,
+                        store_ids: pb_filter.store_ids,
+                    })
+                })
+                .collect(),
+            deleted_store_filter_ids: response.deleted_store_filter_ids,
+            list_settings: response.list_settings,
+            deleted_list_settings_ids: response.deleted_list_setting_ids,
+            categories: response
+                .list_categories
+                .into_iter()
+                .filter_map(transform_category)
+                .collect(),
+            deleted_category_ids: response.deleted_list_category_ids,
+            category_groups: response
+                .list_category_groups
+                .into_iter()
+                .filter_map(|pb_group| {
+                    Some(CategoryGroup {
+                        id: pb_group.identifier?,
+                        name: pb_group.name.unwrap_or_default(),
+                        categories: pb_group
+                            .categories
+                            .into_iter()
+                            .filter_map(transform_category)
+                            .collect(),
+                    })
+                })
+                .collect(),
+            deleted_category_group_ids: response.deleted_list_category_group_ids,
+            list_folders: response.list_folders,
+            deleted_list_folder_ids: response.deleted_list_folder_ids,
+            processed_operation_ids: response.processed_operation_ids,
+        }
+    }
+}
+
+fn transform_category(pb_category: PbListCategory) -> Option<Category> {
+    Some(Category {
+        id: pb_category.identifier?,
+        name: pb_category.name?,
+        icon: pb_category.icon,
+        sort_index: pb_category.sort_index.unwrap_or(0),
+    })
+}
+
+#[cfg(feature = "unconfirmed-watch-sync")]
+impl AnyListClient {
+    /// Fetch the changes to the user's lists since `since`, the
+    /// [`logical_timestamp`](WatchSyncDelta::logical_timestamp) of an earlier
+    /// sync. Pass `None` to fetch everything.
+    ///
+    /// The request format is an assumption (see the [module docs](self)),
+    /// which is why this needs the `unconfirmed-watch-sync` feature. If the
+    /// server doesn't recognise it this fails with the server's error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use anylist_rs::AnyListClient;
+    /// # async fn example(client: AnyListClient) -> anylist_rs::Result<()> {
+    /// let delta = client.watch_sync(None).await?;
+    /// let mut since = delta.logical_timestamp();
+    ///
+    /// // Later: only what changed
+    /// let delta = client.watch_sync(Some(since)).await?;
+    /// for item in delta.items() {
+    ///     println!("{} changed", item.name());
+    /// }
+    /// since = delta.logical_timestamp();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn watch_sync(&self, since: Option<u64>) -> Result<WatchSyncDelta> {
+        // A logical timestamp of 0 asks for a full sync
+        let since = since.unwrap_or(0).to_string();
+        let bytes = self
+            .post_multipart(
+                WATCH_SYNC_ENDPOINT,
+                WATCH_SYNC_TIMESTAMP_FIELD,
+                since.into_bytes(),
+            )
+            .await?;
+        WatchSyncDelta::from_multipart_response(&bytes)
+    }
+}
+
+impl WatchSyncDelta {
+    /// Decode an encoded `PBWatchSyncMultipartResponse`: put its parts back
+    /// together, check them against the full response hash and decode the
+    /// result
+    pub fn from_multipart_response(bytes: &[u8]) -> Result<Self> {
+        let multipart = PbWatchSyncMultipartResponse::decode(bytes)?;
+        Ok(Self::from(reassemble(multipart)?))
+    }
+}
+
+/// Join the parts of a multipart response in order and decode the result,
+/// after checking it against the full response hash
+pub(crate) fn reassemble(multipart: PbWatchSyncMultipartResponse) -> Result<PbWatchSyncResponse> {
+    let mut parts = multipart.reponse_part;
+
+    let parts_count = parts
+        .iter()
+        .find_map(|part| part.parts_count)
+        .unwrap_or(parts.len() as i32);
+    if parts.len() > MAX_PARTS || usize::try_from(parts_count).ok() != Some(parts.len()) {
+        return Err(AnyListError::InvalidResponse(format!(
+            "Watch sync response has {} parts but claims {}",
+            parts.len(),
+            parts_count
+        )));
+    }
+
+    parts.sort_by_key(|part| part.part_index.unwrap_or_default());
+    let expected_hash = multipart.full_response_hash.or_else(|| {
+        parts
+            .iter()
+            .find_map(|part| part.full_response_hash.clone())
+    });
+    // Sorted, so the indexes must be exactly 0, 1, 2, ...
+    if let Some((expected, part)) = parts
+        .iter()
+        .enumerate()
+        .find(|(i, part)| part.part_index.unwrap_or(0) as i64 != *i as i64)
+    {
+        return Err(AnyListError::InvalidResponse(format!(
+            "Watch sync response has part {} where part {} belongs",
+            part.part_index.unwrap_or(0),
+            expected
+        )));
+    }
+
+    let body: Vec<u8> = parts
+        .into_iter()
+        .flat_map(|part| part.response_part.unwrap_or_default())
+        .collect();
+
+    if let Some(expected) = expected_hash {
+        let actual = response_hash(&body);
+        if !actual.eq_ignore_ascii_case(&expected) {
+            return Err(AnyListError::InvalidResponse(format!(
+                "Watch sync response hash mismatch: expected {}, got {}",
+                expected, actual
+            )));
+        }
+    }
+
+    Ok(PbWatchSyncResponse::decode(body.as_ref())?)
+}
+
+/// Hex-encoded SHA-256 of a reassembled response
+pub(crate) fn response_hash(body: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(body))
+}
+
+/// Split an encoded response into parts of at most `part_size` bytes
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn split(
+    response: &PbWatchSyncResponse,
+    part_size: usize,
+) -> PbWatchSyncMultipartResponse {
+    use crate::protobuf::anylist::PbWatchSyncMultipartResponsePart;
+
+    let body = response.encode_to_vec();
+    let hash = response_hash(&body);
+    let chunks: Vec<&[u8]> = if body.is_empty() {
+        vec![&[]]
+    } else {
+        body.chunks(part_size).collect()
+    };
+    let parts_count = chunks.len() as i32;
+
+    PbWatchSyncMultipartResponse {
+        reponse_part: chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| PbWatchSyncMultipartResponsePart {
+                full_response_hash: Some(hash.clone()),
+                part_index: Some(index as i32),
+                parts_count: Some(parts_count),
+                response_part: Some(chunk.to_vec()),
+            })
+            .collect(),
+        full_response_hash: Some(hash),
+        response_logical_timestamp: response.logical_timestamp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::anylist::{PbListItem, PbShoppingList};
+
+    fn response() -> PbWatchSyncResponse {
+        PbWatchSyncResponse {
+            logical_timestamp: Some(12),
+            is_full_sync: Some(true),
+            shopping_lists: vec![PbShoppingList {
+                identifier: "list-1".to_string(),
+                name: Some("Groceries".to_string()),
+                ..Default::default()
+            }],
+            list_items: (0..20)
+                .map(|i| PbListItem {
+                    identifier: format!("item-{}", i),
+                    list_id: Some("list-1".to_string()),
+                    name: Some(format!("Item {}", i)),
+                    ..Default::default()
+                })
+                .collect(),
+            deleted_list_item_ids: vec!["item-old".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parts_are_reassembled_in_order() {
+        let mut multipart = split(&response(), 32);
+        assert!(multipart.reponse_part.len() > 3);
+        multipart.reponse_part.reverse();
+
+        let delta = WatchSyncDelta::from(reassemble(multipart).unwrap());
+        assert_eq!(delta.logical_timestamp(), 12);
+        assert!(delta.is_full_sync());
+        assert_eq!(delta.lists()[0].name(), "Groceries");
+        assert_eq!(delta.items().len(), 20);
+        assert_eq!(delta.deleted_item_ids(), ["item-old".to_string()]);
+    }
+
+    #[test]
+    fn test_missing_part_is_an_error() {
+        let mut multipart = split(&response(), 32);
+        multipart.reponse_part.remove(1);
+
+        assert!(matches!(
+            reassemble(multipart),
+            Err(AnyListError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_inflated_parts_count_is_an_error() {
+        let mut multipart = split(&response(), 32);
+        for part in &mut multipart.reponse_part {
+            part.parts_count = Some(i32::MAX);
+        }
+
+        assert!(matches!(
+            reassemble(multipart),
+            Err(AnyListError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_hash_mismatch_is_an_error() {
+        let mut multipart = split(&response(), 32);
+        multipart.reponse_part[0].response_part.as_mut().unwrap()[0] ^= 0xff;
+
+        match reassemble(multipart) {
+            Err(AnyListError::InvalidResponse(msg)) => assert!(msg.contains("hash mismatch")),
+            other => panic!("expected InvalidResponse, got {:?}", other),
+        }
+    }
+}
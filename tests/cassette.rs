@@ -0,0 +1,90 @@
+use anylist_rs::cassette::read_cassette;
+use anylist_rs::testing::FakeAnyList;
+use anylist_rs::AnyListClient;
+use std::path::PathBuf;
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("anylist_rs-{}-{}.jsonl", name, std::process::id()))
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle.as_bytes())
+}
+
+#[tokio::test]
+async fn recorded_session_replays_offline() {
+    let path = cassette_path("replay");
+    let fake = FakeAnyList::new();
+
+    let client = fake
+        .client_builder()
+        .record(&path)
+        .login("user@example.com", "password")
+        .await
+        .unwrap();
+    let list = client.create_list("Groceries").await.unwrap();
+    client.add_item(list.id(), "Milk").await.unwrap();
+    let recorded = client.get_lists().await.unwrap();
+
+    let interactions = read_cassette(&path).unwrap();
+    let endpoints: Vec<&str> = interactions.iter().map(|i| i.endpoint()).collect();
+    assert_eq!(
+        endpoints,
+        [
+            "/data/shopping-lists/update",
+            "/data/shopping-lists/update",
+            "/data/user-data/get",
+        ]
+    );
+    assert!(interactions[0].field("operations").is_some());
+
+    let replayed = AnyListClient::builder()
+        .replay(&path)
+        .from_tokens(fake.tokens())
+        .await
+        .unwrap();
+    let list = replayed.create_list("Groceries").await.unwrap();
+    replayed.add_item(list.id(), "Milk").await.unwrap();
+    assert_eq!(replayed.get_lists().await.unwrap(), recorded);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn cassettes_contain_no_credentials() {
+    let path = cassette_path("credentials");
+    let fake = FakeAnyList::new();
+
+    let client = fake
+        .client_builder()
+        .record(&path)
+        .login("user@example.com", "password")
+        .await
+        .unwrap();
+    fake.expire_access_token();
+    client.get_lists().await.unwrap();
+
+    let interactions = read_cassette(&path).unwrap();
+    assert_eq!(interactions.len(), 1);
+    assert_eq!(interactions[0].status(), 200);
+
+    // Request and response bodies are stored hex encoded, so search them
+    // decoded as well as the file itself
+    let contents = std::fs::read_to_string(&path).unwrap();
+    for interaction in &interactions {
+        let bodies = interaction
+            .fields()
+            .iter()
+            .map(|field| field.data())
+            .chain([interaction.response()]);
+        for body in bodies.chain([contents.as_bytes()]) {
+            assert!(!contains(body, "password"));
+            assert!(!contains(body, fake.tokens().access_token()));
+            assert!(!contains(body, fake.tokens().refresh_token()));
+        }
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,877 @@
+use anylist_rs::protobuf::anylist::{PbAccountInfoResponse, PbEmailUserIdPair};
+use anylist_rs::testing::FakeAnyList;
+use anylist_rs::{
+    AnyListError, Ingredient, JsonFileTokenStore, LiveAnyList, LiveChange, RetryPolicy, SyncEvent,
+    TokenStore, UserDataSection, UserDataState,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn lists_and_items_round_trip() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+
+    let list = client.create_list("Groceries").await.unwrap();
+    let milk = client.add_item(list.id(), "Milk").await.unwrap();
+    client
+        .add_item_with_details(list.id(), "Apples", Some("2 lbs"), None, Some("Produce"))
+        .await
+        .unwrap();
+    client.cross_off_item(list.id(), milk.id()).await.unwrap();
+    client
+        .rename_list(list.id(), "Weekly Groceries")
+        .await
+        .unwrap();
+
+    let list = client.get_list_by_name("Weekly Groceries").await.unwrap();
+    assert_eq!(list.items().len(), 2);
+    assert!(list
+        .items()
+        .iter()
+        .any(|i| i.name() == "Milk" && i.is_checked()));
+    assert!(list
+        .items()
+        .iter()
+        .any(|i| i.name() == "Apples" && i.quantity() == Some("2 lbs")));
+
+    client
+        .delete_all_crossed_off_items(list.id())
+        .await
+        .unwrap();
+    let list = client.get_list_by_id(list.id()).await.unwrap();
+    assert_eq!(list.items().len(), 1);
+
+    client.delete_list(list.id()).await.unwrap();
+    assert!(client.get_lists().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn stores_round_trip() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+    let list = client.create_list("Groceries").await.unwrap();
+
+    let store = client.create_store(list.id(), "Costco").await.unwrap();
+    client
+        .update_store(list.id(), store.id(), "Safeway")
+        .await
+        .unwrap();
+    let stores = client.get_stores_for_list(list.id()).await.unwrap();
+    assert_eq!(stores.len(), 1);
+    assert_eq!(stores[0].name(), "Safeway");
+
+    client.delete_store(list.id(), store.id()).await.unwrap();
+    assert!(client
+        .get_stores_for_list(list.id())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn stores_and_store_filters_can_be_deleted_in_a_batch() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+    let list = client.create_list("Groceries").await.unwrap();
+    let costco = client.create_store(list.id(), "Costco").await.unwrap();
+    let safeway = client.create_store(list.id(), "Safeway").await.unwrap();
+    let store_ids = vec![costco.id().to_string(), safeway.id().to_string()];
+    client
+        .update_store_filter(list.id(), "filter-1", "Both", &store_ids)
+        .await
+        .unwrap();
+    client
+        .update_store_filter(list.id(), "filter-2", "Costco", &store_ids[..1])
+        .await
+        .unwrap();
+
+    let filters = client.get_store_filters_for_list(list.id()).await.unwrap();
+    let costco_only = filters.iter().find(|f| f.id() == "filter-2").unwrap();
+
+    let mut batch = client.batch();
+    batch
+        .delete_store(list.id(), costco.id())
+        .update_store_filter(list.id(), "filter-1", "Both", &store_ids[1..])
+        .delete_store_filter(list.id(), costco_only);
+    batch.commit().await.unwrap();
+
+    assert_eq!(
+        client.get_stores_for_list(list.id()).await.unwrap(),
+        vec![safeway.clone()]
+    );
+    let filters = client.get_store_filters_for_list(list.id()).await.unwrap();
+    assert_eq!(filters.len(), 1);
+    assert_eq!(filters[0].id(), "filter-1");
+    assert_eq!(filters[0].store_ids(), &[safeway.id().to_string()]);
+}
+
+#[tokio::test]
+async fn recipes_and_collections_round_trip() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+
+    let recipe = client
+        .create_recipe(
+            "Bread",
+            vec![Ingredient::new("Flour").quantity_of("500g")],
+            vec!["Knead".to_string()],
+        )
+        .await
+        .unwrap();
+    let collection = client.create_recipe_collection("Baking").await.unwrap();
+    client
+        .add_recipe_to_collection(collection.id(), recipe.id())
+        .await
+        .unwrap();
+
+    let collections = client.get_recipe_collections().await.unwrap();
+    assert_eq!(collections[0].recipe_ids(), &[recipe.id().to_string()]);
+
+    client.delete_recipe(recipe.id()).await.unwrap();
+    assert!(client.get_recipes().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn meal_plan_events_round_trip() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+
+    let event = client
+        .create_meal_plan_event(fake.calendar_id(), "2026-06-15", None, Some("Pizza"), None)
+        .await
+        .unwrap();
+    let events = client
+        .get_meal_plan_events("2026-06-01", "2026-06-30")
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].title(), Some("Pizza"));
+
+    client
+        .delete_meal_plan_event(fake.calendar_id(), event.id())
+        .await
+        .unwrap();
+    assert!(client
+        .get_meal_plan_events("2026-06-01", "2026-06-30")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn favourites_round_trip() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+
+    let favourite = client.add_favourite("Milk", None).await.unwrap();
+    assert_eq!(client.get_favourites().await.unwrap().len(), 1);
+
+    client
+        .remove_favourite(favourite.list_id(), favourite.id())
+        .await
+        .unwrap();
+    assert!(client.get_favourites().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn photos_round_trip() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+
+    let photo_id = client
+        .upload_photo(vec![0xff, 0xd8, 0xff], "bread.jpg")
+        .await
+        .unwrap();
+    let photo = client.download_photo(&photo_id).await.unwrap();
+    assert_eq!(photo, vec![0xff, 0xd8, 0xff]);
+}
+
+#[tokio::test]
+async fn photo_upload_is_retried_after_refreshing_tokens() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+
+    fake.expire_access_token();
+    let photo_id = client
+        .upload_photo(vec![0xff, 0xd8, 0xff, 0xe0], "toast.jpg")
+        .await
+        .unwrap();
+
+    assert_eq!(client.export_tokens().unwrap(), fake.tokens());
+    let uploads: Vec<_> = fake
+        .requests()
+        .into_iter()
+        .filter(|r| r.url.ends_with("/data/photos/upload"))
+        .collect();
+    assert_eq!(uploads.len(), 2);
+    assert_eq!(uploads[0].body, uploads[1].body);
+    assert_eq!(
+        client.download_photo(&photo_id).await.unwrap(),
+        vec![0xff, 0xd8, 0xff, 0xe0]
+    );
+}
+
+#[tokio::test]
+async fn login_checks_credentials() {
+    let fake = FakeAnyList::new().with_credentials("me@example.com", "hunter2");
+
+    let client = fake
+        .client_builder()
+        .login("me@example.com", "hunter2")
+        .await
+        .unwrap();
+    assert_eq!(client.export_tokens().unwrap().user_id(), fake.user_id());
+
+    let result = fake.client_builder().login("me@example.com", "wrong").await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn login_failures_are_typed() {
+    let fake = FakeAnyList::new();
+
+    let result = fake
+        .client_builder()
+        .login("user@example.com", "wrong")
+        .await;
+    assert!(matches!(result, Err(AnyListError::InvalidCredentials)));
+
+    fake.fail_next(423);
+    let result = fake
+        .client_builder()
+        .login("user@example.com", "password")
+        .await;
+    assert!(matches!(
+        result,
+        Err(AnyListError::AccountLocked { status: 423, .. })
+    ));
+
+    fake.fail_next(429);
+    let result = fake
+        .client_builder()
+        .login("user@example.com", "password")
+        .await;
+    assert!(matches!(result, Err(AnyListError::RateLimited { .. })));
+
+    fake.fail_next(503);
+    let result = fake
+        .client_builder()
+        .login("user@example.com", "password")
+        .await;
+    assert!(matches!(
+        result,
+        Err(AnyListError::ServerError { status: 503, .. })
+    ));
+}
+
+#[tokio::test]
+async fn logout_clears_the_session_and_the_token_store() {
+    let path = std::env::temp_dir().join(format!("anylist_rs-logout-{}.json", std::process::id()));
+    let store = JsonFileTokenStore::new(&path);
+
+    let fake = FakeAnyList::new();
+    let client = fake
+        .client_builder()
+        .token_store(store.clone())
+        .login("user@example.com", "password")
+        .await
+        .unwrap();
+    let tokens = client.export_tokens().unwrap();
+    assert!(store.load().unwrap().is_some());
+
+    client.logout().await.unwrap();
+
+    assert_eq!(store.load().unwrap(), None);
+    assert!(matches!(
+        client.get_lists().await,
+        Err(AnyListError::AuthenticationFailed(_))
+    ));
+    // Revocation isn't supported: the server still honours a saved copy
+    let restored = fake.client_builder().from_tokens(tokens).await.unwrap();
+    restored.refresh_tokens().await.unwrap();
+}
+
+#[tokio::test]
+async fn expired_access_token_is_refreshed() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+    let before = client.export_tokens().unwrap();
+
+    fake.expire_access_token();
+    client.get_lists().await.unwrap();
+
+    assert_ne!(
+        client.export_tokens().unwrap().access_token(),
+        before.access_token()
+    );
+    assert_eq!(client.export_tokens().unwrap(), fake.tokens());
+}
+
+#[tokio::test]
+async fn expiring_access_token_is_refreshed_before_the_request() {
+    // Shorter than the default refresh margin, so it is always due
+    let fake = FakeAnyList::new().with_access_token_lifetime(Duration::from_secs(30));
+    let client = fake.client();
+    let before = client.export_tokens().unwrap();
+    assert!(client.access_token_expires_at().is_some());
+
+    client.get_lists().await.unwrap();
+
+    assert_ne!(client.export_tokens().unwrap(), before);
+    assert_eq!(client.export_tokens().unwrap(), fake.tokens());
+    let refreshes: Vec<bool> = fake
+        .requests()
+        .iter()
+        .map(|r| r.url.ends_with("/auth/token/refresh"))
+        .collect();
+    assert_eq!(refreshes, vec![true, false]);
+}
+
+#[tokio::test]
+async fn failed_early_refresh_falls_back_to_the_current_token() {
+    let fake = FakeAnyList::new().with_access_token_lifetime(Duration::from_secs(30));
+    let client = fake.client();
+    let before = client.export_tokens().unwrap();
+
+    fake.fail_next(503);
+    client.get_lists().await.unwrap();
+
+    assert_eq!(client.export_tokens().unwrap(), before);
+    let refreshes: Vec<bool> = fake
+        .requests()
+        .iter()
+        .map(|r| r.url.ends_with("/auth/token/refresh"))
+        .collect();
+    assert_eq!(refreshes, vec![true, false]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn access_token_is_refreshed_in_the_background() {
+    let fake = FakeAnyList::new().with_access_token_lifetime(Duration::from_secs(600));
+    let client = Arc::new(fake.client());
+    let before = client.export_tokens().unwrap();
+
+    let refresher = client.start_token_refresh();
+    tokio::time::sleep(Duration::from_secs(500)).await;
+    assert_eq!(client.export_tokens().unwrap(), before);
+
+    // Due a minute before the 10 minute lifetime is up
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_ne!(client.export_tokens().unwrap(), before);
+    assert_eq!(client.export_tokens().unwrap(), fake.tokens());
+
+    refresher.abort();
+}
+
+#[tokio::test]
+async fn short_lived_access_token_is_not_refreshed_in_a_loop() {
+    // Shorter than the default refresh margin
+    let fake = FakeAnyList::new().with_access_token_lifetime(Duration::from_secs(30));
+    let client = Arc::new(fake.client());
+
+    // Restored tokens of unknown age are refreshed straight away, the
+    // fresh ones only halfway through their lifetime
+    let refresher = client.start_token_refresh();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    client.get_lists().await.unwrap();
+    client.get_lists().await.unwrap();
+    refresher.abort();
+
+    let refreshes = fake
+        .requests()
+        .iter()
+        .filter(|r| r.url.ends_with("/auth/token/refresh"))
+        .count();
+    assert_eq!(refreshes, 1);
+    assert_eq!(client.export_tokens().unwrap(), fake.tokens());
+}
+
+#[tokio::test]
+async fn refreshed_tokens_are_saved_to_the_token_store() {
+    let path = std::env::temp_dir().join(format!(
+        "anylist_rs-token-store-{}.json",
+        std::process::id()
+    ));
+    let store = JsonFileTokenStore::new(&path);
+    store.clear().unwrap();
+
+    let fake = FakeAnyList::new();
+    let builder = fake.client_builder().token_store(store.clone());
+    assert!(builder
+        .clone()
+        .from_stored_tokens()
+        .await
+        .unwrap()
+        .is_none());
+
+    let client = builder.clone().from_tokens(fake.tokens()).await.unwrap();
+    assert_eq!(store.load().unwrap(), Some(fake.tokens()));
+
+    fake.expire_access_token();
+    client.get_lists().await.unwrap();
+    assert_eq!(store.load().unwrap(), Some(fake.tokens()));
+
+    // A restart picks up the refreshed session
+    let restored = builder.from_stored_tokens().await.unwrap().unwrap();
+    restored.get_lists().await.unwrap();
+
+    store.clear().unwrap();
+}
+
+#[tokio::test]
+async fn account_info_lists_the_family_plan() {
+    let user = |user_id: &str, email: &str| PbEmailUserIdPair {
+        email: Some(email.to_string()),
+        user_id: Some(user_id.to_string()),
+        full_name: None,
+    };
+    let fake = FakeAnyList::new().with_account_info(PbAccountInfoResponse {
+        first_name: Some("Sam".to_string()),
+        last_name: Some("Rivera".to_string()),
+        email: Some("sam@example.com".to_string()),
+        is_premium_user: Some(true),
+        expiration_timestamp_ms: Some(1_900_000_000_000),
+        master_user: Some(user("fake-user", "sam@example.com")),
+        subusers: vec![user("user-2", "alex@example.com")],
+        ..Default::default()
+    });
+
+    let account = fake.client().get_account_info().await.unwrap();
+
+    assert_eq!(account.first_name(), Some("Sam"));
+    assert_eq!(account.last_name(), Some("Rivera"));
+    assert!(account.is_premium_user());
+    assert_eq!(account.expires_at().unwrap().timestamp(), 1_900_000_000);
+    assert_eq!(account.master_user().unwrap().user_id(), fake.user_id());
+    let subusers: Vec<_> = account.subusers().iter().map(|u| u.email()).collect();
+    assert_eq!(subusers, vec![Some("alex@example.com")]);
+}
+
+#[tokio::test]
+async fn changes_made_elsewhere_are_visible() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+    let list = client.create_list("Groceries").await.unwrap();
+
+    fake.update_user_data(|data| {
+        let lists = data.shopping_lists_response.as_mut().unwrap();
+        lists.new_lists[0].name = Some("Renamed on phone".to_string());
+    });
+
+    let list = client.get_list_by_id(list.id()).await.unwrap();
+    assert_eq!(list.name(), "Renamed on phone");
+}
+
+#[tokio::test]
+async fn operations_on_missing_lists_are_rejected() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+
+    let result = client.add_item("no-such-list", "Milk").await;
+    assert!(matches!(
+        result,
+        Err(AnyListError::ClientError { status: 400, .. })
+    ));
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried() {
+    let fake = FakeAnyList::new();
+    let client = fake
+        .client_builder()
+        .retry_policy(RetryPolicy::new().initial_delay(Duration::from_millis(1)))
+        .from_tokens(fake.tokens())
+        .await
+        .unwrap();
+
+    fake.fail_next(503);
+    fake.fail_next(502);
+    let list = client.create_list("Groceries").await.unwrap();
+    assert_eq!(client.get_lists().await.unwrap(), vec![list]);
+
+    fake.fail_next(503);
+    fake.fail_next(503);
+    fake.fail_next(503);
+    let err = client.get_lists().await.unwrap_err();
+    assert_eq!(err.status(), Some(503));
+}
+
+#[tokio::test]
+async fn mutations_return_the_server_outcome() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+    let list = client.create_list("Groceries").await.unwrap();
+
+    let outcome = client.rename_list(list.id(), "Weekly").await.unwrap();
+    assert_eq!(outcome.processed_operation_ids().len(), 1);
+    assert_eq!(
+        outcome.logical_timestamp(list.id()),
+        Some(fake.logical_timestamp(list.id()))
+    );
+}
+
+#[tokio::test]
+async fn silently_dropped_operations_are_reported() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+    let list = client.create_list("Groceries").await.unwrap();
+
+    fake.drop_next_edit();
+    let result = client.rename_list(list.id(), "Weekly").await;
+    assert!(matches!(
+        result,
+        Err(AnyListError::OperationNotProcessed { ref operation_ids }) if operation_ids.len() == 1
+    ));
+    assert_eq!(
+        client.get_list_by_id(list.id()).await.unwrap().name(),
+        "Groceries"
+    );
+}
+
+#[tokio::test]
+async fn batched_operations_are_sent_in_one_request_per_endpoint() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+    let list = client.create_list("Groceries").await.unwrap();
+    let requests_before = fake.requests().len();
+
+    let mut batch = client.batch();
+    let items: Vec<_> = (0..80)
+        .map(|i| batch.add_item(list.id(), &format!("Item {}", i)))
+        .collect();
+    batch.cross_off_item(list.id(), items[0].id());
+    let store = batch.create_store(list.id(), "Costco");
+    let outcome = batch.commit().await.unwrap();
+
+    let requests = fake.requests();
+    assert_eq!(requests.len(), requests_before + 2);
+    assert!(requests[requests_before].url.ends_with("/update"));
+    assert!(requests[requests_before + 1].url.ends_with("/update-v2"));
+    assert_eq!(outcome.processed_operation_ids().len(), 82);
+
+    let list = client.get_list_by_id(list.id()).await.unwrap();
+    assert_eq!(list.items().len(), 80);
+    assert!(list
+        .items()
+        .iter()
+        .any(|i| i.id() == items[0].id() && i.is_checked()));
+    assert_eq!(
+        client.get_stores_for_list(list.id()).await.unwrap(),
+        vec![store]
+    );
+}
+
+#[tokio::test]
+async fn cached_user_data_is_invalidated_by_section() {
+    let fake = FakeAnyList::new();
+    let client = fake
+        .client_builder()
+        .cache_user_data()
+        .from_tokens(fake.tokens())
+        .await
+        .unwrap();
+    let user_data_requests = || {
+        fake.requests()
+            .iter()
+            .filter(|r| r.url.ends_with("/data/user-data/get"))
+            .count()
+    };
+
+    let list = client.create_list("Groceries").await.unwrap();
+    client.get_lists().await.unwrap();
+    client.get_list_by_id(list.id()).await.unwrap();
+    client.get_stores_for_list(list.id()).await.unwrap();
+    assert_eq!(user_data_requests(), 1);
+
+    // Our own mutations invalidate the section they change
+    client.add_item(list.id(), "Milk").await.unwrap();
+    let list = client.get_list_by_id(list.id()).await.unwrap();
+    assert_eq!(list.items().len(), 1);
+    assert_eq!(user_data_requests(), 2);
+
+    fake.update_user_data(|data| {
+        let lists = data.shopping_lists_response.as_mut().unwrap();
+        lists.new_lists[0].name = Some("Renamed on phone".to_string());
+    });
+
+    // Events for other sections leave the lists cached
+    client.apply_sync_event(&SyncEvent::RecipeDataChanged);
+    assert_eq!(
+        client.get_list_by_id(list.id()).await.unwrap().name(),
+        "Groceries"
+    );
+    client.get_recipes().await.unwrap();
+    assert_eq!(user_data_requests(), 3);
+
+    client.apply_sync_event(&SyncEvent::ShoppingListsChanged);
+    assert_eq!(
+        client.get_list_by_id(list.id()).await.unwrap().name(),
+        "Renamed on phone"
+    );
+    assert_eq!(user_data_requests(), 4);
+
+    client.invalidate_user_data(UserDataSection::ShoppingLists);
+    client.get_lists().await.unwrap();
+    assert_eq!(user_data_requests(), 5);
+}
+
+#[tokio::test]
+async fn cached_list_can_be_deleted_right_after_it_is_created() {
+    let fake = FakeAnyList::new();
+    let client = fake
+        .client_builder()
+        .cache_user_data()
+        .from_tokens(fake.tokens())
+        .await
+        .unwrap();
+
+    client.get_lists().await.unwrap();
+    let list = client.create_list("Groceries").await.unwrap();
+    client.delete_list(list.id()).await.unwrap();
+
+    assert!(client.get_lists().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn incremental_sync_matches_a_full_download() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+    let groceries = client.create_list("Groceries").await.unwrap();
+    let hardware = client.create_list("Hardware").await.unwrap();
+
+    let mut state = UserDataState::new();
+    client.sync_user_data(&mut state).await.unwrap();
+    let full = || async { UserDataState::from_data(client.get_user_data().await.unwrap()) };
+    assert_eq!(state, full().await);
+
+    // Nothing changed
+    client.sync_user_data(&mut state).await.unwrap();
+    assert_eq!(state, full().await);
+
+    client.add_item(groceries.id(), "Milk").await.unwrap();
+    client.create_store(groceries.id(), "Costco").await.unwrap();
+    client.delete_list(hardware.id()).await.unwrap();
+    client
+        .create_meal_plan_event(fake.calendar_id(), "2024-01-01", None, Some("Pizza"), None)
+        .await
+        .unwrap();
+    fake.update_user_data(|data| {
+        let lists = data.shopping_lists_response.as_mut().unwrap();
+        lists.new_lists[0].name = Some("Renamed on phone".to_string());
+    });
+
+    client.sync_user_data(&mut state).await.unwrap();
+    assert_eq!(state, full().await);
+    let lists = &state
+        .data()
+        .shopping_lists_response
+        .as_ref()
+        .unwrap()
+        .new_lists;
+    assert_eq!(lists.len(), 1);
+    assert_eq!(lists[0].name.as_deref(), Some("Renamed on phone"));
+    assert_eq!(lists[0].items.len(), 1);
+}
+
+#[tokio::test]
+async fn watch_sync_reassembles_lists_and_items() {
+    let fake = FakeAnyList::new();
+    let client = fake.client();
+
+    let list = client.create_list("Groceries").await.unwrap();
+    for name in ["Milk", "Eggs", "Flour", "Butter"] {
+        client.add_item(list.id(), name).await.unwrap();
+    }
+    client.create_store(list.id(), "Market").await.unwrap();
+
+    let delta = client.watch_sync(None).await.unwrap();
+    assert!(delta.is_full_sync());
+    assert_eq!(delta.lists()[0].name(), "Groceries");
+    assert_eq!(delta.items().len(), 4);
+    assert_eq!(delta.stores()[0].name(), "Market");
+
+    let unchanged = client
+        .watch_sync(Some(delta.logical_timestamp()))
+        .await
+        .unwrap();
+    assert!(!unchanged.is_full_sync());
+    assert!(unchanged.items().is_empty());
+    assert_eq!(unchanged.logical_timestamp(), delta.logical_timestamp());
+
+    client.add_item(list.id(), "Sugar").await.unwrap();
+    let changed = client
+        .watch_sync(Some(delta.logical_timestamp()))
+        .await
+        .unwrap();
+    assert!(changed.logical_timestamp() > delta.logical_timestamp());
+    assert_eq!(changed.items().len(), 5);
+}
+
+#[tokio::test]
+async fn offline_mutations_are_queued_and_replayed_in_order() {
+    let path = std::env::temp_dir().join(format!(
+        "anylist_rs-offline-queue-{}.bin",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let fake = FakeAnyList::new();
+    let builder = fake
+        .client_builder()
+        .retry_policy(RetryPolicy::none())
+        .offline_queue(&path);
+    let client = builder.clone().from_tokens(fake.tokens()).await.unwrap();
+    let list = client.create_list("Groceries").await.unwrap();
+
+    fake.fail_next(503);
+    let milk = client.add_item(list.id(), "Milk").await.unwrap();
+    assert_eq!(client.offline_queue_len(), 1);
+
+    // Still offline: the next change queues up behind the first
+    fake.fail_next(503);
+    client.cross_off_item(list.id(), milk.id()).await.unwrap();
+    assert_eq!(client.offline_queue_len(), 2);
+    assert!(client
+        .get_list_by_id(list.id())
+        .await
+        .unwrap()
+        .items()
+        .is_empty());
+
+    // The queue survives a restart
+    drop(client);
+    let client = builder.from_tokens(fake.tokens()).await.unwrap();
+    assert_eq!(client.offline_queue_len(), 2);
+
+    let report = client.replay_offline_queue().await.unwrap();
+    assert_eq!(report.replayed_operation_ids().len(), 2);
+    assert!(report.failed().is_empty());
+    assert_eq!(report.pending(), 0);
+    let list = client.get_list_by_id(list.id()).await.unwrap();
+    assert!(list.items()[0].is_checked());
+
+    // Rejected operations are dropped and reported
+    fake.fail_next(503);
+    client.add_item(list.id(), "Eggs").await.unwrap();
+    fake.fail_next(400);
+    let report = client.replay_offline_queue().await.unwrap();
+    assert_eq!(report.failed().len(), 1);
+    assert_eq!(report.failed()[0].error().status(), Some(400));
+    assert_eq!(client.offline_queue_len(), 0);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn offline_mutations_stay_queued_when_authentication_fails() {
+    let path = std::env::temp_dir().join(format!(
+        "anylist_rs-offline-auth-{}.bin",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let fake = FakeAnyList::new();
+    let client = fake
+        .client_builder()
+        .retry_policy(RetryPolicy::none())
+        .offline_queue(&path)
+        .from_tokens(fake.tokens())
+        .await
+        .unwrap();
+    let list = client.create_list("Groceries").await.unwrap();
+
+    fake.fail_next(503);
+    client.add_item(list.id(), "Milk").await.unwrap();
+    assert_eq!(client.offline_queue_len(), 1);
+
+    fake.revoke_tokens();
+    let result = client.replay_offline_queue().await;
+    assert!(matches!(result, Err(AnyListError::AuthenticationFailed(_))));
+    assert_eq!(client.offline_queue_len(), 1);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn unreadable_replay_response_does_not_block_the_queue() {
+    let path = std::env::temp_dir().join(format!(
+        "anylist_rs-offline-unreadable-{}.bin",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let fake = FakeAnyList::new();
+    let client = fake
+        .client_builder()
+        .retry_policy(RetryPolicy::none())
+        .offline_queue(&path)
+        .from_tokens(fake.tokens())
+        .await
+        .unwrap();
+    let list = client.create_list("Groceries").await.unwrap();
+
+    // Two endpoints, so two batches
+    fake.fail_next(503);
+    client.add_item(list.id(), "Milk").await.unwrap();
+    fake.fail_next(503);
+    let store = client.create_store(list.id(), "Costco").await.unwrap();
+    assert_eq!(client.offline_queue_len(), 2);
+
+    // Neither a rejection nor a transient failure
+    fake.fail_next(302);
+    let report = client.replay_offline_queue().await.unwrap();
+    assert_eq!(report.failed().len(), 1);
+    assert!(matches!(
+        report.failed()[0].error(),
+        AnyListError::InvalidResponse(_)
+    ));
+    assert_eq!(report.replayed_operation_ids().len(), 1);
+    assert_eq!(report.pending(), 0);
+
+    // The batch behind the unreadable one still went through
+    let list = client.get_list_by_id(list.id()).await.unwrap();
+    assert!(list.items().is_empty());
+    assert_eq!(
+        client.get_stores_for_list(list.id()).await.unwrap(),
+        vec![store]
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn live_list_reports_typed_changes() {
+    let fake = FakeAnyList::new();
+    let client = std::sync::Arc::new(fake.client());
+    let list = client.create_list("Groceries").await.unwrap();
+
+    let live = LiveAnyList::load(client.clone()).await.unwrap();
+    assert_eq!(live.lists().await[0].name(), "Groceries");
+    let mut changes = live.subscribe();
+
+    let milk = client.add_item(list.id(), "Milk").await.unwrap();
+    client.cross_off_item(list.id(), milk.id()).await.unwrap();
+    client
+        .rename_list(list.id(), "Weekly Groceries")
+        .await
+        .unwrap();
+    client
+        .create_meal_plan_event(fake.calendar_id(), "2024-01-01", None, Some("Pizza"), None)
+        .await
+        .unwrap();
+
+    let refreshed = live.refresh().await.unwrap();
+    assert!(matches!(
+        &refreshed[..],
+        [
+            LiveChange::ListRenamed { name, .. },
+            LiveChange::ItemAdded { item, .. },
+            LiveChange::MealPlanEventAdded { .. },
+        ] if name == "Weekly Groceries" && item.name() == "Milk" && item.is_checked()
+    ));
+    assert_eq!(changes.recv().await.unwrap(), refreshed[0]);
+    assert!(live.refresh().await.unwrap().is_empty());
+
+    live.stop().await.unwrap();
+}
@@ -0,0 +1,219 @@
+use anylist_rs::testing::{FakeAnyList, FakeRealtimeServer};
+use anylist_rs::{CloseReason, ConnectionState, RealtimeConfig, RealtimeSync, SyncEvent};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+async fn connect(
+    fake: &FakeAnyList,
+    server: &FakeRealtimeServer,
+    config: RealtimeConfig,
+) -> RealtimeSync {
+    let client = fake
+        .client_builder()
+        .websocket_url(server.url())
+        .from_tokens(fake.tokens())
+        .await
+        .unwrap();
+    let mut sync = RealtimeSync::without_callback(Arc::new(client), config);
+    sync.connect().await.unwrap();
+    sync
+}
+
+async fn wait_for_state(sync: &RealtimeSync, expected: ConnectionState) {
+    sync.watch_state()
+        .wait_for(|state| *state == expected)
+        .await
+        .unwrap();
+}
+
+async fn next_event(events: &mut broadcast::Receiver<SyncEvent>, expected: SyncEvent) {
+    while events.recv().await.unwrap() != expected {}
+}
+
+#[tokio::test(start_paused = true)]
+async fn events_are_dispatched_and_heartbeats_answered() {
+    let fake = FakeAnyList::new();
+    let server = FakeRealtimeServer::start(&fake).await;
+    let mut sync = connect(&fake, &server, RealtimeConfig::default()).await;
+    let mut events = sync.subscribe();
+
+    server.send("refresh-shopping-lists");
+    server.send("refresh-meal-plan-calendar");
+    assert_eq!(
+        events.recv().await.unwrap(),
+        SyncEvent::ShoppingListsChanged
+    );
+    assert_eq!(
+        events.recv().await.unwrap(),
+        SyncEvent::MealPlanCalendarChanged
+    );
+
+    // A minute of answered heartbeats keeps the connection up
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert!(server.heartbeats_received() >= 12);
+    assert_eq!(sync.state().await, ConnectionState::Connected);
+    assert!(sync.health().last_heartbeat().is_some());
+    assert_eq!(server.connection_count(), 1);
+
+    sync.disconnect().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn unrecognised_messages_are_passed_on() {
+    let fake = FakeAnyList::new();
+    let server = FakeRealtimeServer::start(&fake).await;
+    let mut sync = connect(&fake, &server, RealtimeConfig::default()).await;
+    let mut events = sync.subscribe();
+
+    server.send("refresh-pantry-items");
+    server.send_binary(&[1, 2, 3]);
+    assert_eq!(
+        events.recv().await.unwrap(),
+        SyncEvent::Unknown("refresh-pantry-items".to_string())
+    );
+    assert_eq!(
+        events.recv().await.unwrap(),
+        SyncEvent::Binary(vec![1, 2, 3])
+    );
+
+    sync.disconnect().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn dropped_connection_is_reestablished() {
+    let fake = FakeAnyList::new();
+    let server = FakeRealtimeServer::start(&fake).await;
+    let mut sync = connect(&fake, &server, RealtimeConfig::default()).await;
+    let mut events = sync.subscribe();
+
+    server.drop_connections();
+    wait_for_state(&sync, ConnectionState::Reconnecting).await;
+    wait_for_state(&sync, ConnectionState::Connected).await;
+    assert_eq!(server.connection_count(), 2);
+    assert_eq!(sync.health().reconnect_count(), 1);
+
+    // Events arrive over the new connection
+    server.send("refresh-shopping-lists");
+    next_event(&mut events, SyncEvent::ShoppingListsChanged).await;
+
+    sync.disconnect().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn expired_token_is_refreshed_before_reconnecting() {
+    let fake = FakeAnyList::new();
+    let server = FakeRealtimeServer::start(&fake).await;
+    let mut sync = connect(&fake, &server, RealtimeConfig::default()).await;
+    let first_token = fake.tokens().access_token().to_string();
+
+    fake.expire_access_token();
+    server.close_connections(4010);
+    wait_for_state(&sync, ConnectionState::Reconnecting).await;
+    wait_for_state(&sync, ConnectionState::Connected).await;
+
+    let tokens = server.access_tokens();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0], first_token);
+    assert_eq!(tokens[1], fake.tokens().access_token());
+    assert_ne!(tokens[1], first_token);
+    assert_eq!(sync.health().last_close_code(), Some(4010));
+
+    sync.disconnect().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn unanswered_heartbeats_trigger_a_reconnect() {
+    let fake = FakeAnyList::new();
+    let server = FakeRealtimeServer::start(&fake).await;
+    let config = RealtimeConfig::new()
+        .heartbeat_interval(Duration::from_secs(10))
+        .max_missed_heartbeats(2);
+    let mut sync = connect(&fake, &server, config).await;
+
+    server.answer_heartbeats(false);
+    wait_for_state(&sync, ConnectionState::Reconnecting).await;
+    server.answer_heartbeats(true);
+    wait_for_state(&sync, ConnectionState::Connected).await;
+    assert_eq!(server.connection_count(), 2);
+
+    sync.disconnect().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn replaced_connection_cannot_disturb_its_replacement() {
+    let fake = FakeAnyList::new();
+    let server = FakeRealtimeServer::start(&fake).await;
+    let config = RealtimeConfig::new()
+        .heartbeat_interval(Duration::from_secs(10))
+        .max_missed_heartbeats(3);
+    let mut sync = connect(&fake, &server, config).await;
+    let mut events = sync.subscribe();
+
+    // A heartbeat timeout replaces the connection without the server
+    // closing the old one
+    server.answer_heartbeats(false);
+    wait_for_state(&sync, ConnectionState::Reconnecting).await;
+    server.answer_heartbeats(true);
+    wait_for_state(&sync, ConnectionState::Connected).await;
+    assert_eq!(server.connection_count(), 2);
+
+    // Events are not duplicated, and the old socket ending later doesn't
+    // send the healthy connection back to reconnecting
+    server.send("refresh-shopping-lists");
+    server.drop_connection(0);
+    tokio::time::sleep(Duration::from_secs(60)).await;
+
+    let mut received = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        received.push(event);
+    }
+    assert_eq!(
+        received,
+        [
+            SyncEvent::ConnectionStateChanged(ConnectionState::Reconnecting),
+            SyncEvent::ConnectionStateChanged(ConnectionState::Connected),
+            SyncEvent::ShoppingListsChanged,
+        ]
+    );
+    assert_eq!(sync.state().await, ConnectionState::Connected);
+    assert_eq!(server.connection_count(), 2);
+
+    sync.disconnect().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn sync_closes_after_the_last_reconnect_attempt() {
+    let fake = FakeAnyList::new();
+    let server = FakeRealtimeServer::start(&fake).await;
+    let config = RealtimeConfig::new().max_reconnect_attempts(3);
+    let sync = connect(&fake, &server, config).await;
+
+    server.refuse_connections(true);
+    server.drop_connections();
+    wait_for_state(&sync, ConnectionState::Closed).await;
+
+    assert_eq!(
+        sync.close_reason(),
+        Some(CloseReason::ReconnectAttemptsExhausted)
+    );
+    assert!(sync.health().last_error().is_some());
+    assert_eq!(server.connection_count(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn rejected_refresh_token_closes_the_sync() {
+    let fake = FakeAnyList::new();
+    let server = FakeRealtimeServer::start(&fake).await;
+    let sync = connect(&fake, &server, RealtimeConfig::default()).await;
+
+    fake.revoke_tokens();
+    server.close_connections(4010);
+    wait_for_state(&sync, ConnectionState::Closed).await;
+
+    assert!(matches!(
+        sync.close_reason(),
+        Some(CloseReason::AuthenticationFailed(_))
+    ));
+    assert_eq!(server.connection_count(), 1);
+}